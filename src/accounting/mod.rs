@@ -0,0 +1,215 @@
+use std::{collections::BTreeMap, fmt::Display};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    consumer::ConsumerId, error::Result, factory::FactoryId, money::Money, region::RegionId,
+    trader::TraderId,
+};
+
+/// A named account in the books of the world.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Account {
+    /// The cash of a factory.
+    FactoryCash(FactoryId),
+    /// The savings of a household.
+    HouseholdSavings(ConsumerId),
+    /// The cash of a trader.
+    TraderCash(TraderId),
+    /// The money held by the market of a region for open bids and for sales proceeds not yet paid out.
+    MarketEscrow(RegionId),
+    /// Dividends that were not yet distributed to the households.
+    IncomePool,
+    /// The money that existed when the world was created.
+    OpeningBalance,
+    /// The money investors put into new factories and take out of closed ones.
+    Investors,
+    /// The income households receive from outside of the simulated economy.
+    ExternalIncome,
+    /// The holding costs factories pay to the warehouse operators outside of the simulated economy.
+    StorageOperators,
+    /// The transport costs traders pay to the carriers outside of the simulated economy.
+    Carriers,
+}
+
+/// The debits and credits posted to an account.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+struct AccountTotals {
+    debit: Money,
+    credit: Money,
+}
+
+/// Double-entry books of all money in the world.
+///
+/// Every transfer of money is posted as a debit to the receiving account
+/// and a credit of the same amount to the paying account.
+/// Hence the money held by an account is its debits minus its credits,
+/// and the external accounts, through which money enters or leaves the world, carry a credit balance.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Books {
+    #[serde(with = "serde_accounts")]
+    accounts: BTreeMap<Account, AccountTotals>,
+}
+
+impl Account {
+    /// Whether money in this account is held by someone in the world.
+    ///
+    /// The other accounts are the sources and sinks of money outside of the world.
+    pub fn is_internal(&self) -> bool {
+        match self {
+            Account::FactoryCash(_)
+            | Account::HouseholdSavings(_)
+            | Account::TraderCash(_)
+            | Account::MarketEscrow(_)
+            | Account::IncomePool => true,
+            Account::OpeningBalance
+            | Account::Investors
+            | Account::ExternalIncome
+            | Account::StorageOperators
+            | Account::Carriers => false,
+        }
+    }
+}
+
+impl Books {
+    /// Post a transfer of the given amount from one account to another.
+    pub fn transfer(&mut self, from: Account, to: Account, amount: Money) -> Result<()> {
+        if amount == Money::ZERO {
+            return Ok(());
+        }
+
+        let debit = &mut self.accounts.entry(to).or_default().debit;
+        *debit = debit.checked_add(amount)?;
+        let credit = &mut self.accounts.entry(from).or_default().credit;
+        *credit = credit.checked_add(amount)?;
+        Ok(())
+    }
+
+    /// The money held by an internal account according to the books.
+    ///
+    /// Returns `None` if the account was overdrawn, which means that money was booked that does not exist.
+    pub fn balance(&self, account: Account) -> Option<Money> {
+        let totals = self.accounts.get(&account).copied().unwrap_or_default();
+        totals
+            .debit
+            .raw()
+            .checked_sub(totals.credit.raw())
+            .map(Money::from)
+    }
+
+    /// All accounts that were ever posted to.
+    pub fn accounts(&self) -> impl '_ + Iterator<Item = Account> {
+        self.accounts.keys().copied()
+    }
+
+    /// Whether the debits of all accounts add up to their credits.
+    ///
+    /// Transfers always post both sides, so this only fails for books that were altered otherwise,
+    /// such as books loaded from a modified snapshot.
+    pub fn is_balanced(&self) -> Result<bool> {
+        let mut debits = Money::ZERO;
+        let mut credits = Money::ZERO;
+        for totals in self.accounts.values() {
+            debits = debits.checked_add(totals.debit)?;
+            credits = credits.checked_add(totals.credit)?;
+        }
+        Ok(debits == credits)
+    }
+}
+
+impl Display for Account {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Account::FactoryCash(factory_id) => write!(f, "cash of factory {factory_id}"),
+            Account::HouseholdSavings(consumer_id) => {
+                write!(f, "savings of household {consumer_id}")
+            }
+            Account::TraderCash(trader_id) => write!(f, "cash of trader {trader_id}"),
+            Account::MarketEscrow(region_id) => write!(f, "market escrow of region {region_id}"),
+            Account::IncomePool => write!(f, "income pool"),
+            Account::OpeningBalance => write!(f, "opening balance"),
+            Account::Investors => write!(f, "investors"),
+            Account::ExternalIncome => write!(f, "external income"),
+            Account::StorageOperators => write!(f, "storage operators"),
+            Account::Carriers => write!(f, "carriers"),
+        }
+    }
+}
+
+/// Serialise the accounts as a list of accounts and totals, since JSON only supports string keys.
+mod serde_accounts {
+    use std::collections::BTreeMap;
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use super::{Account, AccountTotals};
+
+    pub fn serialize<S: Serializer>(
+        accounts: &BTreeMap<Account, AccountTotals>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(accounts.iter())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<BTreeMap<Account, AccountTotals>, D::Error> {
+        Ok(Vec::<(Account, AccountTotals)>::deserialize(deserializer)?
+            .into_iter()
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{factory::FactoryId, money::Money};
+
+    use super::{Account, Books};
+
+    #[test]
+    fn transfers_debit_the_receiving_and_credit_the_paying_account() {
+        let factory = Account::FactoryCash(FactoryId::from(0));
+        let mut books = Books::default();
+        books
+            .transfer(Account::OpeningBalance, factory, Money::from(100))
+            .unwrap();
+        books
+            .transfer(factory, Account::IncomePool, Money::from(30))
+            .unwrap();
+
+        assert_eq!(books.accounts[&factory].debit, Money::from(100));
+        assert_eq!(books.accounts[&factory].credit, Money::from(30));
+        assert_eq!(books.balance(factory), Some(Money::from(70)));
+        assert_eq!(books.balance(Account::IncomePool), Some(Money::from(30)));
+        // The opening balance is the source of the money, so it carries a credit balance.
+        assert_eq!(books.balance(Account::OpeningBalance), None);
+        assert!(books.is_balanced().unwrap());
+    }
+
+    #[test]
+    fn zero_transfers_are_not_posted() {
+        let mut books = Books::default();
+        books
+            .transfer(Account::Investors, Account::IncomePool, Money::ZERO)
+            .unwrap();
+        assert_eq!(books.accounts().count(), 0);
+        assert_eq!(books.balance(Account::IncomePool), Some(Money::ZERO));
+    }
+
+    #[test]
+    fn overdrawn_accounts_have_no_balance() {
+        let mut books = Books::default();
+        books
+            .transfer(Account::IncomePool, Account::Carriers, Money::from(1))
+            .unwrap();
+        assert_eq!(books.balance(Account::IncomePool), None);
+    }
+
+    #[test]
+    fn books_with_unmatched_debits_are_not_balanced() {
+        let books: Books =
+            serde_json::from_str(r#"{"accounts": [["IncomePool", {"debit": 5, "credit": 0}]]}"#)
+                .unwrap();
+        assert!(!books.is_balanced().unwrap());
+    }
+}
@@ -1,79 +1,305 @@
-use log::debug;
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    market::Market,
-    money::{ApproximateMoney, Money},
-    ware::WareAmount,
+    error::{Error, Result},
+    factory::FactoryId,
+    market::{Buyer, Market},
+    money::Money,
+    region::RegionId,
+    ware::{Ware, WareAmount},
 };
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct ConsumerId(usize);
+
+/// A household that earns an income and spends its savings on a basket of wares.
+///
+/// The household has a constant elasticity of substitution (CES) utility
+/// `U = (Σ aᵢ xᵢ^ρ)^(1/ρ)` with `ρ = (σ - 1) / σ`,
+/// where `aᵢ` is the weight of ware `i` in the basket and `σ` the elasticity of substitution.
+/// Hence it shifts its budget towards the wares that are relatively cheap.
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Consumer {
-    target_ware_amount: WareAmount,
-    target_price: Money,
-    fulfilment: f64,
-    decay: f64,
+    /// The wares the household consumes, with the weight of each ware in its utility.
+    basket: Vec<(Ware, f64)>,
+    elasticity_of_substitution: f64,
+    /// Income from outside of the simulated economy, such as transfers.
+    hourly_income: Money,
+    savings: Money,
+    /// The fraction of its savings the household is willing to spend per hour.
+    propensity_to_consume: f64,
+    /// The lowest wage for which the household works.
+    reservation_wage: Money,
+    employer: Option<FactoryId>,
+    /// The region in which the household lives, shops and works.
+    region: RegionId,
 }
 
 impl Consumer {
-    pub fn new(target_ware_amount: WareAmount, target_price: Money, decay: f64) -> Self {
-        assert!(decay.is_normal() || decay == 0.0);
-        assert!(decay >= 0.0);
-        assert!(decay <= 1.0);
-
-        Self {
-            target_ware_amount,
-            target_price,
-            fulfilment: 1.0,
-            decay,
+    pub fn new(basket: Vec<(Ware, f64)>, elasticity_of_substitution: f64) -> Result<Self> {
+        if basket.is_empty() {
+            return Err(Error::invalid_argument(
+                "basket must contain at least one ware",
+            ));
+        }
+        if !basket
+            .iter()
+            .all(|(_, weight)| weight.is_finite() && *weight > 0.0)
+        {
+            return Err(Error::invalid_argument("basket weights must be positive"));
         }
+        if !(elasticity_of_substitution.is_finite() && elasticity_of_substitution > 0.0) {
+            return Err(Error::invalid_argument(
+                "elasticity of substitution must be positive",
+            ));
+        }
+
+        Ok(Self {
+            basket,
+            elasticity_of_substitution,
+            hourly_income: Money::ZERO,
+            savings: Money::ZERO,
+            propensity_to_consume: 1.0,
+            reservation_wage: Money::ZERO,
+            employer: None,
+            region: RegionId::default(),
+        })
     }
 
-    pub fn consume(&mut self, market: &mut Market) {
-        assert!(self.fulfilment >= 0.0);
-        assert!(self.fulfilment.is_normal() || self.fulfilment == 0.0);
-
-        let raw_demand = self.target_ware_amount.amount() as f64;
-        let mut demand = raw_demand
-            / if self.fulfilment < 1.0 {
-                self.fulfilment * 0.5 + 0.5
-            } else {
-                self.fulfilment
-            };
-        let extra_demand = demand - raw_demand;
-        let mut total_consumption = 0;
-        let mut average_price = ApproximateMoney::ZERO;
-
-        while demand > 0.0 {
-            let Some(price) = market.current_price(self.target_ware_amount.ware()) else {
-                break;
-            };
-
-            let demand_per_item = (price.raw() as f64 / self.target_price.raw() as f64).sqrt();
-            let buy_amount = (demand / demand_per_item).round() as u64;
-            if buy_amount == 0 {
-                break;
-            }
+    pub fn with_hourly_income(mut self, hourly_income: Money) -> Self {
+        self.hourly_income = hourly_income;
+        self
+    }
+
+    pub fn with_savings(mut self, savings: Money) -> Self {
+        self.savings = savings;
+        self
+    }
 
-            let consume_amount = self.target_ware_amount.with_amount(buy_amount);
-            let consumed_amount = market.consume_at_current_price(consume_amount);
-            demand -= demand_per_item * consumed_amount as f64;
-            total_consumption += consumed_amount;
-            average_price += (consumed_amount * price).into();
+    pub fn with_propensity_to_consume(mut self, propensity_to_consume: f64) -> Result<Self> {
+        if !(0.0..=1.0).contains(&propensity_to_consume) {
+            return Err(Error::invalid_argument(
+                "propensity to consume must be between 0.0 and 1.0",
+            ));
         }
+        self.propensity_to_consume = propensity_to_consume;
+        Ok(self)
+    }
+
+    pub fn with_reservation_wage(mut self, reservation_wage: Money) -> Self {
+        self.reservation_wage = reservation_wage;
+        self
+    }
+
+    /// Place the household in the given region.
+    pub fn with_region(mut self, region: RegionId) -> Self {
+        self.region = region;
+        self
+    }
+
+    pub fn region(&self) -> RegionId {
+        self.region
+    }
 
-        let unfulfilled_demand = (demand - extra_demand) / raw_demand;
-        self.fulfilment -= (1.0 - self.decay) * unfulfilled_demand;
-        self.fulfilment -= (self.fulfilment - 1.0) * (1.0 - self.decay);
+    /// The wares the household buys, with their weights.
+    pub fn basket(&self) -> &[(Ware, f64)] {
+        &self.basket
+    }
+
+    pub fn reservation_wage(&self) -> Money {
+        self.reservation_wage
+    }
+
+    pub fn employer(&self) -> Option<FactoryId> {
+        self.employer
+    }
+
+    pub(crate) fn take_job(&mut self, employer: FactoryId) {
+        self.employer = Some(employer);
+    }
+
+    pub(crate) fn leave_job(&mut self) {
+        self.employer = None;
+    }
+
+    pub fn savings(&self) -> Money {
+        self.savings
+    }
+
+    /// Receive the income from outside of the simulated economy and return it.
+    pub(crate) fn earn_income(&mut self) -> Result<Money> {
+        self.earn(self.hourly_income)?;
+        Ok(self.hourly_income)
+    }
+
+    /// Receive wages or dividends from the factories.
+    pub(crate) fn earn(&mut self, income: Money) -> Result<()> {
+        self.savings = self.savings.checked_add(income)?;
+        Ok(())
+    }
+
+    /// The money the household is willing to spend this hour.
+    pub fn budget(&self) -> Money {
+        Money::from((self.savings.raw() as f64 * self.propensity_to_consume) as u64)
+    }
 
-        average_price /= total_consumption;
+    /// Split the budget across the wares of the basket that are offered at the given prices.
+    ///
+    /// Maximising the CES utility under the budget constraint
+    /// yields a budget share for ware `i` proportional to `aᵢ^σ pᵢ^(1-σ)`.
+    fn budget_shares(&self, prices: &[(Ware, Money)]) -> Vec<(Ware, f64)> {
+        let sigma = self.elasticity_of_substitution;
+        let weights: Vec<_> = self
+            .basket
+            .iter()
+            .filter_map(|(ware, weight)| {
+                let (_, price) = prices
+                    .iter()
+                    .find(|(offered_ware, _)| offered_ware == ware)?;
+                Some((
+                    ware.clone(),
+                    weight.powf(sigma) * f64::from(*price).powf(1.0 - sigma),
+                ))
+            })
+            .collect();
+        let total_weight: f64 = weights.iter().map(|(_, weight)| weight).sum();
 
-        if total_consumption > 0 {
-            debug!(
-                "Consumed {} at average price {} (fulfilment: {:.2})",
-                self.target_ware_amount.with_amount(total_consumption),
-                average_price,
-                self.fulfilment,
-            );
+        weights
+            .into_iter()
+            .map(|(ware, weight)| (ware, weight / total_weight))
+            .collect()
+    }
+
+    /// Place bids for the wares of the basket at the price levels currently offered on the market.
+    ///
+    /// The budget is split across the offered wares by their budget shares,
+    /// and each share is spent on the cheapest offers of its ware first.
+    /// Returns the money held back by the market for the bids.
+    pub(crate) fn bid(&mut self, market: &mut Market, consumer_id: ConsumerId) -> Result<Money> {
+        let mut prices = Vec::new();
+        for (ware, _) in &self.basket {
+            if let Some(price) = market.current_price(ware)? {
+                prices.push((ware.clone(), price));
+            }
+        }
+        let budget = self.budget();
+        let mut escrowed_money = Money::ZERO;
+
+        for (ware, budget_share) in self.budget_shares(&prices) {
+            let mut ware_budget = Money::from((budget.raw() as f64 * budget_share) as u64);
+
+            for (price, offered_amount) in market.price_levels(&ware)? {
+                let bid_amount = offered_amount.min(ware_budget.checked_div_money(price)?);
+                if bid_amount == 0 {
+                    break;
+                }
+
+                market.bid(
+                    Buyer::Consumer(consumer_id),
+                    WareAmount::new(ware.clone(), bid_amount),
+                    price,
+                    &mut self.savings,
+                )?;
+                let cost = price.checked_mul(bid_amount)?;
+                ware_budget = ware_budget.checked_sub(cost)?;
+                escrowed_money = escrowed_money.checked_add(cost)?;
+            }
         }
+        Ok(escrowed_money)
+    }
+
+    /// Consume the wares bought when the market was cleared.
+    ///
+    /// Returns the money refunded for the unspent part of the bids.
+    pub(crate) fn consume(
+        &mut self,
+        market: &mut Market,
+        consumer_id: ConsumerId,
+    ) -> Result<Money> {
+        // The wares are consumed right away, so only the unspent money is kept.
+        market.collect_purchases(Buyer::Consumer(consumer_id));
+        market.refund(Buyer::Consumer(consumer_id), &mut self.savings)
+    }
+}
+
+impl From<usize> for ConsumerId {
+    fn from(value: usize) -> Self {
+        Self(value)
+    }
+}
+
+impl From<ConsumerId> for usize {
+    fn from(value: ConsumerId) -> Self {
+        value.0
+    }
+}
+
+impl Display for ConsumerId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        money::Money,
+        ware::{Ware, WareDefinition},
+    };
+
+    use super::Consumer;
+
+    fn fruits() -> (Ware, Ware) {
+        (
+            Ware::new(WareDefinition::new("consumer_test_apple")),
+            Ware::new(WareDefinition::new("consumer_test_pear")),
+        )
+    }
+
+    fn apple_share(elasticity_of_substitution: f64, apple_price: u64, pear_price: u64) -> f64 {
+        let (apple, pear) = fruits();
+        let consumer = Consumer::new(
+            vec![(apple.clone(), 1.0), (pear.clone(), 1.0)],
+            elasticity_of_substitution,
+        )
+        .unwrap();
+        let shares = consumer.budget_shares(&[
+            (apple.clone(), Money::from(apple_price)),
+            (pear, Money::from(pear_price)),
+        ]);
+        assert_eq!(shares.len(), 2);
+        assert!((shares.iter().map(|(_, share)| share).sum::<f64>() - 1.0).abs() < 1e-9);
+        shares
+            .into_iter()
+            .find(|(ware, _)| *ware == apple)
+            .map(|(_, share)| share)
+            .unwrap()
+    }
+
+    #[test]
+    fn budget_shifts_towards_cheaper_ware() {
+        assert!((apple_share(2.0, 10, 10) - 0.5).abs() < 1e-9);
+        // With an elasticity of 2, the shares are inversely proportional to the prices.
+        assert!((apple_share(2.0, 10, 20) - 2.0 / 3.0).abs() < 1e-9);
+        assert!((apple_share(2.0, 20, 10) - 1.0 / 3.0).abs() < 1e-9);
+        // The more substitutable the wares, the stronger the shift.
+        assert!(apple_share(4.0, 10, 20) > apple_share(2.0, 10, 20));
+    }
+
+    #[test]
+    fn budget_shares_ignore_prices_at_unit_elasticity() {
+        assert!((apple_share(1.0, 10, 20) - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn budget_goes_to_offered_wares_only() {
+        let (apple, pear) = fruits();
+        let consumer = Consumer::new(vec![(apple, 1.0), (pear.clone(), 3.0)], 2.0).unwrap();
+        assert_eq!(
+            consumer.budget_shares(&[(pear.clone(), Money::from(10))]),
+            vec![(pear, 1.0)]
+        );
     }
 }
@@ -0,0 +1,103 @@
+use std::{fmt::Display, path::PathBuf};
+
+use crate::{
+    scenario::ScenarioError, snapshot::SnapshotError, statistics::export::ExportError,
+    ware::WareRegistrationError,
+};
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// An error of the simulation.
+#[derive(Debug)]
+pub enum Error {
+    Scenario(ScenarioError),
+    Snapshot(SnapshotError),
+    Export(ExportError),
+    Ware(WareRegistrationError),
+    /// Writing an output file, such as a chart, failed.
+    Output {
+        file: PathBuf,
+        source: Box<dyn std::error::Error>,
+    },
+    /// A constructor or an operation was given an invalid argument.
+    InvalidArgument(String),
+    /// An arithmetic operation left the range of its type.
+    Overflow(&'static str),
+    /// A division had a divisor of zero.
+    DivisionByZero(&'static str),
+    /// The state of the simulation became inconsistent.
+    InvariantViolation(String),
+}
+
+impl Error {
+    pub fn invalid_argument(message: impl Into<String>) -> Self {
+        Self::InvalidArgument(message.into())
+    }
+
+    pub fn invariant_violation(message: impl Into<String>) -> Self {
+        Self::InvariantViolation(message.into())
+    }
+
+    pub fn output(file: impl Into<PathBuf>, source: impl Into<Box<dyn std::error::Error>>) -> Self {
+        Self::Output {
+            file: file.into(),
+            source: source.into(),
+        }
+    }
+}
+
+impl From<ScenarioError> for Error {
+    fn from(value: ScenarioError) -> Self {
+        Self::Scenario(value)
+    }
+}
+
+impl From<SnapshotError> for Error {
+    fn from(value: SnapshotError) -> Self {
+        Self::Snapshot(value)
+    }
+}
+
+impl From<ExportError> for Error {
+    fn from(value: ExportError) -> Self {
+        Self::Export(value)
+    }
+}
+
+impl From<WareRegistrationError> for Error {
+    fn from(value: WareRegistrationError) -> Self {
+        Self::Ware(value)
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Scenario(error) => write!(f, "{error}"),
+            Error::Snapshot(error) => write!(f, "{error}"),
+            Error::Export(error) => write!(f, "{error}"),
+            Error::Ware(error) => write!(f, "{error}"),
+            Error::Output { file, source } => write!(f, "{}: {source}", file.display()),
+            Error::InvalidArgument(message) => write!(f, "invalid argument: {message}"),
+            Error::Overflow(operation) => write!(f, "overflow in {operation}"),
+            Error::DivisionByZero(operation) => write!(f, "division by zero in {operation}"),
+            Error::InvariantViolation(message) => write!(f, "invariant violated: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Scenario(error) => Some(error),
+            Error::Snapshot(error) => Some(error),
+            Error::Export(error) => Some(error),
+            Error::Ware(error) => Some(error),
+            Error::Output { source, .. } => Some(source.as_ref()),
+            Error::InvalidArgument(_)
+            | Error::Overflow(_)
+            | Error::DivisionByZero(_)
+            | Error::InvariantViolation(_) => None,
+        }
+    }
+}
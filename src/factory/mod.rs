@@ -1,149 +1,543 @@
-use std::fmt::Display;
+use std::{collections::BTreeMap, fmt::Display, mem};
 
 use general_stable_vec::interface::StableVecIndex;
 use log::debug;
+use planner::{ProductionPlan, ProductionPlanner};
+use pricing::{CostPlusHalf, PricingStrategy};
+use report::{BalanceSheet, IncomeStatement};
+use serde::{Deserialize, Serialize};
 use template::FactoryTemplate;
 
 use crate::{
-    market::Market,
+    error::{Error, Result},
+    market::{Buyer, Market, OfferId, Seller},
     money::{ApproximateMoney, Money},
+    recipe::Recipe,
+    region::RegionId,
     time::DateTime,
-    ware::WareAmount,
-    warehouse::Warehouse,
+    ware::{Ware, WareAmount},
+    warehouse::{StockLevel, Warehouse, WarehouseBatch},
 };
 
+pub mod planner;
+pub mod pricing;
+pub mod report;
 pub mod template;
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+/// The relative amount by which factories adjust their wage each hour.
+const WAGE_STEP: f64 = 0.05;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct FactoryId(usize);
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Factory {
     template: FactoryTemplate,
+    /// The region on whose market the factory trades and whose households work in it.
+    region: RegionId,
     input_storage: Warehouse,
     output_storage: Warehouse,
     money: Money,
+    /// The wage per worker and hour.
+    wage: Money,
+    /// The number of employed workers.
+    workers: u64,
+    /// The positions that could not be filled when the factory last hired.
+    unfilled_positions: u64,
+    /// The wages paid since the factory last produced.
+    wage_bill: Money,
+    pricing_strategy: Box<dyn PricingStrategy>,
+    planner: ProductionPlanner,
+    /// The recipe applications of the last production.
+    production_plan: ProductionPlan,
+    /// How long offers stay on the market before the unsold wares are returned.
+    offer_lifetime: DateTime,
+    /// The offers of this factory on the market.
+    open_offers: BTreeMap<OfferId, OpenOffer>,
+    /// Money above this reserve is paid out to the households as dividends.
+    cash_reserve: Money,
+    /// The holding costs that were due but could not be paid, which are paid as soon as money comes in.
+    unpaid_holding_costs: Money,
+    /// The number of consecutive hours with a net loss.
+    unprofitable_hours: u64,
+    /// The income statement of the current hour.
+    current_income_statement: IncomeStatement,
+    /// The income statement of the last completed hour.
+    income_statement: IncomeStatement,
+    /// The income statement since the factory was founded, up to the last completed hour.
+    cumulative_income_statement: IncomeStatement,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct OpenOffer {
+    /// The amount offered at the start of the hour.
+    ware_amount: WareAmount,
+    sourcing_cost_per_item: ApproximateMoney,
 }
 
 impl Factory {
     pub fn new(template: FactoryTemplate, starting_money: Money) -> Self {
         Self {
-            template,
-            input_storage: Default::default(),
-            output_storage: Default::default(),
+            wage: template.wage(),
+            region: RegionId::default(),
+            input_storage: Warehouse::new(template.input_storage().clone()),
+            output_storage: Warehouse::new(template.output_storage().clone()),
             money: starting_money,
+            workers: 0,
+            unfilled_positions: 0,
+            wage_bill: Money::ZERO,
+            pricing_strategy: Box::new(CostPlusHalf),
+            planner: ProductionPlanner::new(template.recipes()),
+            production_plan: Default::default(),
+            offer_lifetime: DateTime::from_hours(1),
+            open_offers: Default::default(),
+            cash_reserve: starting_money,
+            unpaid_holding_costs: Money::ZERO,
+            unprofitable_hours: 0,
+            current_income_statement: Default::default(),
+            income_statement: Default::default(),
+            cumulative_income_statement: Default::default(),
+            template,
         }
     }
 
+    pub fn with_pricing_strategy(mut self, pricing_strategy: Box<dyn PricingStrategy>) -> Self {
+        self.pricing_strategy = pricing_strategy;
+        self
+    }
+
+    pub fn with_offer_lifetime(mut self, offer_lifetime: DateTime) -> Result<Self> {
+        if offer_lifetime == DateTime::ZERO {
+            return Err(Error::invalid_argument("offer lifetime must be positive"));
+        }
+        self.offer_lifetime = offer_lifetime;
+        Ok(self)
+    }
+
+    /// Place the factory in the given region.
+    pub fn with_region(mut self, region: RegionId) -> Self {
+        self.region = region;
+        self
+    }
+
+    pub fn template(&self) -> &FactoryTemplate {
+        &self.template
+    }
+
+    pub fn region(&self) -> RegionId {
+        self.region
+    }
+
+    /// How often each recipe was applied in the last production.
+    pub fn production_plan(&self) -> &ProductionPlan {
+        &self.production_plan
+    }
+
     pub fn money(&self) -> Money {
         self.money
     }
 
-    pub fn produce_one_hour(&mut self) {
-        self.produce(DateTime::from_hours(1));
+    /// The number of consecutive hours with a net loss.
+    ///
+    /// Losses are measured by the income statement, such that buying inputs or building up stock does not count.
+    pub fn unprofitable_hours(&self) -> u64 {
+        self.unprofitable_hours
+    }
+
+    pub fn wage(&self) -> Money {
+        self.wage
+    }
+
+    /// The income statement of the last completed hour.
+    pub fn income_statement(&self) -> &IncomeStatement {
+        &self.income_statement
+    }
+
+    /// The income statement since the factory was founded, up to the last completed hour.
+    pub fn cumulative_income_statement(&self) -> &IncomeStatement {
+        &self.cumulative_income_statement
+    }
+
+    /// The stock levels of the wares stored for production.
+    pub fn input_stock(&self) -> BTreeMap<Ware, StockLevel> {
+        self.input_storage.stock_levels()
+    }
+
+    /// The stock levels of the produced wares, including the unsold wares offered on the market.
+    pub fn output_stock(&self) -> BTreeMap<Ware, StockLevel> {
+        let mut stock_levels = self.output_storage.stock_levels();
+        for open_offer in self.open_offers.values() {
+            stock_levels
+                .entry(open_offer.ware_amount.ware().clone())
+                .or_default()
+                .add(
+                    open_offer.ware_amount.amount(),
+                    open_offer.sourcing_cost_per_item,
+                );
+        }
+        stock_levels
+    }
+
+    /// The current assets and liabilities of the factory, valuing wares at their sourcing cost.
+    pub fn balance_sheet(&self) -> BalanceSheet {
+        let mut inventory = self.input_storage.value();
+        inventory += self.output_storage.value();
+        for open_offer in self.open_offers.values() {
+            inventory += open_offer.sourcing_cost_per_item * open_offer.ware_amount.amount();
+        }
+        BalanceSheet::new(
+            self.money,
+            inventory,
+            self.unpaid_holding_costs,
+            self.cash_reserve,
+        )
+    }
+
+    pub fn workers(&self) -> u64 {
+        self.workers
     }
 
-    pub fn produce(&mut self, duration: DateTime) {
+    /// Returns true if the factory owes holding costs it could not pay,
+    /// or cannot pay the workers for a single application of any recipe.
+    pub fn is_bankrupt(&self) -> bool {
+        if self.unpaid_holding_costs > Money::ZERO {
+            return true;
+        }
+        let workers_per_application = self
+            .template
+            .recipes()
+            .iter()
+            .map(Recipe::workers_per_application)
+            .min()
+            .unwrap_or(0);
+        self.wage
+            .checked_mul(workers_per_application)
+            .map_or(true, |wages| self.money < wages)
+    }
+
+    /// Adjust the wage to the labour market.
+    ///
+    /// The wage rises if positions stayed unfilled when the factory last hired,
+    /// and falls otherwise, such that it follows the supply of workers.
+    pub(crate) fn adjust_wage(&mut self) -> Result<()> {
+        let step = Money::from(((self.wage.raw() as f64 * WAGE_STEP).round() as u64).max(1));
+        if self.unfilled_positions > 0 {
+            self.wage = self.wage.checked_add(step)?;
+        } else if self.workers > 0 {
+            self.wage = self.wage.saturating_sub(step).max(Money::from(1));
+        }
+        Ok(())
+    }
+
+    /// The number of workers needed to produce at full capacity, limited by the wages the factory can pay for an hour.
+    pub fn desired_workers(&self) -> Result<u64> {
+        Ok(self
+            .template
+            .capacity()
+            .min(self.money.checked_div_money(self.wage)?))
+    }
+
+    /// Hire workers for the given number of open positions.
+    pub(crate) fn hire(&mut self, open_positions: u64, hired_workers: u64) -> Result<()> {
+        if hired_workers > open_positions {
+            return Err(Error::invariant_violation(format!(
+                "hired {hired_workers} workers for {open_positions} open positions"
+            )));
+        }
+        self.workers += hired_workers;
+        self.unfilled_positions = open_positions - hired_workers;
+        Ok(())
+    }
+
+    /// A worker was laid off or quit.
+    pub(crate) fn lose_worker(&mut self) {
+        self.workers -= 1;
+    }
+
+    /// Pay the wage of one worker for an hour and return it.
+    pub(crate) fn pay_wage(&mut self) -> Result<Money> {
+        self.money = self.money.checked_sub(self.wage).map_err(|_| {
+            Error::invariant_violation(format!(
+                "factory with {} cannot pay a wage of {}",
+                self.money, self.wage
+            ))
+        })?;
+        self.wage_bill = self.wage_bill.checked_add(self.wage)?;
+        self.current_income_statement.record_wages(self.wage)?;
+        Ok(self.wage)
+    }
+
+    /// Produce for the hour that ends at the given time.
+    pub(crate) fn produce_one_hour(&mut self, time: DateTime) -> Result<()> {
+        self.produce(DateTime::from_hours(1), time)
+    }
+
+    /// Produce for the given duration with the employed workers, splitting them between the recipes as planned.
+    ///
+    /// The wages paid since the last production are part of the sourcing cost of the outputs,
+    /// split between the recipes by the work spent on them.
+    /// The outputs count as produced at the given time.
+    pub(crate) fn produce(&mut self, duration: DateTime, time: DateTime) -> Result<()> {
         debug!(
-            "Factory with recipe {} produces for {duration} with {} and inputs {}",
-            self.template.recipe(),
+            "Factory with recipes {} produces for {duration} with {}, {} workers and inputs {}",
+            self.template.recipe_names(),
             self.money,
+            self.workers,
             self.input_storage
         );
 
-        // Compute available recipe applications.
-        let maximum_recipe_application_amount = self.template.recipe().rate() * duration;
-        let recipe_application_amount = self
-            .template
-            .recipe()
-            .inputs()
+        let recipes = self.template.recipes();
+        let plan = self.planner.plan(
+            recipes,
+            self.workers,
+            duration.into_hours(),
+            Some(&self.input_storage),
+            &self.output_storage,
+            &self.offered_amounts(),
+        )?;
+        let work: u64 = recipes
             .iter()
-            .copied()
-            .fold(
-                maximum_recipe_application_amount,
-                |recipe_application_amount, single_input_amount| {
-                    let required_input_amount = single_input_amount * recipe_application_amount;
-                    let available_input_amount =
-                        self.input_storage.ware_amount(required_input_amount.ware());
-
-                    if available_input_amount < required_input_amount {
-                        available_input_amount / single_input_amount
-                    } else {
-                        recipe_application_amount
-                    }
-                },
-            )
-            .min(self.money / self.template.hourly_wages());
-        debug!("Executing the recipe {recipe_application_amount} times");
-
-        if recipe_application_amount > 0 {
-            let duration =
-                recipe_application_amount.div_ceil(self.template.recipe().rate().per_hour());
-            let wages = self.template.hourly_wages() * duration;
-            self.money -= wages;
+            .zip(plan.applications())
+            .map(|(recipe, applications)| applications * recipe.workers_per_application())
+            .sum();
+
+        let wages = mem::replace(&mut self.wage_bill, Money::ZERO);
+        if work > 0 {
+            self.current_income_statement
+                .record_capitalised_wages(wages)?;
+        }
+
+        for (recipe, &recipe_application_amount) in recipes.iter().zip(plan.applications()) {
+            if recipe_application_amount == 0 {
+                continue;
+            }
+            debug!(
+                "Executing the recipe {} {recipe_application_amount} times",
+                recipe.name()
+            );
+
             let mut sourcing_cost_per_item =
-                ApproximateMoney::from(wages) / recipe_application_amount;
+                ApproximateMoney::from(wages) * recipe.workers_per_application() / work;
 
             // Apply recipe.
-            for input in self.template.recipe().inputs() {
+            for input in recipe.inputs() {
                 sourcing_cost_per_item += self
                     .input_storage
-                    .remove_ware(input * recipe_application_amount)
+                    .remove_ware(input.checked_mul(recipe_application_amount)?)?
                     * input.amount();
             }
 
-            sourcing_cost_per_item /= self.template.recipe().output_amount();
-            for output in self.template.recipe().outputs() {
-                self.output_storage
-                    .insert_ware(*output * recipe_application_amount, sourcing_cost_per_item);
+            sourcing_cost_per_item /= recipe.output_amount();
+            for output in recipe.outputs() {
+                let output = output.checked_mul(recipe_application_amount)?;
+                let refused_amount = self.output_storage.insert_ware(
+                    output.clone(),
+                    sourcing_cost_per_item,
+                    time,
+                )?;
+                write_off_refused(
+                    &mut self.current_income_statement,
+                    output.with_amount(refused_amount),
+                    sourcing_cost_per_item,
+                );
             }
         }
+        self.production_plan = plan;
+        Ok(())
     }
 
-    pub fn reuse_inputs(&mut self) {
-        let recipe_production_per_hour = self.template.recipe().rate().per_hour();
+    /// Rank the recipes by their estimated profit margin at the current wage and market prices.
+    pub(crate) fn plan_production(&mut self, market: &Market) -> Result<()> {
+        self.planner
+            .rank(self.template.recipes(), self.wage, market)
+    }
+
+    /// The recipe applications aimed at for the next hour, if all inputs were available.
+    fn target_plan(&self) -> Result<ProductionPlan> {
+        self.planner.plan(
+            self.template.recipes(),
+            self.template.capacity(),
+            1,
+            None,
+            &self.output_storage,
+            &self.offered_amounts(),
+        )
+    }
 
-        for input in self.template.recipe().inputs() {
-            let required_amount = input.amount() * recipe_production_per_hour;
-            let available_amount = self.input_storage.ware_amount(input.ware()).amount();
+    /// The amounts of the wares offered on the market, which still take up space in the output storage.
+    fn offered_amounts(&self) -> BTreeMap<Ware, u64> {
+        let mut offered_amounts = BTreeMap::new();
+        for open_offer in self.open_offers.values() {
+            *offered_amounts
+                .entry(open_offer.ware_amount.ware().clone())
+                .or_default() += open_offer.ware_amount.amount();
+        }
+        offered_amounts
+    }
+
+    pub(crate) fn reuse_inputs(&mut self) -> Result<()> {
+        let required_inputs = self
+            .target_plan()?
+            .required_inputs(self.template.recipes())?;
+
+        for (ware, required_amount) in required_inputs {
+            let available_amount = self.input_storage.ware_amount(&ware).amount();
             let missing_amount = required_amount.saturating_sub(available_amount);
-            let reusable_amount = self.output_storage.ware_amount(input.ware()).amount();
+            let reusable_amount = self.output_storage.ware_amount(&ware).amount();
+            let free_amount = self
+                .input_storage
+                .fitting_multiple(&[WareAmount::new(ware.clone(), 1)], &BTreeMap::new());
             let transfer_amount =
-                WareAmount::new(input.ware(), missing_amount.min(reusable_amount));
+                WareAmount::new(ware, missing_amount.min(reusable_amount).min(free_amount));
 
             if transfer_amount.amount() > 0 {
-                let sourcing_cost_per_item = self.output_storage.remove_ware(transfer_amount);
-                self.input_storage
-                    .insert_ware(transfer_amount, sourcing_cost_per_item);
+                for batch in self.output_storage.remove_batches(transfer_amount)? {
+                    self.store_batch(batch, true)?;
+                }
             }
         }
+        Ok(())
     }
 
-    pub fn offer_outputs(&mut self, market: &mut Market, factory_id: FactoryId) {
+    /// Offer all outputs on the market.
+    ///
+    /// Offers of perishable wares expire early, see [`WarehouseBatch::offer_lifetime`].
+    pub(crate) fn offer_outputs(
+        &mut self,
+        market: &mut Market,
+        factory_id: FactoryId,
+        time: DateTime,
+    ) -> Result<()> {
         for batch in self.output_storage.drain() {
-            market.offer(
-                batch.ware(),
-                batch.amount(),
-                (f64::from(batch.sourcing_cost_per_item() + 0.5).ceil() as u64).into(),
-                factory_id,
+            let expiry_time = time.checked_add(batch.offer_lifetime(self.offer_lifetime, time))?;
+            let price_per_item =
+                self.pricing_strategy
+                    .price(&batch, Seller::Factory(factory_id), market);
+            let open_offer = OpenOffer {
+                ware_amount: WareAmount::new(batch.ware().clone(), batch.amount()),
+                sourcing_cost_per_item: batch.sourcing_cost_per_item(),
+            };
+            let offer_id = market.offer(
+                batch,
+                price_per_item,
+                Seller::Factory(factory_id),
+                expiry_time,
             );
+            self.open_offers.insert(offer_id, open_offer);
         }
+        Ok(())
     }
 
-    pub fn buy_inputs(&mut self, market: &mut Market) {
-        if self.template.recipe().inputs().is_empty() {
-            return;
+    /// Put the wares of expired offers back into the output storage.
+    pub(crate) fn restock_expired_offers(
+        &mut self,
+        expired_offers: Vec<(OfferId, WarehouseBatch)>,
+    ) -> Result<()> {
+        for (offer_id, batch) in expired_offers {
+            self.open_offers.remove(&offer_id);
+            self.store_batch(batch, false)?;
+        }
+        Ok(())
+    }
+
+    /// Put a batch into the input or the output storage, and write off the part that does not fit.
+    fn store_batch(&mut self, batch: WarehouseBatch, input: bool) -> Result<()> {
+        let ware_amount = WareAmount::new(batch.ware().clone(), batch.amount());
+        let sourcing_cost_per_item = batch.sourcing_cost_per_item();
+        let storage = if input {
+            &mut self.input_storage
+        } else {
+            &mut self.output_storage
+        };
+        let refused_amount = storage.insert_batch(batch)?;
+        write_off_refused(
+            &mut self.current_income_statement,
+            ware_amount.with_amount(refused_amount),
+            sourcing_cost_per_item,
+        );
+        Ok(())
+    }
+
+    /// Remove the wares that spoiled in the hour that ends at the given time, and write them off.
+    pub(crate) fn spoil_wares(&mut self, time: DateTime) {
+        let spoiled_inputs = self.input_storage.spoil(time);
+        let spoiled_outputs = self.output_storage.spoil(time);
+        for (ware, stock_level) in spoiled_inputs.into_iter().chain(spoiled_outputs) {
+            debug!(
+                "{} worth {} spoiled",
+                WareAmount::new(ware, stock_level.amount()),
+                stock_level.value()
+            );
+            self.current_income_statement
+                .record_spoilage(stock_level.value());
+        }
+    }
+
+    /// Withdraw all offers of this factory from the market and put the wares back into the output storage.
+    pub(crate) fn withdraw_offers(&mut self, market: &mut Market) -> Result<()> {
+        for offer_id in mem::take(&mut self.open_offers).into_keys() {
+            if let Some(batch) = market.withdraw(offer_id) {
+                self.store_batch(batch, false)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Inform the pricing strategy about the sales of this hour, and book the cost of the sold wares.
+    pub(crate) fn record_sales(&mut self, market: &Market) {
+        let mut offered_amounts: BTreeMap<Ware, (u64, u64)> = BTreeMap::new();
+        let income_statement = &mut self.current_income_statement;
+        self.open_offers.retain(|offer_id, open_offer| {
+            let ware_amount = &open_offer.ware_amount;
+            let remaining_amount = market.offered_amount(*offer_id);
+            let (offered_amount, sold_amount) = offered_amounts
+                .entry(ware_amount.ware().clone())
+                .or_default();
+            *offered_amount += ware_amount.amount();
+            *sold_amount += ware_amount.amount() - remaining_amount;
+            income_statement.record_cost_of_goods_sold(
+                open_offer.sourcing_cost_per_item * (ware_amount.amount() - remaining_amount),
+            );
+
+            open_offer.ware_amount = ware_amount.with_amount(remaining_amount);
+            remaining_amount > 0
+        });
+
+        for (ware, (offered_amount, sold_amount)) in offered_amounts {
+            self.pricing_strategy
+                .record_sales(ware, offered_amount, sold_amount);
+        }
+    }
+
+    /// Place bids for the inputs required for the next hour of production.
+    ///
+    /// The factory bids for as much as it can afford after reserving money for the wages and fit into its input storage,
+    /// offering the price of the most expensive offer it needs.
+    ///
+    /// Returns the money held back by the market for the bids.
+    pub(crate) fn bid_for_inputs(
+        &mut self,
+        market: &mut Market,
+        factory_id: FactoryId,
+    ) -> Result<Money> {
+        let recipes = self.template.recipes();
+        if recipes.iter().all(|recipe| recipe.inputs().is_empty()) {
+            return Ok(Money::ZERO);
         }
 
         debug!(
-            "Buying factory inputs from market for recipe {}",
-            self.template.recipe()
+            "Bidding for factory inputs on market for recipes {}",
+            self.template.recipe_names()
         );
 
-        let recipe_production_per_hour = self.template.recipe().rate().per_hour();
+        let target_plan = self.target_plan()?;
+        // Keep enough money to pay the workers for the next hour.
+        let budget = self
+            .money
+            .saturating_sub(self.wage.checked_mul(self.workers)?);
 
         let mut left = 0;
-        let mut right = recipe_production_per_hour;
+        let mut right = target_plan.total_applications();
         let mut ceil_middle = false;
 
         while left < right {
@@ -155,16 +549,14 @@ impl Factory {
             ceil_middle = !ceil_middle;
 
             let mut total_price = Money::ZERO;
-
-            for input in self.template.recipe().inputs() {
-                let required_amount = input.amount() * middle;
-                let available_amount = self.input_storage.ware_amount(input.ware()).amount();
-                let missing_amount = required_amount.saturating_sub(available_amount);
-                let (_, price) = market.total_price(WareAmount::new(input.ware(), missing_amount));
-                total_price += price;
+            let plan = self.planner.truncate(&target_plan, middle);
+            let missing_inputs = plan.missing_inputs(recipes, &self.input_storage)?;
+            for ware_amount in &missing_inputs {
+                let (amount, price) = market.marginal_price(ware_amount)?;
+                total_price = total_price.checked_add(price.checked_mul(amount)?)?;
             }
 
-            if total_price <= self.money.saturating_sub(self.template.hourly_wages()) {
+            if total_price <= budget && self.input_storage.fits(&missing_inputs) {
                 left = middle;
             } else {
                 right = middle - 1;
@@ -172,25 +564,124 @@ impl Factory {
         }
 
         debug_assert_eq!(left, right);
-        let buy_target = left;
+        let bid_target = left;
+
+        let missing_inputs = self
+            .planner
+            .truncate(&target_plan, bid_target)
+            .missing_inputs(recipes, &self.input_storage)?;
+        let mut escrowed_money = Money::ZERO;
+        for ware_amount in missing_inputs {
+            let (amount, price) = market.marginal_price(&ware_amount)?;
+            debug!(
+                "Bidding for {} at {price} per item",
+                ware_amount.with_amount(amount)
+            );
+            market.bid(
+                Buyer::Factory(factory_id),
+                ware_amount.with_amount(amount),
+                price,
+                &mut self.money,
+            )?;
+            escrowed_money = escrowed_money.checked_add(price.checked_mul(amount)?)?;
+        }
+        Ok(escrowed_money)
+    }
 
-        for input in self.template.recipe().inputs() {
-            let required_amount = input.amount() * buy_target;
-            let available_amount = self.input_storage.ware_amount(input.ware()).amount();
-            let missing_amount = required_amount.saturating_sub(available_amount);
+    /// Put the inputs bought when the market was cleared into the input storage.
+    ///
+    /// Returns the money refunded for the unspent part of the bids.
+    pub(crate) fn collect_purchases(
+        &mut self,
+        market: &mut Market,
+        factory_id: FactoryId,
+    ) -> Result<Money> {
+        for trade in market.collect_purchases(Buyer::Factory(factory_id)) {
+            debug!(
+                "Bought {} for {} per item",
+                trade.ware_amount(),
+                trade.price_per_item()
+            );
+            let refused_amount = self.input_storage.insert_ware(
+                trade.ware_amount(),
+                trade.price_per_item().into(),
+                trade.produced(),
+            )?;
+            write_off_refused(
+                &mut self.current_income_statement,
+                trade.ware_amount().with_amount(refused_amount),
+                trade.price_per_item().into(),
+            );
+        }
+        market.refund(Buyer::Factory(factory_id), &mut self.money)
+    }
+
+    /// Charge the holding cost of the warehouses for an hour, and return the amount paid.
+    ///
+    /// The wares offered on the market are still stored in the output storage.
+    /// What the money does not suffice for is owed, and paid first in the following hours.
+    pub(crate) fn pay_holding_costs(&mut self) -> Result<Money> {
+        let offered_amounts = self.offered_amounts();
+        let holding_costs = self
+            .input_storage
+            .charge_holding_cost(&BTreeMap::new())
+            .checked_add(self.output_storage.charge_holding_cost(&offered_amounts))?;
+        self.current_income_statement
+            .record_holding_costs(holding_costs)?;
+
+        let due = self.unpaid_holding_costs.checked_add(holding_costs)?;
+        let paid = due.min(self.money);
+        self.money = self.money.checked_sub(paid)?;
+        self.unpaid_holding_costs = due.checked_sub(paid)?;
+        Ok(paid)
+    }
 
-            let ware_amount = WareAmount::new(input.ware(), missing_amount);
-            let actual_amount = market.buy(ware_amount, &mut self.input_storage, &mut self.money);
-            let actual_ware_amount = ware_amount.with_amount(actual_amount);
-            debug!("Bought {actual_ware_amount} (wanted {missing_amount})");
+    /// Collect the proceeds of the sales on the market, and return them.
+    pub(crate) fn collect_money(
+        &mut self,
+        market: &mut Market,
+        factory_id: FactoryId,
+    ) -> Result<Money> {
+        let proceeds = market.transfer_money(&mut self.money, Seller::Factory(factory_id))?;
+        self.current_income_statement.record_revenue(proceeds)?;
+        Ok(proceeds)
+    }
+
+    /// Update the profitability tracking and the income statements at the end of an hour.
+    pub(crate) fn close_hour(&mut self) -> Result<()> {
+        if self.current_income_statement.net_profit() < 0.0 {
+            self.unprofitable_hours += 1;
+        } else {
+            self.unprofitable_hours = 0;
         }
+
+        self.cumulative_income_statement
+            .accumulate(&self.current_income_statement)?;
+        self.income_statement = mem::take(&mut self.current_income_statement);
+        Ok(())
     }
 
-    pub fn collect_money(&mut self, market: &mut Market, factory_id: FactoryId) {
-        market.transfer_money(&mut self.money, factory_id);
+    /// Pay out the money above the cash reserve and return it.
+    pub(crate) fn pay_dividends(&mut self) -> Result<Money> {
+        let dividends = self.money.saturating_sub(self.cash_reserve);
+        self.money = self.money.checked_sub(dividends)?;
+        Ok(dividends)
     }
 }
 
+/// Write off wares that did not fit into a warehouse, at their sourcing cost.
+fn write_off_refused(
+    income_statement: &mut IncomeStatement,
+    ware_amount: WareAmount,
+    sourcing_cost_per_item: ApproximateMoney,
+) {
+    if ware_amount.amount() == 0 {
+        return;
+    }
+    debug!("{ware_amount} did not fit into the warehouse and were written off");
+    income_statement.record_spoilage(sourcing_cost_per_item * ware_amount.amount());
+}
+
 impl StableVecIndex for FactoryId {}
 
 impl From<usize> for FactoryId {
@@ -210,3 +701,91 @@ impl Display for FactoryId {
         self.0.fmt(f)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_xoshiro::Xoshiro256PlusPlus;
+
+    use crate::{
+        consumer::ConsumerId,
+        market::{Buyer, Market, Seller},
+        money::{ApproximateMoney, Money},
+        recipe::{ProductionRate, Recipe},
+        time::DateTime,
+        ware::{Ware, WareAmount, WareDefinition},
+        warehouse::WarehouseBatch,
+    };
+
+    use super::{template::FactoryTemplate, Factory, FactoryId};
+
+    #[test]
+    fn buying_inputs_is_not_a_loss() {
+        let orange = Ware::new(WareDefinition::new("factory_test_orange"));
+        let juice = Ware::new(WareDefinition::new("factory_test_juice"));
+        let recipe = Recipe::new(
+            [WareAmount::new(orange.clone(), 1)],
+            [WareAmount::new(juice.clone(), 1)],
+            ProductionRate::new(1),
+        );
+        let template = FactoryTemplate::new(recipe, Money::from(10)).unwrap();
+        let mut factory = Factory::new(template, Money::from(1_000));
+        let factory_id = FactoryId::from(0);
+        let mut market = Market::default();
+
+        // The factory offers ten juices that cost 1 each at 2, and buys ten oranges at 5.
+        factory
+            .output_storage
+            .insert_ware(
+                WareAmount::new(juice.clone(), 10),
+                1.0.into(),
+                DateTime::ZERO,
+            )
+            .unwrap();
+        factory
+            .offer_outputs(&mut market, factory_id, DateTime::from_hours(1))
+            .unwrap();
+        market.offer(
+            WarehouseBatch::new(
+                orange.clone(),
+                10,
+                ApproximateMoney::from(1),
+                DateTime::ZERO,
+            ),
+            Money::from(5),
+            Seller::Factory(FactoryId::from(1)),
+            DateTime::from_hours(2),
+        );
+        market.sort_offers(&mut Xoshiro256PlusPlus::seed_from_u64(0));
+        market
+            .bid(
+                Buyer::Factory(factory_id),
+                WareAmount::new(orange, 10),
+                Money::from(5),
+                &mut factory.money,
+            )
+            .unwrap();
+        market
+            .bid(
+                Buyer::Consumer(ConsumerId::from(0)),
+                WareAmount::new(juice, 10),
+                Money::from(2),
+                &mut Money::from(100),
+            )
+            .unwrap();
+        market
+            .clear(&mut Xoshiro256PlusPlus::seed_from_u64(0))
+            .unwrap();
+
+        factory.collect_purchases(&mut market, factory_id).unwrap();
+        factory.collect_money(&mut market, factory_id).unwrap();
+        factory.record_sales(&market);
+        factory.pay_holding_costs().unwrap();
+        factory.close_hour().unwrap();
+
+        // The factory spent more cash than it earned, but made a profit of 10 on the juices.
+        assert_eq!(factory.money(), Money::from(1_000 - 50 + 20));
+        assert_eq!(factory.income_statement().net_profit(), 10.0);
+        assert_eq!(factory.unprofitable_hours(), 0);
+    }
+}
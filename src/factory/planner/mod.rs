@@ -0,0 +1,365 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{Error, Result},
+    market::Market,
+    money::Money,
+    recipe::Recipe,
+    ware::{Ware, WareAmount},
+    warehouse::Warehouse,
+};
+
+use super::template::EstimatedProfitMargin;
+
+/// How often a factory applies each of its recipes in an hour.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProductionPlan {
+    /// The number of applications of each recipe, in the order of the recipes of the template.
+    applications: Vec<u64>,
+}
+
+/// Splits the capacity of a factory between its recipes.
+///
+/// The recipes are ranked by their estimated profit margin at the market prices.
+/// Each hour, the best recipe is applied as often as its rate, the workers, the inputs and the storage space allow,
+/// and the remaining workers apply the next recipe, as long as it is not expected to make a loss.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProductionPlanner {
+    /// The indices of the recipes that are applied, the most profitable first.
+    priorities: Vec<usize>,
+}
+
+impl ProductionPlan {
+    pub fn applications(&self) -> &[u64] {
+        &self.applications
+    }
+
+    pub fn total_applications(&self) -> u64 {
+        self.applications.iter().sum()
+    }
+
+    /// The inputs needed to execute this plan.
+    pub fn required_inputs(&self, recipes: &[Recipe]) -> Result<BTreeMap<Ware, u64>> {
+        let mut required_inputs = BTreeMap::new();
+        for (recipe, applications) in recipes.iter().zip(&self.applications) {
+            for input in recipe.inputs() {
+                add_amount(&mut required_inputs, &input.checked_mul(*applications)?)?;
+            }
+        }
+        Ok(required_inputs)
+    }
+
+    /// The inputs missing from the warehouse to execute this plan.
+    pub fn missing_inputs(
+        &self,
+        recipes: &[Recipe],
+        inputs: &Warehouse,
+    ) -> Result<Vec<WareAmount>> {
+        Ok(self
+            .required_inputs(recipes)?
+            .into_iter()
+            .map(|(ware, required_amount)| {
+                let available_amount = inputs.ware_amount(&ware).amount();
+                WareAmount::new(ware, required_amount.saturating_sub(available_amount))
+            })
+            .collect())
+    }
+}
+
+impl ProductionPlanner {
+    /// A planner that prefers the recipes in their given order until it has seen the market.
+    pub fn new(recipes: &[Recipe]) -> Self {
+        Self {
+            priorities: (0..recipes.len()).collect(),
+        }
+    }
+
+    /// Rank the recipes by their estimated profit margin at the given wage and the current market prices.
+    ///
+    /// Recipes whose margin cannot be estimated come last, in their original order.
+    /// The best recipe is always applied, such that a factory produces before its outputs have a price.
+    /// The other recipes are dropped if they are expected to make a loss or their outputs have no price.
+    pub fn rank(&mut self, recipes: &[Recipe], wage: Money, market: &Market) -> Result<()> {
+        let mut margins = Vec::new();
+        for recipe in recipes {
+            margins.push(EstimatedProfitMargin::of_recipe(recipe, wage, market)?);
+        }
+        self.priorities = (0..recipes.len()).collect();
+        self.priorities
+            .sort_by(|a, b| match (margins[*a].margin(), margins[*b].margin()) {
+                (Some(a), Some(b)) => b.total_cmp(&a),
+                (a, b) => b.is_some().cmp(&a.is_some()),
+            });
+
+        let best = self.priorities.first().copied();
+        self.priorities.retain(|&index| match margins[index] {
+            _ if Some(index) == best => true,
+            EstimatedProfitMargin::Margin(margin) => margin >= 1.0,
+            EstimatedProfitMargin::MissingInput => true,
+            EstimatedProfitMargin::MissingOutput => false,
+        });
+        Ok(())
+    }
+
+    /// Plan the applications of the recipes for the given workers and hours.
+    ///
+    /// If inputs are given, no recipe is applied more often than the inputs left by the recipes ranked above it allow.
+    /// No recipe is applied more often than its outputs fit into the output warehouse,
+    /// next to the reserved wares and the outputs of the recipes ranked above it.
+    pub fn plan(
+        &self,
+        recipes: &[Recipe],
+        workers: u64,
+        hours: u64,
+        inputs: Option<&Warehouse>,
+        outputs: &Warehouse,
+        reserved_outputs: &BTreeMap<Ware, u64>,
+    ) -> Result<ProductionPlan> {
+        let mut applications = vec![0; recipes.len()];
+        let mut worker_hours = workers
+            .checked_mul(hours)
+            .ok_or(Error::Overflow("worker hours"))?;
+        let mut used_inputs: BTreeMap<Ware, u64> = BTreeMap::new();
+        let mut used_outputs = reserved_outputs.clone();
+
+        for &index in &self.priorities {
+            let recipe = &recipes[index];
+            let mut amount = recipe
+                .rate()
+                .per_hour()
+                .checked_mul(hours)
+                .ok_or(Error::Overflow("recipe applications"))?
+                .min(worker_hours / recipe.workers_per_application());
+            if let Some(inputs) = inputs {
+                for input in recipe.inputs() {
+                    let available_amount = inputs
+                        .ware_amount(input.ware())
+                        .amount()
+                        .saturating_sub(used_inputs.get(input.ware()).copied().unwrap_or(0));
+                    amount = amount.min(available_amount / input.amount());
+                }
+            }
+            amount = amount.min(outputs.fitting_multiple(recipe.outputs(), &used_outputs));
+
+            for input in recipe.inputs() {
+                add_amount(&mut used_inputs, &input.checked_mul(amount)?)?;
+            }
+            for output in recipe.outputs() {
+                add_amount(&mut used_outputs, &output.checked_mul(amount)?)?;
+            }
+            worker_hours = amount
+                .checked_mul(recipe.workers_per_application())
+                .and_then(|work| worker_hours.checked_sub(work))
+                .ok_or(Error::Overflow("worker hours"))?;
+            applications[index] = amount;
+        }
+
+        Ok(ProductionPlan { applications })
+    }
+
+    /// Cut the plan down to the given total number of applications, keeping the applications of the best recipes.
+    pub fn truncate(&self, plan: &ProductionPlan, mut total_applications: u64) -> ProductionPlan {
+        let mut applications = vec![0; plan.applications.len()];
+        for &index in &self.priorities {
+            applications[index] = plan.applications[index].min(total_applications);
+            total_applications -= applications[index];
+        }
+        ProductionPlan { applications }
+    }
+}
+
+/// Add a ware amount to the amounts of its ware, failing if the sum does not fit.
+fn add_amount(amounts: &mut BTreeMap<Ware, u64>, ware_amount: &WareAmount) -> Result<()> {
+    let amount = amounts.entry(ware_amount.ware().clone()).or_default();
+    *amount = amount
+        .checked_add(ware_amount.amount())
+        .ok_or(Error::Overflow("ware amount addition"))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use rand::SeedableRng;
+    use rand_xoshiro::Xoshiro256PlusPlus;
+
+    use crate::{
+        factory::FactoryId,
+        market::{Market, Seller},
+        money::{ApproximateMoney, Money},
+        recipe::{ProductionRate, Recipe},
+        time::DateTime,
+        ware::{Ware, WareAmount, WareDefinition},
+        warehouse::{template::WarehouseTemplate, Warehouse, WarehouseBatch},
+    };
+
+    use super::{ProductionPlan, ProductionPlanner};
+
+    fn ware(id: &str) -> Ware {
+        Ware::new(WareDefinition::new(format!("planner_test_{id}")))
+    }
+
+    /// A bakery that bakes bread from flour, or cake from sugar, ten times per hour each.
+    fn recipes() -> Vec<Recipe> {
+        vec![
+            Recipe::new(
+                [WareAmount::new(ware("flour"), 1)],
+                [WareAmount::new(ware("bread"), 1)],
+                ProductionRate::new(10),
+            ),
+            Recipe::new(
+                [WareAmount::new(ware("sugar"), 1)],
+                [WareAmount::new(ware("cake"), 1)],
+                ProductionRate::new(10),
+            ),
+        ]
+    }
+
+    fn market(prices: &[(&str, u64)]) -> Market {
+        let mut market = Market::default();
+        for (id, price) in prices {
+            market.offer(
+                WarehouseBatch::new(ware(id), 100, ApproximateMoney::from(1), DateTime::ZERO),
+                Money::from(*price),
+                Seller::Factory(FactoryId::from(0)),
+                DateTime::from_hours(1),
+            );
+        }
+        market.sort_offers(&mut Xoshiro256PlusPlus::seed_from_u64(0));
+        market
+    }
+
+    /// At a wage of one, bread has a margin of 5 / (1 + 1) = 2.5 and cake a margin of 20 / (1 + 2) = 6.7,
+    /// unless the bread price is given.
+    fn ranked_planner(bread_price: u64) -> ProductionPlanner {
+        let mut planner = ProductionPlanner::new(&recipes());
+        planner
+            .rank(
+                &recipes(),
+                Money::from(1),
+                &market(&[
+                    ("flour", 1),
+                    ("sugar", 2),
+                    ("bread", bread_price),
+                    ("cake", 20),
+                ]),
+            )
+            .unwrap();
+        planner
+    }
+
+    fn inputs(flour: u64, sugar: u64) -> Warehouse {
+        let mut inputs = Warehouse::new(WarehouseTemplate::new());
+        for (id, amount) in [("flour", flour), ("sugar", sugar)] {
+            inputs
+                .insert_ware(
+                    WareAmount::new(ware(id), amount),
+                    ApproximateMoney::from(1),
+                    DateTime::ZERO,
+                )
+                .unwrap();
+        }
+        inputs
+    }
+
+    fn plan(planner: &ProductionPlanner, workers: u64, inputs: &Warehouse) -> Vec<u64> {
+        planner
+            .plan(
+                &recipes(),
+                workers,
+                1,
+                Some(inputs),
+                &Warehouse::new(WarehouseTemplate::new()),
+                &BTreeMap::new(),
+            )
+            .unwrap()
+            .applications()
+            .to_vec()
+    }
+
+    #[test]
+    fn ranks_most_profitable_recipe_first() {
+        assert_eq!(ranked_planner(5).priorities, vec![1, 0]);
+    }
+
+    #[test]
+    fn drops_unprofitable_recipes_except_the_best() {
+        assert_eq!(ranked_planner(1).priorities, vec![1]);
+
+        let mut planner = ProductionPlanner::new(&recipes());
+        planner
+            .rank(
+                &recipes(),
+                Money::from(1),
+                &market(&[("flour", 1), ("sugar", 2), ("bread", 1)]),
+            )
+            .unwrap();
+        assert_eq!(planner.priorities, vec![0]);
+    }
+
+    #[test]
+    fn drops_recipes_without_output_price_unless_no_recipe_has_one() {
+        let mut planner = ProductionPlanner::new(&recipes());
+        planner
+            .rank(
+                &recipes(),
+                Money::from(1),
+                &market(&[("flour", 1), ("sugar", 2), ("bread", 5)]),
+            )
+            .unwrap();
+        assert_eq!(planner.priorities, vec![0]);
+
+        planner
+            .rank(
+                &recipes(),
+                Money::from(1),
+                &market(&[("flour", 1), ("sugar", 2)]),
+            )
+            .unwrap();
+        assert_eq!(planner.priorities, vec![0]);
+    }
+
+    #[test]
+    fn plans_best_recipe_first() {
+        assert_eq!(plan(&ranked_planner(5), 10, &inputs(100, 100)), vec![0, 10]);
+        assert_eq!(plan(&ranked_planner(5), 15, &inputs(100, 100)), vec![5, 10]);
+    }
+
+    #[test]
+    fn falls_back_to_second_recipe_when_inputs_run_short() {
+        assert_eq!(plan(&ranked_planner(5), 10, &inputs(100, 4)), vec![6, 4]);
+        assert_eq!(plan(&ranked_planner(5), 10, &inputs(3, 4)), vec![3, 4]);
+    }
+
+    #[test]
+    fn leaves_workers_idle_rather_than_making_a_loss() {
+        assert_eq!(plan(&ranked_planner(1), 10, &inputs(100, 4)), vec![0, 4]);
+    }
+
+    #[test]
+    fn reports_overflowing_worker_hours() {
+        let result = ranked_planner(5).plan(
+            &recipes(),
+            u64::MAX,
+            2,
+            None,
+            &Warehouse::new(WarehouseTemplate::new()),
+            &BTreeMap::new(),
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn truncates_plan_keeping_best_recipes() {
+        let planner = ranked_planner(5);
+        let plan = ProductionPlan {
+            applications: vec![6, 4],
+        };
+        assert_eq!(planner.truncate(&plan, 10), plan);
+        assert_eq!(planner.truncate(&plan, 7).applications(), [3, 4]);
+        assert_eq!(planner.truncate(&plan, 3).applications(), [0, 3]);
+    }
+}
@@ -0,0 +1,329 @@
+use std::{collections::BTreeMap, fmt::Debug};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{Error, Result},
+    market::{Market, Seller},
+    money::{ApproximateMoney, Money},
+    ware::Ware,
+    warehouse::WarehouseBatch,
+};
+
+/// Decides the prices at which a factory or trader offers its wares on the market.
+#[typetag::serde(tag = "type")]
+pub trait PricingStrategy: Debug {
+    /// Returns the price per item at which the given batch is offered.
+    fn price(&mut self, batch: &WarehouseBatch, seller: Seller, market: &Market) -> Money;
+
+    /// Called once per offered ware after the market closes.
+    fn record_sales(&mut self, _ware: Ware, _offered_amount: u64, _sold_amount: u64) {}
+
+    /// Clone this strategy including its state.
+    fn boxed_clone(&self) -> Box<dyn PricingStrategy>;
+}
+
+/// Offer at the sourcing cost plus a half, rounded up to whole money.
+///
+/// This is the default strategy, which always asks at least half a unit more than the wares cost.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CostPlusHalf;
+
+/// Offer at the sourcing cost plus a fixed relative markup.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CostPlus {
+    markup: f64,
+}
+
+/// Raise prices of wares that sold out, and cut prices of wares that remained unsold.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DemandFollowing {
+    /// The markup over the sourcing cost of the first offer of a ware.
+    initial_markup: f64,
+    /// The relative price change per hour.
+    step: f64,
+    prices: BTreeMap<Ware, f64>,
+}
+
+/// Offer slightly below the cheapest competitor of the previous hour, but never below the sourcing cost.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompetitorUndercut {
+    /// The markup over the sourcing cost if there are no competitors.
+    markup: f64,
+    undercut: Money,
+}
+
+impl CostPlus {
+    pub fn new(markup: f64) -> Result<Self> {
+        check_markup(markup)?;
+        Ok(Self { markup })
+    }
+}
+
+impl DemandFollowing {
+    pub fn new(initial_markup: f64, step: f64) -> Result<Self> {
+        check_markup(initial_markup)?;
+        if !(step > 0.0 && step < 1.0) {
+            return Err(Error::invalid_argument("step must be between 0.0 and 1.0"));
+        }
+        Ok(Self {
+            initial_markup,
+            step,
+            prices: Default::default(),
+        })
+    }
+}
+
+impl CompetitorUndercut {
+    pub fn new(markup: f64, undercut: Money) -> Result<Self> {
+        check_markup(markup)?;
+        Ok(Self { markup, undercut })
+    }
+}
+
+fn check_markup(markup: f64) -> Result<()> {
+    if markup >= 0.0 && markup.is_finite() {
+        Ok(())
+    } else {
+        Err(Error::invalid_argument("markup must not be negative"))
+    }
+}
+
+#[typetag::serde(name = "cost_plus_half")]
+impl PricingStrategy for CostPlusHalf {
+    fn price(&mut self, batch: &WarehouseBatch, _: Seller, _: &Market) -> Money {
+        (f64::from(batch.sourcing_cost_per_item() + 0.5).ceil() as u64).into()
+    }
+
+    fn boxed_clone(&self) -> Box<dyn PricingStrategy> {
+        Box::new(self.clone())
+    }
+}
+
+#[typetag::serde(name = "cost_plus")]
+impl PricingStrategy for CostPlus {
+    fn price(&mut self, batch: &WarehouseBatch, _: Seller, _: &Market) -> Money {
+        cost_plus(batch.sourcing_cost_per_item(), self.markup)
+    }
+
+    fn boxed_clone(&self) -> Box<dyn PricingStrategy> {
+        Box::new(self.clone())
+    }
+}
+
+#[typetag::serde(name = "demand_following")]
+impl PricingStrategy for DemandFollowing {
+    fn price(&mut self, batch: &WarehouseBatch, _: Seller, _: &Market) -> Money {
+        let price = *self.prices.entry(batch.ware().clone()).or_insert_with(|| {
+            f64::from(batch.sourcing_cost_per_item()) * (1.0 + self.initial_markup)
+        });
+        round_price(price)
+    }
+
+    fn record_sales(&mut self, ware: Ware, offered_amount: u64, sold_amount: u64) {
+        if let Some(price) = self.prices.get_mut(&ware) {
+            if sold_amount >= offered_amount {
+                *price *= 1.0 + self.step;
+            } else {
+                *price = (*price * (1.0 - self.step)).max(1.0);
+            }
+        }
+    }
+
+    fn boxed_clone(&self) -> Box<dyn PricingStrategy> {
+        Box::new(self.clone())
+    }
+}
+
+#[typetag::serde(name = "competitor_undercut")]
+impl PricingStrategy for CompetitorUndercut {
+    fn price(&mut self, batch: &WarehouseBatch, seller: Seller, market: &Market) -> Money {
+        let minimum_price = cost_plus(batch.sourcing_cost_per_item(), 0.0);
+        match market.lowest_competitor_price(batch.ware(), seller) {
+            Some(price) => price.saturating_sub(self.undercut).max(minimum_price),
+            None => cost_plus(batch.sourcing_cost_per_item(), self.markup),
+        }
+    }
+
+    fn boxed_clone(&self) -> Box<dyn PricingStrategy> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for Box<dyn PricingStrategy> {
+    fn clone(&self) -> Self {
+        self.boxed_clone()
+    }
+}
+
+fn cost_plus(sourcing_cost_per_item: ApproximateMoney, markup: f64) -> Money {
+    round_price(f64::from(sourcing_cost_per_item) * (1.0 + markup))
+}
+
+/// Round up to whole money, but never offer for free.
+fn round_price(price: f64) -> Money {
+    Money::from((price.ceil() as u64).max(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_xoshiro::Xoshiro256PlusPlus;
+
+    use crate::{
+        factory::FactoryId,
+        market::{Market, Seller},
+        money::{ApproximateMoney, Money},
+        time::DateTime,
+        ware::{Ware, WareDefinition},
+        warehouse::WarehouseBatch,
+    };
+
+    use super::{CompetitorUndercut, CostPlus, DemandFollowing, PricingStrategy};
+
+    fn fig() -> Ware {
+        Ware::new(WareDefinition::new("pricing_test_fig"))
+    }
+
+    fn batch(sourcing_cost_per_item: f64) -> WarehouseBatch {
+        WarehouseBatch::new(
+            fig(),
+            10,
+            ApproximateMoney::from(sourcing_cost_per_item),
+            DateTime::ZERO,
+        )
+    }
+
+    fn seller() -> Seller {
+        Seller::Factory(FactoryId::from(0))
+    }
+
+    /// A market on which other factories offer figs at the given prices.
+    fn market_with_competitors(prices: &[u64]) -> Market {
+        let mut market = Market::default();
+        for (index, price) in prices.iter().enumerate() {
+            market.offer(
+                batch(1.0),
+                Money::from(*price),
+                Seller::Factory(FactoryId::from(index + 1)),
+                DateTime::from_hours(1),
+            );
+        }
+        market.sort_offers(&mut Xoshiro256PlusPlus::seed_from_u64(0));
+        market
+    }
+
+    #[test]
+    fn cost_plus_rounds_up_the_marked_up_cost() {
+        let mut strategy = CostPlus::new(0.5).unwrap();
+        let market = Market::default();
+        assert_eq!(
+            strategy.price(&batch(10.0), seller(), &market),
+            Money::from(15)
+        );
+        assert_eq!(
+            strategy.price(&batch(3.1), seller(), &market),
+            Money::from(5)
+        );
+        assert_eq!(
+            strategy.price(&batch(0.0), seller(), &market),
+            Money::from(1)
+        );
+        assert!(CostPlus::new(-0.1).is_err());
+    }
+
+    #[test]
+    fn demand_following_raises_the_price_of_sold_out_wares() {
+        let mut strategy = DemandFollowing::new(1.0, 0.1).unwrap();
+        let market = Market::default();
+        assert_eq!(
+            strategy.price(&batch(10.0), seller(), &market),
+            Money::from(20)
+        );
+
+        strategy.record_sales(fig(), 10, 10);
+        assert_eq!(
+            strategy.price(&batch(10.0), seller(), &market),
+            Money::from(22)
+        );
+    }
+
+    #[test]
+    fn demand_following_cuts_the_price_of_leftover_wares() {
+        let mut strategy = DemandFollowing::new(1.0, 0.1).unwrap();
+        let market = Market::default();
+        assert_eq!(
+            strategy.price(&batch(10.0), seller(), &market),
+            Money::from(20)
+        );
+
+        strategy.record_sales(fig(), 10, 9);
+        assert_eq!(
+            strategy.price(&batch(10.0), seller(), &market),
+            Money::from(18)
+        );
+    }
+
+    #[test]
+    fn demand_following_never_cuts_the_price_below_one() {
+        let mut strategy = DemandFollowing::new(0.0, 0.5).unwrap();
+        let market = Market::default();
+        assert_eq!(
+            strategy.price(&batch(1.0), seller(), &market),
+            Money::from(1)
+        );
+
+        for _ in 0..5 {
+            strategy.record_sales(fig(), 10, 0);
+        }
+        assert_eq!(strategy.prices[&fig()], 1.0);
+        assert_eq!(
+            strategy.price(&batch(1.0), seller(), &market),
+            Money::from(1)
+        );
+    }
+
+    #[test]
+    fn competitor_undercut_marks_up_the_cost_without_competitors() {
+        let mut strategy = CompetitorUndercut::new(0.2, Money::from(1)).unwrap();
+        assert_eq!(
+            strategy.price(&batch(10.0), seller(), &market_with_competitors(&[])),
+            Money::from(12)
+        );
+    }
+
+    #[test]
+    fn competitor_undercut_undercuts_the_cheapest_competitor() {
+        let mut strategy = CompetitorUndercut::new(0.2, Money::from(2)).unwrap();
+        let market = market_with_competitors(&[30, 20, 25]);
+        assert_eq!(
+            strategy.price(&batch(10.0), seller(), &market),
+            Money::from(18)
+        );
+    }
+
+    #[test]
+    fn competitor_undercut_ignores_own_offers() {
+        let mut strategy = CompetitorUndercut::new(0.2, Money::from(2)).unwrap();
+        let market = market_with_competitors(&[30, 20]);
+        // The factory with id 2 offers at 20 itself, so the cheapest competitor asks 30.
+        let seller = Seller::Factory(FactoryId::from(2));
+        assert_eq!(
+            strategy.price(&batch(10.0), seller, &market),
+            Money::from(28)
+        );
+    }
+
+    #[test]
+    fn competitor_undercut_never_offers_below_cost_or_one() {
+        let mut strategy = CompetitorUndercut::new(0.2, Money::from(5)).unwrap();
+        assert_eq!(
+            strategy.price(&batch(10.0), seller(), &market_with_competitors(&[12])),
+            Money::from(10)
+        );
+        assert_eq!(
+            strategy.price(&batch(0.0), seller(), &market_with_competitors(&[3])),
+            Money::from(1)
+        );
+    }
+}
@@ -0,0 +1,253 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::Result,
+    money::{ApproximateMoney, Money},
+};
+
+/// The income and expenses of a factory over a period of time.
+///
+/// Wages paid for hours in which the factory produced are part of the sourcing cost of its outputs,
+/// so they become an expense only once the outputs are sold.
+/// Wages paid for hours without production are an expense right away.
+/// Spoiled wares are written off at their sourcing cost, and the holding costs of the warehouses are an expense right away.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub struct IncomeStatement {
+    /// The proceeds of the sales.
+    revenue: Money,
+    /// The sourcing cost of the sold wares.
+    cost_of_goods_sold: ApproximateMoney,
+    /// All wages paid.
+    wages: Money,
+    /// The wages that became part of the sourcing cost of the produced wares.
+    capitalised_wages: Money,
+    /// The sourcing cost of the wares that spoiled.
+    spoilage: ApproximateMoney,
+    /// The cost of storing wares in the warehouses.
+    holding_costs: Money,
+}
+
+/// The assets of a factory at a point in time, and the liabilities and equity they are financed by.
+#[derive(Clone, Copy, Debug)]
+pub struct BalanceSheet {
+    cash: Money,
+    /// The sourcing cost of all stored wares and of the wares offered on the market.
+    inventory: ApproximateMoney,
+    /// The holding costs that are due but were not paid yet.
+    unpaid_holding_costs: Money,
+    /// The money the factory was founded with.
+    paid_in_capital: Money,
+}
+
+impl IncomeStatement {
+    pub fn record_revenue(&mut self, revenue: Money) -> Result<()> {
+        self.revenue = self.revenue.checked_add(revenue)?;
+        Ok(())
+    }
+
+    pub fn record_cost_of_goods_sold(&mut self, cost: ApproximateMoney) {
+        self.cost_of_goods_sold += cost;
+    }
+
+    pub fn record_wages(&mut self, wages: Money) -> Result<()> {
+        self.wages = self.wages.checked_add(wages)?;
+        Ok(())
+    }
+
+    pub fn record_capitalised_wages(&mut self, wages: Money) -> Result<()> {
+        self.capitalised_wages = self.capitalised_wages.checked_add(wages)?;
+        Ok(())
+    }
+
+    pub fn record_spoilage(&mut self, cost: ApproximateMoney) {
+        self.spoilage += cost;
+    }
+
+    pub fn record_holding_costs(&mut self, holding_costs: Money) -> Result<()> {
+        self.holding_costs = self.holding_costs.checked_add(holding_costs)?;
+        Ok(())
+    }
+
+    /// Add the income and expenses of the given statement, such as those of a later period.
+    pub fn accumulate(&mut self, rhs: &Self) -> Result<()> {
+        self.revenue = self.revenue.checked_add(rhs.revenue)?;
+        self.cost_of_goods_sold += rhs.cost_of_goods_sold;
+        self.wages = self.wages.checked_add(rhs.wages)?;
+        self.capitalised_wages = self.capitalised_wages.checked_add(rhs.capitalised_wages)?;
+        self.spoilage += rhs.spoilage;
+        self.holding_costs = self.holding_costs.checked_add(rhs.holding_costs)?;
+        Ok(())
+    }
+
+    pub fn revenue(&self) -> Money {
+        self.revenue
+    }
+
+    pub fn cost_of_goods_sold(&self) -> ApproximateMoney {
+        self.cost_of_goods_sold
+    }
+
+    pub fn wages(&self) -> Money {
+        self.wages
+    }
+
+    pub fn capitalised_wages(&self) -> Money {
+        self.capitalised_wages
+    }
+
+    pub fn spoilage(&self) -> ApproximateMoney {
+        self.spoilage
+    }
+
+    pub fn holding_costs(&self) -> Money {
+        self.holding_costs
+    }
+
+    /// Revenue minus the cost of goods sold.
+    pub fn gross_profit(&self) -> f64 {
+        f64::from(self.revenue) - f64::from(self.cost_of_goods_sold)
+    }
+
+    /// Gross profit minus the wages that were not capitalised, the spoiled wares and the holding costs.
+    pub fn net_profit(&self) -> f64 {
+        self.gross_profit()
+            - (f64::from(self.wages) - f64::from(self.capitalised_wages))
+            - f64::from(self.spoilage)
+            - f64::from(self.holding_costs)
+    }
+}
+
+impl BalanceSheet {
+    pub fn new(
+        cash: Money,
+        inventory: ApproximateMoney,
+        unpaid_holding_costs: Money,
+        paid_in_capital: Money,
+    ) -> Self {
+        Self {
+            cash,
+            inventory,
+            unpaid_holding_costs,
+            paid_in_capital,
+        }
+    }
+
+    pub fn cash(&self) -> Money {
+        self.cash
+    }
+
+    pub fn inventory(&self) -> ApproximateMoney {
+        self.inventory
+    }
+
+    pub fn total_assets(&self) -> f64 {
+        f64::from(self.cash) + f64::from(self.inventory)
+    }
+
+    pub fn unpaid_holding_costs(&self) -> Money {
+        self.unpaid_holding_costs
+    }
+
+    /// The assets minus the liabilities.
+    pub fn equity(&self) -> f64 {
+        self.total_assets() - f64::from(self.unpaid_holding_costs)
+    }
+
+    pub fn paid_in_capital(&self) -> Money {
+        self.paid_in_capital
+    }
+
+    /// The profits that were not paid out as dividends, or the losses if negative.
+    pub fn retained_earnings(&self) -> f64 {
+        self.equity() - f64::from(self.paid_in_capital)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::money::{ApproximateMoney, Money};
+
+    use super::{BalanceSheet, IncomeStatement};
+
+    /// Sold wares for 100 that cost 60 to source.
+    fn statement() -> IncomeStatement {
+        let mut statement = IncomeStatement::default();
+        statement.record_revenue(Money::from(100)).unwrap();
+        statement.record_cost_of_goods_sold(ApproximateMoney::from(60.0));
+        statement
+    }
+
+    #[test]
+    fn gross_profit_is_revenue_minus_cost_of_goods_sold() {
+        assert_eq!(statement().gross_profit(), 40.0);
+        assert_eq!(statement().net_profit(), 40.0);
+    }
+
+    #[test]
+    fn capitalised_wages_leave_net_profit_unchanged() {
+        let mut statement = statement();
+        statement.record_wages(Money::from(30)).unwrap();
+        statement.record_capitalised_wages(Money::from(30)).unwrap();
+        assert_eq!(statement.net_profit(), 40.0);
+
+        // Wages for hours without production are an expense right away.
+        statement.record_wages(Money::from(10)).unwrap();
+        assert_eq!(statement.net_profit(), 30.0);
+        assert_eq!(statement.gross_profit(), 40.0);
+    }
+
+    #[test]
+    fn spoilage_and_holding_costs_reduce_net_profit() {
+        let mut statement = statement();
+        statement.record_spoilage(ApproximateMoney::from(7.5));
+        assert_eq!(statement.net_profit(), 32.5);
+        statement.record_holding_costs(Money::from(12)).unwrap();
+        assert_eq!(statement.net_profit(), 20.5);
+        assert_eq!(statement.gross_profit(), 40.0);
+    }
+
+    #[test]
+    fn accumulated_statements_add_up() {
+        let mut total = statement();
+        let mut later = statement();
+        later.record_wages(Money::from(10)).unwrap();
+        later.record_holding_costs(Money::from(5)).unwrap();
+        total.accumulate(&later).unwrap();
+        assert_eq!(total.revenue(), Money::from(200));
+        assert_eq!(
+            total.net_profit(),
+            statement().net_profit() + later.net_profit()
+        );
+    }
+
+    #[test]
+    fn equity_is_assets_minus_unpaid_holding_costs() {
+        let balance_sheet = BalanceSheet::new(
+            Money::from(500),
+            ApproximateMoney::from(120.5),
+            Money::from(20),
+            Money::from(400),
+        );
+        assert_eq!(balance_sheet.total_assets(), 620.5);
+        assert_eq!(balance_sheet.equity(), 600.5);
+    }
+
+    #[test]
+    fn retained_earnings_are_equity_minus_paid_in_capital() {
+        let profitable = BalanceSheet::new(
+            Money::from(500),
+            ApproximateMoney::from(120.5),
+            Money::from(20),
+            Money::from(400),
+        );
+        assert_eq!(profitable.retained_earnings(), 200.5);
+
+        let unprofitable = BalanceSheet::new(
+            Money::from(100),
+            ApproximateMoney::from(50.0),
+            Money::from(30),
+            Money::from(400),
+        );
+        assert_eq!(unprofitable.retained_earnings(), -280.0);
+    }
+}
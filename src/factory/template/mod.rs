@@ -1,16 +1,26 @@
+use serde::{Deserialize, Serialize};
+
 use crate::{
+    error::{Error, Result},
     market::Market,
     money::{ApproximateMoney, Money},
     recipe::Recipe,
+    warehouse::template::WarehouseTemplate,
 };
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FactoryTemplate {
-    recipe: Recipe,
-    hourly_wages: Money,
+    /// The recipes between which the factory splits its capacity, at least one.
+    recipes: Vec<Recipe>,
+    /// The wage per worker and hour that new factories offer.
+    wage: Money,
+    /// The warehouse for the inputs of the recipes.
+    input_storage: WarehouseTemplate,
+    /// The warehouse for the outputs of the recipes, which also holds the wares offered on the market.
+    output_storage: WarehouseTemplate,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub enum EstimatedProfitMargin {
     /// The ratio between estimated income and estimated expenses.
     ///
@@ -23,60 +33,119 @@ pub enum EstimatedProfitMargin {
 }
 
 impl FactoryTemplate {
-    pub fn new(recipe: Recipe, hourly_wages: Money) -> Self {
-        assert!(hourly_wages % recipe.rate().per_hour() == Money::ZERO);
-        Self {
-            recipe,
-            hourly_wages,
+    pub fn new(recipe: Recipe, wage: Money) -> Result<Self> {
+        if wage == Money::ZERO {
+            return Err(Error::invalid_argument("wage must be positive"));
         }
+        Ok(Self {
+            recipes: vec![recipe],
+            wage,
+            input_storage: Default::default(),
+            output_storage: Default::default(),
+        })
+    }
+
+    /// Allow the factory to apply another recipe.
+    pub fn with_recipe(mut self, recipe: Recipe) -> Self {
+        self.recipes.push(recipe);
+        self
     }
 
-    pub fn recipe(&self) -> &Recipe {
-        &self.recipe
+    pub fn with_input_storage(mut self, input_storage: WarehouseTemplate) -> Self {
+        self.input_storage = input_storage;
+        self
     }
 
-    pub fn hourly_wages(&self) -> Money {
-        self.hourly_wages
+    pub fn with_output_storage(mut self, output_storage: WarehouseTemplate) -> Self {
+        self.output_storage = output_storage;
+        self
     }
 
-    pub fn estimated_profit_margin(&self, market: &Market) -> EstimatedProfitMargin {
-        if self
-            .recipe
-            .inputs()
+    pub fn recipes(&self) -> &[Recipe] {
+        &self.recipes
+    }
+
+    /// The number of workers the factory employs when it produces at full capacity.
+    ///
+    /// This is enough to apply the most labour-intensive recipe at its full rate,
+    /// and the workers are split between the recipes if several are applied.
+    pub fn capacity(&self) -> u64 {
+        self.recipes
             .iter()
-            .any(|input| market.current_price(input.ware()).is_none())
-        {
-            return EstimatedProfitMargin::MissingInput;
-        }
+            .map(Recipe::workers_at_full_rate)
+            .max()
+            .unwrap_or(0)
+    }
 
-        if self
-            .recipe
-            .outputs()
+    /// The names of the recipes, separated by commas.
+    pub fn recipe_names(&self) -> String {
+        self.recipes
             .iter()
-            .any(|output| market.current_price(output.ware()).is_none())
-        {
-            return EstimatedProfitMargin::MissingOutput;
+            .map(Recipe::name)
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    pub fn wage(&self) -> Money {
+        self.wage
+    }
+
+    pub fn input_storage(&self) -> &WarehouseTemplate {
+        &self.input_storage
+    }
+
+    pub fn output_storage(&self) -> &WarehouseTemplate {
+        &self.output_storage
+    }
+
+    /// The best estimated profit margin of the recipes.
+    pub fn estimated_profit_margin(&self, market: &Market) -> Result<EstimatedProfitMargin> {
+        let mut best: Option<EstimatedProfitMargin> = None;
+        for recipe in &self.recipes {
+            let margin = EstimatedProfitMargin::of_recipe(recipe, self.wage, market)?;
+            if best.is_none_or(|best| margin.margin() > best.margin()) {
+                best = Some(margin);
+            }
         }
+        best.ok_or_else(|| Error::invalid_argument("factory template has no recipes"))
+    }
+}
 
-        let hourly_expenses = self.hourly_wages
-            + self
-                .recipe
-                .inputs()
-                .iter()
-                .map(|input| market.current_price(input.ware()).unwrap() * input.amount())
-                .sum::<Money>()
-                * self.recipe.rate().per_hour();
-
-        let hourly_income = self
-            .recipe
-            .outputs()
-            .iter()
-            .map(|output| market.current_price(output.ware()).unwrap() * output.amount())
-            .sum::<Money>()
-            * self.recipe.rate().per_hour();
+impl EstimatedProfitMargin {
+    /// Estimate the profit margin of applying the recipe at its full rate,
+    /// paying the given wage and trading at the current market prices.
+    pub fn of_recipe(recipe: &Recipe, wage: Money, market: &Market) -> Result<Self> {
+        let mut input_cost = Money::ZERO;
+        for input in recipe.inputs() {
+            let Some(price) = market.current_price(input.ware())? else {
+                return Ok(Self::MissingInput);
+            };
+            input_cost = input_cost.checked_add(price.checked_mul(input.amount())?)?;
+        }
 
-        EstimatedProfitMargin::Margin(
+        let mut output_value = Money::ZERO;
+        for output in recipe.outputs() {
+            let Some(price) = market.current_price(output.ware())? else {
+                return Ok(Self::MissingOutput);
+            };
+            output_value = output_value.checked_add(price.checked_mul(output.amount())?)?;
+        }
+
+        let hourly_expenses = wage
+            .checked_mul(recipe.workers_at_full_rate())?
+            .checked_add(input_cost.checked_mul(recipe.rate().per_hour())?)?;
+        let hourly_income = output_value.checked_mul(recipe.rate().per_hour())?;
+
+        Ok(Self::Margin(
             ApproximateMoney::from(hourly_income) / ApproximateMoney::from(hourly_expenses),
-        )
+        ))
+    }
+
+    /// The margin, if it could be estimated.
+    pub fn margin(&self) -> Option<f64> {
+        match self {
+            Self::Margin(margin) => Some(*margin),
+            Self::MissingInput | Self::MissingOutput => None,
+        }
     }
 }
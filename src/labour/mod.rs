@@ -0,0 +1,155 @@
+use rand::{seq::SliceRandom, Rng};
+
+use crate::{consumer::ConsumerId, factory::FactoryId, money::Money};
+
+/// Positions that a factory wants to fill at its current wage.
+#[derive(Debug, Clone, Copy)]
+pub struct JobOffer {
+    factory_id: FactoryId,
+    positions: u64,
+    wage: Money,
+}
+
+/// A household that looks for a job, or for a better paid one.
+#[derive(Debug, Clone, Copy)]
+pub struct JobSeeker {
+    consumer_id: ConsumerId,
+    /// The lowest wage the household accepts.
+    minimum_wage: Money,
+}
+
+impl JobOffer {
+    pub fn new(factory_id: FactoryId, positions: u64, wage: Money) -> Self {
+        Self {
+            factory_id,
+            positions,
+            wage,
+        }
+    }
+
+    pub fn factory_id(&self) -> FactoryId {
+        self.factory_id
+    }
+
+    pub fn positions(&self) -> u64 {
+        self.positions
+    }
+}
+
+impl JobSeeker {
+    pub fn new(consumer_id: ConsumerId, minimum_wage: Money) -> Self {
+        Self {
+            consumer_id,
+            minimum_wage,
+        }
+    }
+
+    pub fn consumer_id(&self) -> ConsumerId {
+        self.consumer_id
+    }
+}
+
+/// Match job seekers with job offers.
+///
+/// The best paid offers are filled first, each by the first job seekers (in random order)
+/// that accept the wage, so factories compete for workers by raising their wages.
+/// Returns the households that were hired, together with their new employer.
+pub fn match_workers(
+    mut job_offers: Vec<JobOffer>,
+    mut job_seekers: Vec<JobSeeker>,
+    rng: &mut impl Rng,
+) -> Vec<(ConsumerId, FactoryId)> {
+    job_offers.shuffle(rng);
+    job_offers.sort_by(|a, b| b.wage.cmp(&a.wage));
+    job_seekers.shuffle(rng);
+
+    let mut hires = Vec::new();
+    for job_offer in job_offers {
+        let mut remaining_positions = job_offer.positions;
+        job_seekers.retain(|job_seeker| {
+            if remaining_positions == 0 || job_seeker.minimum_wage > job_offer.wage {
+                return true;
+            }
+
+            remaining_positions -= 1;
+            hires.push((job_seeker.consumer_id, job_offer.factory_id));
+            false
+        });
+    }
+    hires
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use rand::SeedableRng;
+    use rand_xoshiro::Xoshiro256PlusPlus;
+
+    use crate::{consumer::ConsumerId, factory::FactoryId, money::Money};
+
+    use super::{match_workers, JobOffer, JobSeeker};
+
+    fn job_seekers(minimum_wages: &[u64]) -> Vec<JobSeeker> {
+        minimum_wages
+            .iter()
+            .enumerate()
+            .map(|(id, wage)| JobSeeker::new(ConsumerId::from(id), Money::from(*wage)))
+            .collect()
+    }
+
+    fn employers(hires: &[(ConsumerId, FactoryId)]) -> BTreeMap<usize, usize> {
+        hires
+            .iter()
+            .map(|(consumer_id, factory_id)| ((*consumer_id).into(), (*factory_id).into()))
+            .collect()
+    }
+
+    #[test]
+    fn job_seekers_reject_wages_below_their_reservation_wage() {
+        for seed in 0..10 {
+            let hires = match_workers(
+                vec![JobOffer::new(FactoryId::from(0), 10, Money::from(10))],
+                job_seekers(&[5, 10, 15]),
+                &mut Xoshiro256PlusPlus::seed_from_u64(seed),
+            );
+            assert_eq!(employers(&hires), BTreeMap::from([(0, 0), (1, 0)]));
+        }
+    }
+
+    #[test]
+    fn best_paid_offers_are_filled_first() {
+        for seed in 0..10 {
+            let hires = match_workers(
+                vec![
+                    JobOffer::new(FactoryId::from(0), 2, Money::from(10)),
+                    JobOffer::new(FactoryId::from(1), 2, Money::from(20)),
+                ],
+                job_seekers(&[5, 5, 5]),
+                &mut Xoshiro256PlusPlus::seed_from_u64(seed),
+            );
+            let employers = employers(&hires);
+            assert_eq!(employers.len(), 3);
+            assert_eq!(
+                employers.values().filter(|factory| **factory == 1).count(),
+                2
+            );
+            assert_eq!(
+                employers.values().filter(|factory| **factory == 0).count(),
+                1
+            );
+        }
+    }
+
+    #[test]
+    fn hires_at_most_the_offered_positions() {
+        for seed in 0..10 {
+            let hires = match_workers(
+                vec![JobOffer::new(FactoryId::from(0), 2, Money::from(10))],
+                job_seekers(&[5, 5, 5, 5]),
+                &mut Xoshiro256PlusPlus::seed_from_u64(seed),
+            );
+            assert_eq!(hires.len(), 2);
+        }
+    }
+}
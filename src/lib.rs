@@ -0,0 +1,62 @@
+//! A simulation of an economy of factories and households trading wares on regional markets,
+//! connected by traders that ship wares between the regions.
+//!
+//! Worlds are either loaded from a [scenario] file, or built programmatically:
+//!
+//! ```
+//! use economy_sim::{
+//!     money::Money, recipe::ProductionRate, time::DateTime, Consumer, Factory, FactoryTemplate,
+//!     Recipe, Ware, WareAmount, WareDefinition, World,
+//! };
+//! use rand::SeedableRng;
+//! use rand_xoshiro::Xoshiro256PlusPlus;
+//!
+//! # fn main() -> economy_sim::Result<()> {
+//! let apple = Ware::new(WareDefinition::new("apple").with_plural("apples"));
+//! let orchard = Recipe::new([], [WareAmount::new(apple.clone(), 1)], ProductionRate::new(10));
+//!
+//! let mut builder = World::builder().with_factory(Factory::new(
+//!     FactoryTemplate::new(orchard, Money::from(100))?,
+//!     Money::from(10_000),
+//! ));
+//! for _ in 0..10 {
+//!     let household = Consumer::new(vec![(apple.clone(), 1.0)], 1.0)?.with_hourly_income(Money::from(50));
+//!     builder = builder.with_consumer(household);
+//! }
+//! let mut world = builder.with_invariant_checks().build()?;
+//!
+//! let mut rng = Xoshiro256PlusPlus::seed_from_u64(42);
+//! world.advance_time(DateTime::from_hours(24), &mut rng)?;
+//! assert_eq!(world.time(), DateTime::from_hours(24));
+//! # Ok(())
+//! # }
+//! ```
+
+mod accounting;
+pub mod consumer;
+pub mod error;
+pub mod factory;
+mod labour;
+pub mod market;
+pub mod money;
+pub mod recipe;
+pub mod region;
+pub mod scenario;
+pub mod snapshot;
+pub mod statistics;
+pub mod time;
+pub mod trader;
+pub mod ware;
+pub mod warehouse;
+pub mod world;
+
+pub use consumer::Consumer;
+pub use error::{Error, Result};
+pub use factory::{template::FactoryTemplate, Factory, FactoryId};
+pub use market::Market;
+pub use recipe::Recipe;
+pub use region::{route::Route, Region, RegionId};
+pub use statistics::Statistics;
+pub use trader::{Trader, TraderId};
+pub use ware::{Ware, WareAmount, WareDefinition, WareRegistry};
+pub use world::{World, WorldBuilder};
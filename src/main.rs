@@ -1,29 +1,16 @@
-use std::time::Instant;
+use std::{path::PathBuf, process::ExitCode, time::Instant};
 
 use clap::Parser;
-use consumer::Consumer;
-use factory::{template::FactoryTemplate, Factory};
-use log::{info, LevelFilter};
-use money::Money;
+use economy_sim::{
+    scenario, snapshot,
+    statistics::{self, export::ExportFormat},
+    time::DateTime,
+    Error,
+};
+use log::{error, info, warn, LevelFilter};
 use rand::SeedableRng;
 use rand_xoshiro::Xoshiro256PlusPlus;
-use recipe::{ProductionRate, Recipe};
 use simplelog::TermLogger;
-use statistics::factory_money_statistics::FactoryMoneyStatistics;
-use time::DateTime;
-use ware::{Ware, WareAmount};
-use world::World;
-
-mod consumer;
-mod factory;
-mod market;
-mod money;
-mod recipe;
-mod statistics;
-mod time;
-mod ware;
-mod warehouse;
-mod world;
 
 #[derive(Parser)]
 struct Cli {
@@ -32,9 +19,58 @@ struct Cli {
 
     #[arg(long, short = 'r', default_value = "10")]
     rounds: u64,
+
+    /// The scenario file to load the world from.
+    ///
+    /// If not given, the built-in apple orchard scenario is used.
+    /// When resuming from a snapshot, only the statistics of the scenario are used.
+    #[arg(long, short = 's')]
+    scenario: Option<PathBuf>,
+
+    /// The seed for the random number generator.
+    ///
+    /// Runs with the same seed and scenario produce identical results.
+    /// If not given, a random seed is chosen and logged.
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Save a snapshot of the world every this many hours.
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    save_snapshot_every: Option<u64>,
+
+    /// The directory into which snapshots are saved.
+    #[arg(long, default_value = ".")]
+    snapshot_directory: PathBuf,
+
+    /// Resume the simulation from the given snapshot instead of creating a new world.
+    ///
+    /// The rounds are computed in addition to the rounds already contained in the snapshot.
+    #[arg(long)]
+    resume_from: Option<PathBuf>,
+
+    /// Record every trade and write them to the given CSV file at the end of the simulation.
+    ///
+    /// When resuming from a snapshot that was saved without a trade ledger,
+    /// only the trades made after resuming are written.
+    #[arg(long)]
+    trade_ledger: Option<PathBuf>,
+
+    /// Export the data of the statistics collectors of the given type at the end of the simulation.
+    ///
+    /// Given as `TYPE=FILE`, where the type is the one used in the scenario file, such as `factory_money`.
+    /// The scenario must collect the statistics of the given type exactly once.
+    /// The data is written in long format, as CSV or JSON depending on the file extension.
+    /// May be given multiple times.
+    #[arg(long, value_name = "TYPE=FILE", value_parser = parse_export)]
+    export_statistics: Vec<(String, PathBuf)>,
+
+    /// Check after every hour that the books of the world match the money held by everyone,
+    /// and that no money was created or destroyed unexpectedly.
+    #[arg(long)]
+    check_invariants: bool,
 }
 
-fn main() {
+fn main() -> ExitCode {
     let cli = Cli::parse();
 
     TermLogger::init(
@@ -45,62 +81,102 @@ fn main() {
     )
     .unwrap();
 
-    info!("Creating world");
-    let mut world = World::new(
-        [
-            Factory::new(
-                FactoryTemplate::new(
-                    Recipe::new(
-                        [],
-                        [WareAmount::new(Ware::Water, 10)],
-                        ProductionRate::new(100),
-                    ),
-                    Money::from(100),
-                ),
-                Money::from(10_000),
-            ),
-            Factory::new(
-                FactoryTemplate::new(
-                    Recipe::new([], [WareAmount::new(Ware::Seed, 1)], ProductionRate::new(1)),
-                    Money::from(100),
-                ),
-                Money::from(10_000),
-            ),
-            Factory::new(
-                FactoryTemplate::new(
-                    Recipe::new(
-                        [
-                            WareAmount::new(Ware::Water, 100),
-                            WareAmount::new(Ware::Seed, 1),
-                        ],
-                        [
-                            WareAmount::new(Ware::Apple, 10),
-                            WareAmount::new(Ware::Seed, 2),
-                        ],
-                        ProductionRate::new(10),
-                    ),
-                    Money::from(100),
-                ),
-                Money::from(10_000),
+    match run(&cli) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            error!("{error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(cli: &Cli) -> Result<(), Error> {
+    let (mut world, mut rng) = if let Some(snapshot) = &cli.resume_from {
+        info!("Resuming from snapshot {}", snapshot.display());
+        if cli.seed.is_some() {
+            warn!("Ignoring seed, the rng state is restored from the snapshot");
+        }
+        let (mut world, rng): (_, Xoshiro256PlusPlus) = snapshot::load(snapshot)?;
+
+        if let Some(scenario) = &cli.scenario {
+            info!("Attaching statistics from scenario {}", scenario.display());
+            for statistics in scenario::load_statistics(scenario)? {
+                world.attach_statistics(statistics);
+            }
+        }
+
+        (world, rng)
+    } else {
+        let seed = cli.seed.unwrap_or_else(rand::random);
+        info!("Creating rng with seed {seed}");
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(seed);
+
+        info!("Creating world");
+        let world = match &cli.scenario {
+            Some(scenario) => scenario::load(scenario, &mut rng),
+            None => scenario::parse(
+                scenario::DEFAULT_SCENARIO,
+                scenario::DEFAULT_SCENARIO_FILE,
+                &mut rng,
             ),
-        ],
-        [Consumer::new(
-            WareAmount::new(Ware::Apple, 100),
-            Money::from(11),
-            0.9,
-        )],
-        vec![Box::new(FactoryMoneyStatistics::new("factory_money.svg"))],
-    );
+        }?;
+
+        (world, rng)
+    };
+
+    for (name, _) in &cli.export_statistics {
+        match world
+            .statistics()
+            .filter(|statistics| statistics.name() == name)
+            .count()
+        {
+            0 => {
+                return Err(Error::invalid_argument(format!(
+                    "cannot export {name} statistics, since the scenario does not collect them"
+                )))
+            }
+            1 => {}
+            _ => {
+                return Err(Error::invalid_argument(format!(
+                "cannot export {name} statistics, since the scenario collects them more than once"
+            )))
+            }
+        }
+    }
 
-    info!("Creating rng");
-    let mut rng = Xoshiro256PlusPlus::from_entropy();
+    if cli.trade_ledger.is_some() {
+        world.enable_trade_ledger();
+    }
+
+    if cli.check_invariants {
+        info!("Checking invariants after every hour");
+        world.enable_invariant_checks();
+    }
 
     info!("Computing {} rounds", cli.rounds);
     let start_time = Instant::now();
-    world.advance_time(DateTime::from_hours(cli.rounds), &mut rng);
-    let end_time = Instant::now();
+    let end_time = world.time().checked_add(DateTime::from_hours(cli.rounds))?;
+    while world.time() < end_time {
+        let next_stop = match cli.save_snapshot_every {
+            Some(interval) => {
+                DateTime::from_hours((world.time().into_hours() / interval + 1) * interval)
+                    .min(end_time)
+            }
+            None => end_time,
+        };
+        world.advance_time(next_stop.checked_sub(world.time())?, &mut rng)?;
 
-    let duration = end_time - start_time;
+        if let Some(interval) = cli.save_snapshot_every {
+            if world.time().into_hours() % interval == 0 {
+                let path = cli
+                    .snapshot_directory
+                    .join(format!("snapshot_{}.json", world.time().into_hours()));
+                info!("Saving snapshot {}", path.display());
+                snapshot::save(&path, &world, &rng)?;
+            }
+        }
+    }
+    let duration = Instant::now() - start_time;
     let duration_per_round = duration.as_secs_f64() / cli.rounds as f64;
     info!(
         "Took {}s to compute {} rounds ({}s/round)",
@@ -110,7 +186,40 @@ fn main() {
     );
 
     info!("Finalising statistics");
-    world.finalise_statistics();
+    world.finalise_statistics()?;
+
+    for (name, path) in &cli.export_statistics {
+        let records = world
+            .statistics()
+            .find(|statistics| statistics.name() == name)
+            .map(|statistics| statistics.records())
+            .unwrap_or_default();
+        info!("Exporting {name} statistics to {}", path.display());
+        statistics::export::export(&records, path)?;
+    }
+
+    if let Some(path) = &cli.trade_ledger {
+        info!("Exporting trade ledger to {}", path.display());
+        if let Some(trade_ledger) = world.trade_ledger() {
+            trade_ledger.export_csv(path)?;
+        }
+    }
 
     info!("Done");
+    Ok(())
+}
+
+/// Parse a statistics export given as `TYPE=FILE`.
+fn parse_export(value: &str) -> Result<(String, PathBuf), String> {
+    let (name, path) = value
+        .split_once('=')
+        .ok_or_else(|| format!("expected TYPE=FILE, got `{value}`"))?;
+    let path = PathBuf::from(path);
+    if ExportFormat::from_path(&path).is_none() {
+        return Err(format!(
+            "expected a .csv or .json file, got `{}`",
+            path.display()
+        ));
+    }
+    Ok((name.to_string(), path))
 }
@@ -0,0 +1,255 @@
+use std::{fs::File, io::Write, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{Error, Result},
+    market::{Buyer, Seller, Trade},
+    money::ApproximateMoney,
+    region::RegionId,
+    time::DateTime,
+    ware::Ware,
+};
+
+/// A record of every trade made on the markets of all regions.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct TradeLedger {
+    /// The entries in the order in which the trades were made.
+    entries: Vec<LedgerEntry>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct LedgerEntry {
+    time: DateTime,
+    region: RegionId,
+    trade: Trade,
+}
+
+/// A row of the CSV export.
+#[derive(Serialize)]
+struct CsvRecord<'ledger> {
+    hour: u64,
+    region: usize,
+    ware: &'ledger str,
+    amount: u64,
+    price_per_item: u64,
+    seller_kind: &'static str,
+    seller: usize,
+    buyer_kind: &'static str,
+    buyer: usize,
+}
+
+impl TradeLedger {
+    /// Record the trades made at the given time on the market of the given region.
+    ///
+    /// Trades must be recorded in chronological order.
+    pub fn record(&mut self, time: DateTime, region: RegionId, trades: &[Trade]) -> Result<()> {
+        if let Some(entry) = self.entries.last().filter(|entry| entry.time > time) {
+            return Err(Error::invariant_violation(format!(
+                "trades of hour {time} recorded after trades of hour {}",
+                entry.time
+            )));
+        }
+        self.entries.extend(trades.iter().map(|trade| LedgerEntry {
+            time,
+            region,
+            trade: trade.clone(),
+        }));
+        Ok(())
+    }
+
+    /// The entries of all trades of the ware made at the given time in the given region.
+    fn entries_at<'ledger>(
+        &'ledger self,
+        region: RegionId,
+        ware: &'ledger Ware,
+        time: DateTime,
+    ) -> impl Iterator<Item = &'ledger LedgerEntry> {
+        let start = self.entries.partition_point(|entry| entry.time < time);
+        let end = self.entries.partition_point(|entry| entry.time <= time);
+        self.entries[start..end]
+            .iter()
+            .filter(move |entry| entry.region == region && entry.trade.ware() == ware)
+    }
+
+    /// The amount of the ware traded at the given time in the given region.
+    pub fn volume(&self, region: RegionId, ware: &Ware, time: DateTime) -> u64 {
+        self.entries_at(region, ware, time)
+            .map(|entry| entry.trade.amount())
+            .sum()
+    }
+
+    /// The volume-weighted average price of the ware at the given time in the given region.
+    ///
+    /// Returns `None` if the ware was not traded at that time.
+    pub fn volume_weighted_average_price(
+        &self,
+        region: RegionId,
+        ware: &Ware,
+        time: DateTime,
+    ) -> Option<ApproximateMoney> {
+        let mut volume = 0;
+        let mut total_price = ApproximateMoney::ZERO;
+        for entry in self.entries_at(region, ware, time) {
+            volume += entry.trade.amount();
+            total_price +=
+                ApproximateMoney::from(entry.trade.price_per_item()) * entry.trade.amount();
+        }
+
+        (volume > 0).then(|| total_price / volume)
+    }
+
+    /// Write all trades to the given file in CSV format.
+    pub fn export_csv(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        File::create(path)
+            .map_err(csv::Error::from)
+            .and_then(|file| self.write_csv(file))
+            .map_err(|error| Error::output(path, error))
+    }
+
+    /// Write all trades in CSV format, one row per trade.
+    pub fn write_csv(&self, writer: impl Write) -> Result<(), csv::Error> {
+        let mut writer = csv::Writer::from_writer(writer);
+        for entry in &self.entries {
+            let (seller_kind, seller) = match entry.trade.seller() {
+                Seller::Factory(factory_id) => ("factory", factory_id.into()),
+                Seller::Trader(trader_id) => ("trader", trader_id.into()),
+            };
+            let (buyer_kind, buyer) = match entry.trade.buyer() {
+                Buyer::Factory(factory_id) => ("factory", factory_id.into()),
+                Buyer::Consumer(consumer_id) => ("consumer", consumer_id.into()),
+                Buyer::Trader(trader_id) => ("trader", trader_id.into()),
+            };
+            writer.serialize(CsvRecord {
+                hour: entry.time.into_hours(),
+                region: entry.region.into(),
+                ware: entry.trade.ware().id(),
+                amount: entry.trade.amount(),
+                price_per_item: entry.trade.price_per_item().raw(),
+                seller_kind,
+                seller,
+                buyer_kind,
+                buyer,
+            })?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        consumer::ConsumerId,
+        factory::FactoryId,
+        market::{Buyer, Seller, Trade},
+        money::{ApproximateMoney, Money},
+        region::RegionId,
+        time::DateTime,
+        trader::TraderId,
+        ware::{Ware, WareDefinition},
+    };
+
+    use super::TradeLedger;
+
+    fn kiwi() -> Ware {
+        Ware::new(WareDefinition::new("ledger_test_kiwi"))
+    }
+
+    fn trade(amount: u64, price_per_item: u64, buyer: Buyer) -> Trade {
+        Trade {
+            ware: kiwi(),
+            amount,
+            price_per_item: Money::from(price_per_item),
+            seller: Seller::Factory(FactoryId::from(0)),
+            buyer,
+            produced: DateTime::ZERO,
+        }
+    }
+
+    /// A ledger with trades in region 0 in hours 1 and 3, and in region 1 in hour 3.
+    fn ledger() -> TradeLedger {
+        let mut ledger = TradeLedger::default();
+        let region = RegionId::from(0);
+        ledger
+            .record(
+                DateTime::from_hours(1),
+                region,
+                &[trade(5, 10, Buyer::Consumer(ConsumerId::from(0)))],
+            )
+            .unwrap();
+        ledger
+            .record(
+                DateTime::from_hours(3),
+                region,
+                &[
+                    trade(1, 10, Buyer::Consumer(ConsumerId::from(1))),
+                    trade(3, 20, Buyer::Trader(TraderId::from(2))),
+                ],
+            )
+            .unwrap();
+        ledger
+            .record(
+                DateTime::from_hours(3),
+                RegionId::from(1),
+                &[trade(4, 100, Buyer::Factory(FactoryId::from(3)))],
+            )
+            .unwrap();
+        ledger
+    }
+
+    #[test]
+    fn averages_prices_weighted_by_volume() {
+        let ledger = ledger();
+        let region = RegionId::from(0);
+        let hour = DateTime::from_hours(3);
+        assert_eq!(ledger.volume(region, &kiwi(), hour), 4);
+        assert_eq!(
+            ledger.volume_weighted_average_price(region, &kiwi(), hour),
+            Some(ApproximateMoney::from(17.5))
+        );
+        assert_eq!(
+            ledger.volume_weighted_average_price(region, &kiwi(), DateTime::from_hours(1)),
+            Some(ApproximateMoney::from(10.0))
+        );
+    }
+
+    #[test]
+    fn hours_without_trades_have_no_price() {
+        let ledger = ledger();
+        let region = RegionId::from(0);
+        let hour = DateTime::from_hours(2);
+        assert_eq!(ledger.volume(region, &kiwi(), hour), 0);
+        assert_eq!(
+            ledger.volume_weighted_average_price(region, &kiwi(), hour),
+            None
+        );
+        assert_eq!(
+            TradeLedger::default().volume_weighted_average_price(region, &kiwi(), hour),
+            None
+        );
+    }
+
+    #[test]
+    fn rejects_trades_recorded_out_of_order() {
+        let mut ledger = ledger();
+        assert!(ledger
+            .record(DateTime::from_hours(2), RegionId::from(0), &[])
+            .is_err());
+    }
+
+    #[test]
+    fn writes_one_csv_row_per_trade() {
+        let mut output = Vec::new();
+        ledger().write_csv(&mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "hour,region,ware,amount,price_per_item,seller_kind,seller,buyer_kind,buyer\n\
+             1,0,ledger_test_kiwi,5,10,factory,0,consumer,0\n\
+             3,0,ledger_test_kiwi,1,10,factory,0,consumer,1\n\
+             3,0,ledger_test_kiwi,3,20,factory,0,trader,2\n\
+             3,1,ledger_test_kiwi,4,100,factory,0,factory,3\n"
+        );
+    }
+}
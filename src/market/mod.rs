@@ -1,198 +1,528 @@
-use std::{collections::HashMap, fmt::Display};
+use std::{collections::BTreeMap, fmt::Display, mem};
 
 use rand::{seq::SliceRandom, Rng};
+use serde::{Deserialize, Serialize};
 
 use crate::{
+    consumer::ConsumerId,
+    error::{Error, Result},
     factory::FactoryId,
     money::{ApproximateMoney, Money},
+    time::DateTime,
+    trader::TraderId,
     ware::{Ware, WareAmount},
-    warehouse::Warehouse,
+    warehouse::WarehouseBatch,
 };
 
-#[derive(Debug, Default)]
+pub mod ledger;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct Market {
     market_offers_sorted: bool,
-    offers: HashMap<Ware, Vec<MarketOffer>>,
-    money_transactions: HashMap<FactoryId, Vec<Money>>,
+    offers: BTreeMap<Ware, Vec<MarketOffer>>,
+    /// The ware of each offer on the market.
+    offer_wares: BTreeMap<OfferId, Ware>,
+    next_offer_id: u64,
+    #[serde(with = "serde_seller_map")]
+    money_transactions: BTreeMap<Seller, Vec<Money>>,
+    /// The lowest price per ware of each seller at the time the offers were last sorted.
+    #[serde(with = "serde_seller_map")]
+    seller_prices: BTreeMap<Seller, BTreeMap<Ware, Money>>,
+    /// The bids placed since the market was last cleared.
+    /// Bids only live within an hour and are therefore not part of snapshots.
+    #[serde(skip)]
+    bids: BTreeMap<Ware, Vec<Bid>>,
+    /// The money held back for the bids of each buyer until the purchases are collected.
+    #[serde(skip)]
+    escrow: BTreeMap<Buyer, Money>,
+    /// The trades of each buyer that were not collected yet.
+    #[serde(skip)]
+    purchases: BTreeMap<Buyer, Vec<Trade>>,
+    trades: Vec<Trade>,
+    clearing_prices: BTreeMap<Ware, Money>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct OfferId(u64);
+
+/// A participant of the market that buys wares.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Buyer {
+    Factory(FactoryId),
+    Consumer(ConsumerId),
+    Trader(TraderId),
+}
+
+/// A participant of the market that sells wares.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Seller {
+    Factory(FactoryId),
+    Trader(TraderId),
 }
 
 #[derive(Debug)]
+struct Bid {
+    buyer: Buyer,
+    amount: u64,
+    max_price_per_item: Money,
+}
+
+/// Wares that changed hands when the market was cleared.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Trade {
+    ware: Ware,
+    amount: u64,
+    price_per_item: Money,
+    seller: Seller,
+    buyer: Buyer,
+    /// When the traded wares were produced.
+    produced: DateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 struct MarketOffer {
-    source_factory: FactoryId,
+    id: OfferId,
+    seller: Seller,
     amount: u64,
     price_per_item: Money,
+    sourcing_cost_per_item: ApproximateMoney,
+    produced: DateTime,
+    /// The offer is removed from the market once this time is reached.
+    expires_at: DateTime,
 }
 
 impl Market {
-    pub fn offer(
+    /// Offer the batch on the market until the given time.
+    ///
+    /// The returned id can be used to track and withdraw the offer.
+    pub(crate) fn offer(
         &mut self,
-        ware: Ware,
-        amount: u64,
+        batch: WarehouseBatch,
         price_per_item: Money,
-        source_factory: FactoryId,
-    ) {
+        seller: Seller,
+        expires_at: DateTime,
+    ) -> OfferId {
         self.market_offers_sorted = false;
+        let id = OfferId(self.next_offer_id);
+        self.next_offer_id += 1;
+        self.offer_wares.insert(id, batch.ware().clone());
+
         let offer = MarketOffer {
-            source_factory,
-            amount,
+            id,
+            seller,
+            amount: batch.amount(),
             price_per_item,
+            sourcing_cost_per_item: batch.sourcing_cost_per_item(),
+            produced: batch.produced(),
+            expires_at,
         };
 
-        if let Some(offers) = self.offers.get_mut(&ware) {
+        if let Some(offers) = self.offers.get_mut(batch.ware()) {
             offers.push(offer);
         } else {
-            self.offers.insert(ware, vec![offer]);
+            self.offers.insert(batch.ware().clone(), vec![offer]);
         }
+
+        id
     }
 
     /// Sort market offers by price descending.
     ///
     /// The lowest price will be at the end of the [`Vec`],
     /// and hence the cheapest offer can be bought efficiently with [`Vec::pop`].
-    pub fn sort_offers(&mut self, rng: &mut impl Rng) {
+    pub(crate) fn sort_offers(&mut self, rng: &mut impl Rng) {
         for offers in self.offers.values_mut() {
             offers.shuffle(rng);
             offers.sort_by(|a, b| b.price_per_item.cmp(&a.price_per_item));
         }
         self.market_offers_sorted = true;
+
+        self.seller_prices.clear();
+        for (ware, offers) in &self.offers {
+            // Offers are sorted descending, so later offers of the same seller overwrite earlier ones.
+            for offer in offers {
+                self.seller_prices
+                    .entry(offer.seller)
+                    .or_default()
+                    .insert(ware.clone(), offer.price_per_item);
+            }
+        }
+    }
+
+    /// The amount that is still offered by the given offer.
+    ///
+    /// Returns zero if the offer was sold out, expired or withdrawn.
+    pub fn offered_amount(&self, offer_id: OfferId) -> u64 {
+        self.offer_wares
+            .get(&offer_id)
+            .and_then(|ware| self.offers.get(ware))
+            .and_then(|offers| offers.iter().find(|offer| offer.id == offer_id))
+            .map(|offer| offer.amount)
+            .unwrap_or(0)
     }
 
-    /// Attempt to buy the requested amount of wares and store them into the output warehouse.
+    /// Remove the given offer from the market.
     ///
-    /// The returned value is the actual amount bought,
-    /// which may be lower in case there are not enough offers on the market.
-    pub fn buy(
+    /// Returns the unsold wares with the sourcing cost they were offered with,
+    /// or `None` if the offer does not exist anymore.
+    pub(crate) fn withdraw(&mut self, offer_id: OfferId) -> Option<WarehouseBatch> {
+        let ware = self.offer_wares.remove(&offer_id)?;
+        let offers = self.offers.get_mut(&ware)?;
+        let index = offers.iter().position(|offer| offer.id == offer_id)?;
+        // Use remove instead of swap_remove to keep the offers sorted.
+        let offer = offers.remove(index);
+        Some(offer.unsold_batch(ware))
+    }
+
+    /// Remove all offers that expire at or before the given time.
+    ///
+    /// Returns the unsold wares grouped by the seller that offered them,
+    /// with the sourcing cost they were offered with.
+    pub(crate) fn expire_offers(
         &mut self,
+        time: DateTime,
+    ) -> BTreeMap<Seller, Vec<(OfferId, WarehouseBatch)>> {
+        let mut expired_offers: BTreeMap<_, Vec<_>> = BTreeMap::new();
+        for (ware, offers) in &mut self.offers {
+            offers.retain(|offer| {
+                if offer.expires_at > time {
+                    return true;
+                }
+
+                self.offer_wares.remove(&offer.id);
+                expired_offers
+                    .entry(offer.seller)
+                    .or_default()
+                    .push((offer.id, offer.unsold_batch(ware.clone())));
+                false
+            });
+        }
+        self.offers.retain(|_, offers| !offers.is_empty());
+        expired_offers
+    }
+
+    /// The lowest price at which any other seller offered the ware when the offers were last sorted.
+    pub fn lowest_competitor_price(&self, ware: &Ware, seller: Seller) -> Option<Money> {
+        self.seller_prices
+            .iter()
+            .filter(|(other, _)| **other != seller)
+            .filter_map(|(_, prices)| prices.get(ware).copied())
+            .min()
+    }
+
+    /// The lowest price at which the ware was offered when the offers were last sorted.
+    pub fn lowest_ask(&self, ware: &Ware) -> Option<Money> {
+        self.seller_prices
+            .values()
+            .filter_map(|prices| prices.get(ware).copied())
+            .min()
+    }
+
+    /// The amount of the ware that is offered on the market and not sold yet.
+    pub fn unsold_amount(&self, ware: &Ware) -> u64 {
+        self.offers
+            .get(ware)
+            .into_iter()
+            .flatten()
+            .map(|offer| offer.amount)
+            .sum()
+    }
+
+    /// Place a bid to buy up to the given amount of wares for at most the given price per item.
+    ///
+    /// The maximum cost of the bid is held back from the given money until the buyer
+    /// collects its purchases after the market was cleared.
+    /// Fails if the money does not cover the maximum cost.
+    pub(crate) fn bid(
+        &mut self,
+        buyer: Buyer,
         ware_amount: WareAmount,
-        output_warehouse: &mut Warehouse,
+        max_price_per_item: Money,
         money: &mut Money,
-    ) -> u64 {
-        assert!(self.market_offers_sorted);
-        let mut total_sourcing_cost = Money::ZERO;
-        let bought_amount = if let Some(offers) = self.offers.get_mut(&ware_amount.ware()) {
-            let mut remaining_amount = ware_amount.amount();
-            while remaining_amount > 0 {
-                if let Some(offer) = offers.last_mut() {
-                    let money_limited_amount = *money / offer.price_per_item;
-                    let offer_buy_amount =
-                        offer.amount.min(remaining_amount).min(money_limited_amount);
-                    if offer_buy_amount == 0 {
-                        // Can't buy more with the current money.
-                        break;
-                    }
+    ) -> Result<()> {
+        if ware_amount.amount() == 0 {
+            return Ok(());
+        }
 
-                    let offer_sourcing_cost = offer.price_per_item * offer_buy_amount;
-                    total_sourcing_cost += offer_sourcing_cost;
-                    *money -= offer_sourcing_cost;
-
-                    if let Some(money_transactions) =
-                        self.money_transactions.get_mut(&offer.source_factory)
-                    {
-                        money_transactions.push(offer_sourcing_cost);
-                    } else {
-                        self.money_transactions
-                            .insert(offer.source_factory, vec![offer_sourcing_cost]);
-                    }
+        let max_cost = max_price_per_item.checked_mul(ware_amount.amount())?;
+        if max_cost > *money {
+            return Err(Error::invalid_argument(format!(
+                "bid for {ware_amount} at {max_price_per_item} per item exceeds the available {money}"
+            )));
+        }
+        *money = money.checked_sub(max_cost)?;
+        let escrow = self.escrow.entry(buyer).or_insert(Money::ZERO);
+        *escrow = escrow.checked_add(max_cost)?;
+
+        self.bids
+            .entry(ware_amount.ware().clone())
+            .or_default()
+            .push(Bid {
+                buyer,
+                amount: ware_amount.amount(),
+                max_price_per_item,
+            });
+        Ok(())
+    }
 
-                    offer.amount -= offer_buy_amount;
-                    remaining_amount -= offer_buy_amount;
+    /// Match all bids with the offers on the market in a call auction.
+    ///
+    /// Per ware, the highest bids are matched with the cheapest offers as long as the bid covers the offer price.
+    /// All matched wares are traded at a single clearing price halfway between the marginal offer and bid,
+    /// such that the outcome does not depend on the order in which bids were placed.
+    /// Partially sold offers stay on the market, while unfilled bids are cancelled.
+    pub(crate) fn clear(&mut self, rng: &mut impl Rng) -> Result<()> {
+        self.check_offers_sorted()?;
+        self.trades.clear();
+        self.clearing_prices.clear();
+
+        for (ware, mut bids) in mem::take(&mut self.bids) {
+            let Some(offers) = self.offers.get_mut(&ware) else {
+                continue;
+            };
+            bids.shuffle(rng);
+            bids.sort_by(|a, b| b.max_price_per_item.cmp(&a.max_price_per_item));
 
-                    if offer.amount == 0 {
-                        offers.pop();
+            // Match the highest bids with the cheapest offers.
+            // Offers are sorted descending, so the cheapest offer is at the end.
+            let mut matches = Vec::new();
+            let mut marginal_prices = None;
+            let mut offer_index = offers.len();
+            let mut offer_remaining_amount = 0;
+            'bids: for (bid_index, bid) in bids.iter().enumerate() {
+                let mut bid_remaining_amount = bid.amount;
+                while bid_remaining_amount > 0 {
+                    if offer_remaining_amount == 0 {
+                        if offer_index == 0 {
+                            break 'bids;
+                        }
+                        offer_index -= 1;
+                        offer_remaining_amount = offers[offer_index].amount;
                     }
-                } else {
-                    break;
-                }
-            }
 
-            ware_amount.amount() - remaining_amount
-        } else {
-            0
-        };
+                    let offer = &offers[offer_index];
+                    if offer.price_per_item > bid.max_price_per_item {
+                        break 'bids;
+                    }
 
-        output_warehouse.insert_ware(
-            WareAmount::new(ware_amount.ware(), bought_amount),
-            ApproximateMoney::from(total_sourcing_cost) / bought_amount,
-        );
+                    let amount = bid_remaining_amount.min(offer_remaining_amount);
+                    bid_remaining_amount -= amount;
+                    offer_remaining_amount -= amount;
+                    matches.push((bid_index, offer_index, amount));
+                    marginal_prices = Some((offer.price_per_item, bid.max_price_per_item));
+                }
+            }
 
-        bought_amount
-    }
+            let Some((marginal_offer_price, marginal_bid_price)) = marginal_prices else {
+                continue;
+            };
+            let price_per_item = marginal_offer_price
+                .checked_add(marginal_bid_price)?
+                .checked_div(2)?;
+            self.clearing_prices.insert(ware.clone(), price_per_item);
 
-    pub fn consume_at_current_price(&mut self, ware_amount: WareAmount) -> u64 {
-        assert!(self.market_offers_sorted);
+            for (bid_index, offer_index, amount) in matches {
+                let bid = &bids[bid_index];
+                let offer = &mut offers[offer_index];
+                let cost = price_per_item.checked_mul(amount)?;
+                offer.amount -= amount;
+                let escrow = self.escrow.entry(bid.buyer).or_insert(Money::ZERO);
+                *escrow = escrow.checked_sub(cost)?;
+                self.money_transactions
+                    .entry(offer.seller)
+                    .or_default()
+                    .push(cost);
 
-        let bought_amount = if let Some(offers) = self.offers.get_mut(&ware_amount.ware()) {
-            if let Some(offer) = offers.last_mut() {
-                let offer_buy_amount = offer.amount.min(ware_amount.amount());
-                let offer_sourcing_cost = offer.price_per_item * offer_buy_amount;
+                let trade = Trade {
+                    ware: ware.clone(),
+                    amount,
+                    price_per_item,
+                    seller: offer.seller,
+                    buyer: bid.buyer,
+                    produced: offer.produced,
+                };
+                self.purchases
+                    .entry(bid.buyer)
+                    .or_default()
+                    .push(trade.clone());
+                self.trades.push(trade);
+            }
 
-                if let Some(money_transactions) =
-                    self.money_transactions.get_mut(&offer.source_factory)
-                {
-                    money_transactions.push(offer_sourcing_cost);
+            offers.retain(|offer| {
+                if offer.amount > 0 {
+                    true
                 } else {
-                    self.money_transactions
-                        .insert(offer.source_factory, vec![offer_sourcing_cost]);
+                    self.offer_wares.remove(&offer.id);
+                    false
                 }
+            });
+        }
+        Ok(())
+    }
 
-                offer.amount -= offer_buy_amount;
+    /// Collect the wares bought by the buyer when the market was last cleared.
+    pub(crate) fn collect_purchases(&mut self, buyer: Buyer) -> Vec<Trade> {
+        self.purchases.remove(&buyer).unwrap_or_default()
+    }
 
-                if offer.amount == 0 {
-                    offers.pop();
-                }
+    /// Return the money held back for the bids of the buyer that was not spent,
+    /// and return the refunded amount.
+    pub(crate) fn refund(&mut self, buyer: Buyer, money: &mut Money) -> Result<Money> {
+        let refund = self.escrow.remove(&buyer).unwrap_or(Money::ZERO);
+        *money = money.checked_add(refund)?;
+        Ok(refund)
+    }
 
-                offer_buy_amount
-            } else {
-                0
-            }
-        } else {
-            0
-        };
+    /// The money held by the market, i.e. the money held back for bids
+    /// and the proceeds that were not yet transferred to the sellers.
+    pub fn money(&self) -> Result<Money> {
+        self.escrow
+            .values()
+            .chain(self.money_transactions.values().flatten())
+            .try_fold(Money::ZERO, |sum, money| sum.checked_add(*money))
+    }
 
-        bought_amount
+    /// The trades made when the market was last cleared.
+    pub fn trades(&self) -> &[Trade] {
+        &self.trades
     }
 
-    pub fn total_price(&mut self, ware_amount: WareAmount) -> (u64, Money) {
-        assert!(self.market_offers_sorted);
-        let mut total_sourcing_cost = Money::ZERO;
-        let bought_amount = if let Some(offers) = self.offers.get_mut(&ware_amount.ware()) {
-            let mut remaining_amount = ware_amount.amount();
-            for offer in offers.iter().rev() {
-                let offer_buy_amount = offer.amount.min(remaining_amount);
+    /// The price at which the ware was traded when the market was last cleared.
+    pub fn clearing_price(&self, ware: &Ware) -> Option<Money> {
+        self.clearing_prices.get(ware).copied()
+    }
 
-                let offer_sourcing_cost = offer.price_per_item * offer_buy_amount;
-                total_sourcing_cost += offer_sourcing_cost;
-                remaining_amount -= offer_buy_amount;
+    /// The amount of the ware traded when the market was last cleared.
+    pub fn traded_volume(&self, ware: &Ware) -> u64 {
+        self.trades
+            .iter()
+            .filter(|trade| trade.ware == *ware)
+            .map(|trade| trade.amount)
+            .sum()
+    }
 
-                if remaining_amount == 0 {
-                    break;
-                }
-            }
+    /// The offered amounts of the ware grouped by price, starting with the lowest price.
+    pub fn price_levels(&self, ware: &Ware) -> Result<Vec<(Money, u64)>> {
+        self.price_levels_of(ware, |_| true)
+    }
 
-            ware_amount.amount() - remaining_amount
-        } else {
-            0
-        };
+    /// The amounts of the ware offered by any other seller grouped by price, starting with the lowest price.
+    pub fn competitor_price_levels(
+        &self,
+        ware: &Ware,
+        seller: Seller,
+    ) -> Result<Vec<(Money, u64)>> {
+        self.price_levels_of(ware, |offer| offer.seller != seller)
+    }
+
+    fn price_levels_of(
+        &self,
+        ware: &Ware,
+        include: impl Fn(&MarketOffer) -> bool,
+    ) -> Result<Vec<(Money, u64)>> {
+        self.check_offers_sorted()?;
+        let mut price_levels: Vec<(Money, u64)> = Vec::new();
+        for offer in self.offers.get(ware).into_iter().flatten().rev() {
+            if !include(offer) {
+                continue;
+            }
+            match price_levels.last_mut() {
+                Some((price, amount)) if *price == offer.price_per_item => *amount += offer.amount,
+                _ => price_levels.push((offer.price_per_item, offer.amount)),
+            }
+        }
+        Ok(price_levels)
+    }
 
-        (bought_amount, total_sourcing_cost)
+    /// The price of the most expensive offer required to buy the requested amount of wares.
+    ///
+    /// The returned amount may be lower than requested in case there are not enough offers on the market.
+    pub fn marginal_price(&self, ware_amount: &WareAmount) -> Result<(u64, Money)> {
+        let mut available_amount = 0;
+        let mut marginal_price = Money::ZERO;
+        for (price, amount) in self.price_levels(ware_amount.ware())? {
+            if available_amount >= ware_amount.amount() {
+                break;
+            }
+            available_amount += amount.min(ware_amount.amount() - available_amount);
+            marginal_price = price;
+        }
+        Ok((available_amount, marginal_price))
     }
 
-    pub fn current_price(&self, ware: Ware) -> Option<Money> {
-        assert!(self.market_offers_sorted);
-        match self.offers.get(&ware) {
+    /// The price of the cheapest offer of the ware, if any.
+    pub fn current_price(&self, ware: &Ware) -> Result<Option<Money>> {
+        self.check_offers_sorted()?;
+        Ok(match self.offers.get(ware) {
             Some(offers) => offers.last().map(|offer| offer.price_per_item),
             None => None,
+        })
+    }
+
+    /// Prices can only be looked up while the offers are sorted.
+    fn check_offers_sorted(&self) -> Result<()> {
+        if self.market_offers_sorted {
+            Ok(())
+        } else {
+            Err(Error::invariant_violation(
+                "market offers must be sorted before looking up prices",
+            ))
         }
     }
 
-    pub fn transfer_money(&mut self, money: &mut Money, factory_id: FactoryId) {
-        for money_transaction in self
+    /// Pay the proceeds of the sales of the seller, and return the paid amount.
+    pub(crate) fn transfer_money(&mut self, money: &mut Money, seller: Seller) -> Result<Money> {
+        let proceeds = self
             .money_transactions
-            .remove(&factory_id)
+            .remove(&seller)
             .unwrap_or_default()
-        {
-            *money += money_transaction;
-        }
+            .into_iter()
+            .try_fold(Money::ZERO, Money::checked_add)?;
+        *money = money.checked_add(proceeds)?;
+        Ok(proceeds)
+    }
+}
+
+impl Trade {
+    pub fn ware(&self) -> &Ware {
+        &self.ware
+    }
+
+    pub fn amount(&self) -> u64 {
+        self.amount
+    }
+
+    pub fn ware_amount(&self) -> WareAmount {
+        WareAmount::new(self.ware.clone(), self.amount)
+    }
+
+    pub fn price_per_item(&self) -> Money {
+        self.price_per_item
+    }
+
+    pub fn seller(&self) -> Seller {
+        self.seller
+    }
+
+    pub fn buyer(&self) -> Buyer {
+        self.buyer
+    }
+
+    /// When the traded wares were produced.
+    pub fn produced(&self) -> DateTime {
+        self.produced
+    }
+}
+
+impl MarketOffer {
+    /// The unsold wares of this offer, with the sourcing cost they were offered with.
+    fn unsold_batch(&self, ware: Ware) -> WarehouseBatch {
+        WarehouseBatch::new(
+            ware,
+            self.amount,
+            self.sourcing_cost_per_item,
+            self.produced,
+        )
     }
 }
 
@@ -201,13 +531,166 @@ impl Display for Market {
         write!(f, "Market {{")?;
         let mut once = false;
         for (ware, offers) in &self.offers {
+            let Some(offer) = offers.last() else {
+                continue;
+            };
             if once {
                 write!(f, ", ")?;
             } else {
                 once = true;
             }
-            write!(f, "{ware}: {}", offers.last().unwrap().price_per_item)?;
+            write!(f, "{ware}: {}", offer.price_per_item)?;
         }
         write!(f, "}}")
     }
 }
+
+/// Serialise maps keyed by seller as a list of sellers and values, since JSON only supports string keys.
+mod serde_seller_map {
+    use std::collections::BTreeMap;
+
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::Seller;
+
+    pub fn serialize<S: Serializer, V: Serialize>(
+        map: &BTreeMap<Seller, V>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(map.iter())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>, V: Deserialize<'de>>(
+        deserializer: D,
+    ) -> Result<BTreeMap<Seller, V>, D::Error> {
+        Ok(Vec::<(Seller, V)>::deserialize(deserializer)?
+            .into_iter()
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_xoshiro::Xoshiro256PlusPlus;
+
+    use crate::{
+        consumer::ConsumerId,
+        factory::FactoryId,
+        money::{ApproximateMoney, Money},
+        time::DateTime,
+        ware::{Ware, WareAmount, WareDefinition},
+        warehouse::WarehouseBatch,
+    };
+
+    use super::{Buyer, Market, OfferId, Seller};
+
+    fn plum() -> Ware {
+        Ware::new(WareDefinition::new("market_test_plum"))
+    }
+
+    fn buyer(id: usize) -> Buyer {
+        Buyer::Consumer(ConsumerId::from(id))
+    }
+
+    /// A market offering five plums at 10 and five plums at 20.
+    fn plum_market() -> (Market, OfferId, OfferId) {
+        let mut market = Market::default();
+        let mut offer = |seller: usize, price: u64| {
+            market.offer(
+                WarehouseBatch::new(plum(), 5, ApproximateMoney::from(1), DateTime::ZERO),
+                Money::from(price),
+                Seller::Factory(FactoryId::from(seller)),
+                DateTime::from_hours(1),
+            )
+        };
+        let cheap_offer = offer(0, 10);
+        let expensive_offer = offer(1, 20);
+        market.sort_offers(&mut Xoshiro256PlusPlus::seed_from_u64(0));
+        (market, cheap_offer, expensive_offer)
+    }
+
+    /// Place bids for four plums each at 30, 25 and 15 in the given order, and clear the market.
+    ///
+    /// Returns the amount bought and the money refunded to each buyer.
+    fn clear_with_bids(market: &mut Market, order: [usize; 3], seed: u64) -> Vec<(u64, Money)> {
+        let max_prices = [30, 25, 15];
+        for id in order {
+            let mut money = Money::from(1_000);
+            market
+                .bid(
+                    buyer(id),
+                    WareAmount::new(plum(), 4),
+                    Money::from(max_prices[id]),
+                    &mut money,
+                )
+                .unwrap();
+        }
+        market
+            .clear(&mut Xoshiro256PlusPlus::seed_from_u64(seed))
+            .unwrap();
+
+        (0..3)
+            .map(|id| {
+                let bought = market
+                    .collect_purchases(buyer(id))
+                    .iter()
+                    .map(|trade| trade.amount())
+                    .sum();
+                let mut refund = Money::ZERO;
+                market.refund(buyer(id), &mut refund).unwrap();
+                (bought, refund)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn trades_at_clearing_price_between_marginal_offer_and_bid() {
+        let (mut market, _, _) = plum_market();
+        clear_with_bids(&mut market, [0, 1, 2], 0);
+
+        // The bids at 30 and 25 buy the five plums at 10 and three plums at 20,
+        // while the bid at 15 does not cover the remaining offer.
+        assert_eq!(market.clearing_price(&plum()), Some(Money::from(22)));
+        assert!(market
+            .trades()
+            .iter()
+            .all(|trade| trade.price_per_item() == Money::from(22)));
+        assert_eq!(
+            market
+                .trades()
+                .iter()
+                .map(|trade| trade.amount())
+                .sum::<u64>(),
+            8
+        );
+    }
+
+    #[test]
+    fn partially_filled_offers_stay_and_unfilled_bids_are_refunded() {
+        let (mut market, cheap_offer, expensive_offer) = plum_market();
+        let results = clear_with_bids(&mut market, [0, 1, 2], 0);
+
+        assert_eq!(market.offered_amount(cheap_offer), 0);
+        assert_eq!(market.offered_amount(expensive_offer), 2);
+        assert_eq!(
+            results,
+            vec![
+                (4, Money::from(120 - 4 * 22)),
+                (4, Money::from(100 - 4 * 22)),
+                (0, Money::from(60)),
+            ]
+        );
+        assert_eq!(market.money().unwrap(), Money::from(8 * 22));
+    }
+
+    #[test]
+    fn outcome_does_not_depend_on_bid_order() {
+        let (mut market, _, _) = plum_market();
+        let expected = clear_with_bids(&mut market, [0, 1, 2], 0);
+        for (seed, order) in [[2, 1, 0], [1, 2, 0], [2, 0, 1]].into_iter().enumerate() {
+            let (mut market, _, _) = plum_market();
+            assert_eq!(clear_with_bids(&mut market, order, seed as u64), expected);
+        }
+    }
+}
@@ -1,13 +1,18 @@
 use std::{
     fmt::Display,
-    iter::Sum,
-    ops::{Add, AddAssign, Div, DivAssign, Mul, Rem, Sub, SubAssign},
+    ops::{Add, AddAssign, Div, DivAssign, Mul},
 };
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
 pub struct Money(u64);
 
-#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd, Serialize, Deserialize)]
 pub struct ApproximateMoney(f64);
 
 impl Money {
@@ -17,8 +22,44 @@ impl Money {
         self.0
     }
 
-    pub fn checked_add(self, rhs: Self) -> Option<Self> {
-        self.0.checked_add(rhs.0).map(Self)
+    /// Add money, failing if the sum does not fit.
+    ///
+    /// Money is only added, subtracted, multiplied and divided through the checked methods,
+    /// such that an extreme run reports an overflow instead of aborting.
+    pub fn checked_add(self, rhs: Self) -> Result<Self> {
+        self.0
+            .checked_add(rhs.0)
+            .map(Self)
+            .ok_or(Error::Overflow("money addition"))
+    }
+
+    pub fn checked_sub(self, rhs: Self) -> Result<Self> {
+        self.0
+            .checked_sub(rhs.0)
+            .map(Self)
+            .ok_or(Error::Overflow("money subtraction"))
+    }
+
+    pub fn checked_mul(self, rhs: u64) -> Result<Self> {
+        self.0
+            .checked_mul(rhs)
+            .map(Self)
+            .ok_or(Error::Overflow("money multiplication"))
+    }
+
+    /// Split money into equal parts, rounding down.
+    pub fn checked_div(self, rhs: u64) -> Result<Self> {
+        self.0
+            .checked_div(rhs)
+            .map(Self)
+            .ok_or(Error::DivisionByZero("money division"))
+    }
+
+    /// The number of times the given amount fits into this one, such as the number of items affordable at a price.
+    pub fn checked_div_money(self, rhs: Self) -> Result<u64> {
+        self.0
+            .checked_div(rhs.0)
+            .ok_or(Error::DivisionByZero("money division"))
     }
 
     pub fn saturating_sub(self, rhs: Self) -> Self {
@@ -54,80 +95,6 @@ impl From<Money> for f64 {
     }
 }
 
-impl Mul<u64> for Money {
-    type Output = Money;
-
-    fn mul(self, rhs: u64) -> Self::Output {
-        Self(self.0 * rhs)
-    }
-}
-
-impl Mul<Money> for u64 {
-    type Output = Money;
-
-    fn mul(self, rhs: Money) -> Self::Output {
-        rhs * self
-    }
-}
-
-impl Div<u64> for Money {
-    type Output = Self;
-
-    fn div(self, rhs: u64) -> Self::Output {
-        Self(self.0.checked_div(rhs).unwrap())
-    }
-}
-
-impl Rem<u64> for Money {
-    type Output = Money;
-
-    fn rem(self, rhs: u64) -> Self::Output {
-        Self(self.0 % rhs)
-    }
-}
-
-impl AddAssign for Money {
-    fn add_assign(&mut self, rhs: Self) {
-        self.0 = self.0.checked_add(rhs.0).unwrap();
-    }
-}
-
-impl SubAssign for Money {
-    fn sub_assign(&mut self, rhs: Self) {
-        self.0 = self.0.checked_sub(rhs.0).unwrap()
-    }
-}
-
-impl Add for Money {
-    type Output = Money;
-
-    fn add(self, rhs: Self) -> Self::Output {
-        Self(self.0.checked_add(rhs.0).unwrap())
-    }
-}
-
-impl Sub for Money {
-    type Output = Money;
-
-    fn sub(self, rhs: Self) -> Self::Output {
-        Self(self.0.checked_sub(rhs.0).unwrap())
-    }
-}
-
-impl Div for Money {
-    type Output = u64;
-
-    fn div(self, rhs: Self) -> Self::Output {
-        self.0.checked_div(rhs.0).unwrap()
-    }
-}
-
-impl Sum for Money {
-    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
-        iter.fold(Money::ZERO, |sum, money| sum.checked_add(money).unwrap())
-    }
-}
-
 impl From<f64> for ApproximateMoney {
     fn from(value: f64) -> Self {
         Self(value)
@@ -198,8 +165,13 @@ impl DivAssign<u64> for ApproximateMoney {
 
 impl Display for ApproximateMoney {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        assert!(self.0.is_sign_positive(), "{}", self.0);
-        assert!(self.0.is_normal() || self.0 == 0.0, "{}", self.0);
+        if !self.0.is_finite() {
+            return write!(f, "{}€", self.0);
+        }
+        if self.0 < 0.0 {
+            write!(f, "-")?;
+            return Self(-self.0).fmt(f);
+        }
 
         if self.0 < 1e0 {
             write!(f, "{:.3}€", self.0)
@@ -216,3 +188,36 @@ impl Display for ApproximateMoney {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{ApproximateMoney, Money};
+
+    #[test]
+    fn arithmetic_reports_overflow() {
+        let max = Money::from(u64::MAX);
+        assert!(max.checked_add(Money::from(1)).is_err());
+        assert!(Money::ZERO.checked_sub(Money::from(1)).is_err());
+        assert!(max.checked_mul(2).is_err());
+        assert_eq!(Money::from(3).checked_mul(4).unwrap(), Money::from(12));
+    }
+
+    #[test]
+    fn division_by_zero_is_an_error() {
+        assert!(Money::from(10).checked_div(0).is_err());
+        assert!(Money::from(10).checked_div_money(Money::ZERO).is_err());
+        assert_eq!(Money::from(10).checked_div(3).unwrap(), Money::from(3));
+        assert_eq!(
+            Money::from(10).checked_div_money(Money::from(3)).unwrap(),
+            3
+        );
+    }
+
+    #[test]
+    fn displays_any_approximate_money() {
+        assert_eq!(ApproximateMoney::from(12.34).to_string(), "12.3€");
+        assert_eq!(ApproximateMoney::from(-0.5).to_string(), "-0.500€");
+        assert_eq!(ApproximateMoney::from(f64::NAN).to_string(), "NaN€");
+        assert_eq!(ApproximateMoney::from(f64::INFINITY).to_string(), "inf€");
+    }
+}
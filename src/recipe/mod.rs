@@ -1,15 +1,25 @@
 use std::{fmt::Display, ops::Mul};
 
-use crate::{time::DateTime, ware::WareAmount};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug)]
+use crate::{
+    error::{Error, Result},
+    time::DateTime,
+    ware::WareAmount,
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Recipe {
+    /// The name by which the recipe is shown in statistics.
+    name: String,
     inputs: Vec<WareAmount>,
     outputs: Vec<WareAmount>,
     rate: ProductionRate,
+    /// The number of workers needed to apply the recipe once per hour.
+    workers_per_application: u64,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct ProductionRate {
     per_hour: u64,
 }
@@ -20,17 +30,53 @@ impl Recipe {
         outputs: impl IntoIterator<Item = WareAmount>,
         rate: ProductionRate,
     ) -> Self {
+        let outputs: Vec<WareAmount> = outputs.into_iter().collect();
         Self {
+            name: outputs
+                .iter()
+                .map(|output| output.ware().id())
+                .collect::<Vec<_>>()
+                .join("+"),
             inputs: inputs.into_iter().collect(),
-            outputs: outputs.into_iter().collect(),
+            outputs,
             rate,
+            workers_per_application: 1,
+        }
+    }
+
+    /// Name the recipe, instead of naming it after its outputs.
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    pub fn with_workers_per_application(mut self, workers_per_application: u64) -> Result<Self> {
+        if workers_per_application == 0 {
+            return Err(Error::invalid_argument(
+                "workers per application must be positive",
+            ));
         }
+        self.workers_per_application = workers_per_application;
+        Ok(self)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
     }
 
     pub fn rate(&self) -> ProductionRate {
         self.rate
     }
 
+    pub fn workers_per_application(&self) -> u64 {
+        self.workers_per_application
+    }
+
+    /// The number of workers needed to apply the recipe at its full rate.
+    pub fn workers_at_full_rate(&self) -> u64 {
+        self.rate.per_hour() * self.workers_per_application
+    }
+
     pub fn inputs(&self) -> &[WareAmount] {
         &self.inputs
     }
@@ -64,7 +110,11 @@ impl Mul<DateTime> for ProductionRate {
 
 impl Display for Recipe {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Recipe {{rate: {}, recipe: (", self.rate)?;
+        write!(
+            f,
+            "Recipe {{rate: {}, workers: {}, recipe: (",
+            self.rate, self.workers_per_application
+        )?;
         let mut once = true;
         for input in &self.inputs {
             if once {
@@ -0,0 +1,60 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use crate::market::Market;
+
+pub mod route;
+
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub struct RegionId(usize);
+
+/// A part of the world with its own market, on which the factories and households of the region trade.
+///
+/// Wares only get from one region to another if traders ship them along a [`route::Route`].
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Region {
+    name: String,
+    market: Market,
+}
+
+impl Region {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            market: Default::default(),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn market(&self) -> &Market {
+        &self.market
+    }
+
+    pub(crate) fn market_mut(&mut self) -> &mut Market {
+        &mut self.market
+    }
+}
+
+impl From<usize> for RegionId {
+    fn from(value: usize) -> Self {
+        Self(value)
+    }
+}
+
+impl From<RegionId> for usize {
+    fn from(value: RegionId) -> Self {
+        value.0
+    }
+}
+
+impl Display for RegionId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
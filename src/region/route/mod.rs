@@ -0,0 +1,81 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{Error, Result},
+    money::{ApproximateMoney, Money},
+    time::DateTime,
+};
+
+use super::RegionId;
+
+/// A connection between two regions, along which wares can be shipped in both directions.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Route {
+    regions: (RegionId, RegionId),
+    /// In kilometres.
+    distance: u64,
+    /// The transport cost per item of any ware.
+    cost_per_item: ApproximateMoney,
+    travel_time: DateTime,
+}
+
+impl Route {
+    pub fn new(
+        from: RegionId,
+        to: RegionId,
+        distance: u64,
+        cost_per_item: f64,
+        travel_time: DateTime,
+    ) -> Result<Self> {
+        if from == to {
+            return Err(Error::invalid_argument(format!(
+                "route must connect two different regions, but starts and ends in region {from}"
+            )));
+        }
+        if !(cost_per_item >= 0.0 && cost_per_item.is_finite()) {
+            return Err(Error::invalid_argument(
+                "transport cost per item must not be negative",
+            ));
+        }
+        if travel_time == DateTime::ZERO {
+            return Err(Error::invalid_argument("travel time must be positive"));
+        }
+        Ok(Self {
+            regions: (from, to),
+            distance,
+            cost_per_item: cost_per_item.into(),
+            travel_time,
+        })
+    }
+
+    /// The regions at both ends of the route.
+    pub fn regions(&self) -> (RegionId, RegionId) {
+        self.regions
+    }
+
+    /// The region at the other end of the route, if the route starts or ends in the given region.
+    pub fn destination_from(&self, region: RegionId) -> Option<RegionId> {
+        match self.regions {
+            (from, to) if from == region => Some(to),
+            (from, to) if to == region => Some(from),
+            _ => None,
+        }
+    }
+
+    pub fn distance(&self) -> u64 {
+        self.distance
+    }
+
+    pub fn cost_per_item(&self) -> ApproximateMoney {
+        self.cost_per_item
+    }
+
+    pub fn travel_time(&self) -> DateTime {
+        self.travel_time
+    }
+
+    /// The cost of shipping the given number of items, rounded up to whole money.
+    pub fn transport_cost(&self, amount: u64) -> Money {
+        Money::from((f64::from(self.cost_per_item * amount)).ceil() as u64)
+    }
+}
@@ -0,0 +1,1042 @@
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    fs,
+    ops::Range,
+    path::{Path, PathBuf},
+    slice,
+};
+
+use rand::{distributions::uniform::SampleUniform, Rng};
+use serde::Deserialize;
+use toml::Spanned;
+
+use crate::{
+    consumer::Consumer,
+    factory::{
+        pricing::{CompetitorUndercut, CostPlus, CostPlusHalf, DemandFollowing, PricingStrategy},
+        template::FactoryTemplate,
+        Factory,
+    },
+    money::Money,
+    recipe::{ProductionRate, Recipe},
+    region::{route::Route, Region, RegionId},
+    statistics::{
+        factory_money_statistics::FactoryMoneyStatistics,
+        income_statement_statistics::IncomeStatementStatistics,
+        inventory_statistics::InventoryStatistics, market_price_statistics::MarketPriceStatistics,
+        production_statistics::ProductionStatistics, Statistics,
+    },
+    time::DateTime,
+    trader::Trader,
+    ware::{self, Ware, WareAmount},
+    warehouse::{template::WarehouseTemplate, valuation::InventoryValuation},
+    world::{market_entry::MarketEntry, World},
+};
+
+/// The scenario that is used if no scenario file is given.
+pub const DEFAULT_SCENARIO: &str = include_str!("../../scenarios/apple.toml");
+
+/// The file name reported in errors of the [`DEFAULT_SCENARIO`].
+pub const DEFAULT_SCENARIO_FILE: &str = "scenarios/apple.toml";
+
+/// An error in a scenario file, with the location of the offending value if known.
+#[derive(Debug)]
+pub struct ScenarioError {
+    file: PathBuf,
+    /// One-based line and column.
+    location: Option<(usize, usize)>,
+    message: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ScenarioFile {
+    #[serde(default)]
+    regions: Vec<RegionDefinition>,
+    #[serde(default)]
+    routes: Vec<RouteDefinition>,
+    #[serde(default)]
+    wares: Vec<WareDefinition>,
+    #[serde(default)]
+    recipes: Vec<RecipeDefinition>,
+    #[serde(default)]
+    factory_templates: Vec<FactoryTemplateDefinition>,
+    #[serde(default)]
+    factories: Vec<FactoryDefinition>,
+    #[serde(default)]
+    consumers: Vec<ConsumerDefinition>,
+    #[serde(default)]
+    traders: Vec<TraderDefinition>,
+    market_entry: Option<MarketEntryDefinition>,
+    #[serde(default)]
+    statistics: Vec<StatisticsDefinition>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RegionDefinition {
+    name: Spanned<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RouteDefinition {
+    from: Spanned<String>,
+    to: Spanned<String>,
+    /// In kilometres.
+    distance: u64,
+    /// The transport cost per item of any ware.
+    cost_per_item: Spanned<f64>,
+    /// In hours.
+    travel_time: Spanned<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WareDefinition {
+    id: Spanned<String>,
+    name: Option<String>,
+    plural: Option<String>,
+    #[serde(default = "default_countable")]
+    countable: bool,
+    unit: Option<String>,
+    /// In hours.
+    shelf_life: Option<Spanned<u64>>,
+    /// The fraction of the stored amount that spoils every hour.
+    decay_rate: Option<Spanned<f64>>,
+    /// The storage space taken by one item.
+    volume: Option<Spanned<u64>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WareAmountDefinition {
+    ware: Spanned<String>,
+    amount: Spanned<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RecipeDefinition {
+    name: Spanned<String>,
+    #[serde(default)]
+    inputs: Vec<WareAmountDefinition>,
+    outputs: Spanned<Vec<WareAmountDefinition>>,
+    rate: Spanned<u64>,
+    workers_per_application: Option<Spanned<u64>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FactoryTemplateDefinition {
+    name: Spanned<String>,
+    /// Either a single recipe, or several recipes between which the factory splits its capacity.
+    recipe: Option<Spanned<String>>,
+    #[serde(default)]
+    recipes: Vec<Spanned<String>>,
+    /// The initial wage per worker and hour.
+    wage: Spanned<u64>,
+    input_storage: Option<WarehouseDefinition>,
+    output_storage: Option<WarehouseDefinition>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WarehouseDefinition {
+    /// The total storage space, measured in the volume of the wares.
+    volume: Option<u64>,
+    /// The maximum amounts of individual wares.
+    #[serde(default)]
+    wares: Vec<WareAmountDefinition>,
+    /// Per unit of volume and hour.
+    holding_cost: Option<Spanned<f64>>,
+    valuation: Option<ValuationDefinition>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum ValuationDefinition {
+    Fifo,
+    Lifo,
+    WeightedAverage,
+    OldestProducedFirst,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FactoryDefinition {
+    template: Spanned<String>,
+    /// The first region if not given.
+    region: Option<Spanned<String>>,
+    starting_money: u64,
+    pricing: Option<Spanned<PricingDefinition>>,
+    /// In hours.
+    offer_lifetime: Option<Spanned<u64>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConsumerDefinition {
+    /// The number of households created from this definition.
+    #[serde(default = "default_count")]
+    count: u64,
+    /// The first region if not given.
+    region: Option<Spanned<String>>,
+    basket: Spanned<Vec<BasketDefinition>>,
+    elasticity_of_substitution: Spanned<SampledValue<f64>>,
+    hourly_income: Option<Spanned<SampledValue<u64>>>,
+    savings: Option<Spanned<SampledValue<u64>>>,
+    propensity_to_consume: Option<Spanned<SampledValue<f64>>>,
+    reservation_wage: Option<Spanned<SampledValue<u64>>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BasketDefinition {
+    ware: Spanned<String>,
+    weight: Spanned<SampledValue<f64>>,
+}
+
+/// A value that is either the same for all households, or sampled uniformly for each household.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum SampledValue<T> {
+    Fixed(T),
+    Uniform { min: T, max: T },
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TraderDefinition {
+    /// The number of traders created from this definition.
+    #[serde(default = "default_count")]
+    count: u64,
+    starting_money: u64,
+    minimum_margin: Option<Spanned<f64>>,
+    /// The maximum amount of a ware bought per region and hour.
+    shipment_size: Option<Spanned<u64>>,
+    pricing: Option<Spanned<PricingDefinition>>,
+    /// In hours.
+    offer_lifetime: Option<Spanned<u64>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MarketEntryDefinition {
+    templates: Vec<Spanned<String>>,
+    minimum_profit_margin: Spanned<f64>,
+    starting_money: u64,
+    pricing: Option<Spanned<PricingDefinition>>,
+    /// In hours.
+    offer_lifetime: Option<Spanned<u64>>,
+    maximum_unprofitable_hours: u64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum PricingDefinition {
+    CostPlusHalf,
+    CostPlus { markup: f64 },
+    DemandFollowing { initial_markup: f64, step: f64 },
+    CompetitorUndercut { markup: f64, undercut: u64 },
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum StatisticsDefinition {
+    FactoryMoney {
+        output_file: PathBuf,
+    },
+    IncomeStatements {
+        output_file: PathBuf,
+    },
+    Inventory {
+        output_directory: PathBuf,
+    },
+    MarketPrices {
+        output_file: PathBuf,
+        #[serde(default)]
+        volume_bars: bool,
+    },
+    Production {
+        output_file: PathBuf,
+    },
+}
+
+/// Load a world from the scenario file at the given path.
+///
+/// The random number generator is used to sample the households.
+pub fn load(path: impl AsRef<Path>, rng: &mut impl Rng) -> Result<World, ScenarioError> {
+    let path = path.as_ref();
+    parse(&read(path)?, path, rng)
+}
+
+/// Create a world from the given scenario source.
+///
+/// The `file` is only used for error messages.
+pub fn parse(
+    source: &str,
+    file: impl Into<PathBuf>,
+    rng: &mut impl Rng,
+) -> Result<World, ScenarioError> {
+    let builder = ScenarioBuilder {
+        file: file.into(),
+        source,
+    };
+    let scenario: ScenarioFile =
+        toml::from_str(source).map_err(|error| builder.error(error.span(), error.message()))?;
+    builder.build(scenario, rng)
+}
+
+/// Load only the statistics from the scenario file at the given path.
+pub fn load_statistics(path: impl AsRef<Path>) -> Result<Vec<Box<dyn Statistics>>, ScenarioError> {
+    let path = path.as_ref();
+    let source = read(path)?;
+    let builder = ScenarioBuilder {
+        file: path.into(),
+        source: &source,
+    };
+    let scenario: ScenarioFile =
+        toml::from_str(&source).map_err(|error| builder.error(error.span(), error.message()))?;
+    Ok(builder.build_statistics(scenario.statistics))
+}
+
+fn read(path: &Path) -> Result<String, ScenarioError> {
+    fs::read_to_string(path).map_err(|error| ScenarioError {
+        file: path.into(),
+        location: None,
+        message: format!("cannot read scenario file: {error}"),
+    })
+}
+
+struct ScenarioBuilder<'source> {
+    file: PathBuf,
+    source: &'source str,
+}
+
+impl ScenarioBuilder<'_> {
+    fn build(&self, scenario: ScenarioFile, rng: &mut impl Rng) -> Result<World, ScenarioError> {
+        let mut regions = HashMap::new();
+        for (index, definition) in scenario.regions.iter().enumerate() {
+            if regions
+                .insert(definition.name.get_ref().as_str(), RegionId::from(index))
+                .is_some()
+            {
+                return Err(self.error(
+                    definition.name.span(),
+                    format!("duplicate region `{}`", definition.name.get_ref()),
+                ));
+            }
+        }
+        let routes = scenario
+            .routes
+            .iter()
+            .map(|definition| self.build_route(definition, &regions))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut wares = HashMap::new();
+        for definition in &scenario.wares {
+            let ware = self.build_ware(definition)?;
+            if wares
+                .insert(definition.id.get_ref().as_str(), ware)
+                .is_some()
+            {
+                return Err(self.error(
+                    definition.id.span(),
+                    format!("duplicate ware `{}`", definition.id.get_ref()),
+                ));
+            }
+        }
+
+        let mut recipes = HashMap::new();
+        for definition in &scenario.recipes {
+            let recipe = self.build_recipe(definition, &wares)?;
+            if recipes
+                .insert(definition.name.get_ref().as_str(), recipe)
+                .is_some()
+            {
+                return Err(self.error(
+                    definition.name.span(),
+                    format!("duplicate recipe `{}`", definition.name.get_ref()),
+                ));
+            }
+        }
+
+        let mut factory_templates = HashMap::new();
+        for definition in &scenario.factory_templates {
+            let template = self.build_factory_template(definition, &recipes, &wares)?;
+            if factory_templates
+                .insert(definition.name.get_ref().as_str(), template)
+                .is_some()
+            {
+                return Err(self.error(
+                    definition.name.span(),
+                    format!("duplicate factory template `{}`", definition.name.get_ref()),
+                ));
+            }
+        }
+
+        let factories = scenario
+            .factories
+            .iter()
+            .map(|definition| {
+                let template =
+                    self.lookup(&factory_templates, &definition.template, "factory template")?;
+                let pricing_strategy = self.build_pricing_strategy(definition.pricing.as_ref())?;
+                let offer_lifetime =
+                    self.build_offer_lifetime(definition.offer_lifetime.as_ref())?;
+                let region = self.build_region(definition.region.as_ref(), &regions)?;
+                Factory::new(template.clone(), Money::from(definition.starting_money))
+                    .with_region(region)
+                    .with_pricing_strategy(pricing_strategy)
+                    .with_offer_lifetime(offer_lifetime)
+                    .map_err(|error| self.error(definition.template.span(), error.to_string()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut consumers = Vec::new();
+        for definition in &scenario.consumers {
+            let region = self.build_region(definition.region.as_ref(), &regions)?;
+            for _ in 0..definition.count {
+                consumers.push(
+                    self.build_consumer(definition, &wares, rng)?
+                        .with_region(region),
+                );
+            }
+        }
+
+        let mut builder = World::builder();
+        for definition in scenario.regions {
+            builder = builder.with_region(Region::new(definition.name.into_inner()));
+        }
+        for route in routes {
+            builder = builder.with_route(route);
+        }
+        builder = builder.with_factories(factories).with_consumers(consumers);
+        for definition in &scenario.traders {
+            for _ in 0..definition.count {
+                builder = builder.with_trader(self.build_trader(definition)?);
+            }
+        }
+        for statistics in self.build_statistics(scenario.statistics) {
+            builder = builder.with_statistics(statistics);
+        }
+        if let Some(definition) = &scenario.market_entry {
+            builder =
+                builder.with_market_entry(self.build_market_entry(definition, &factory_templates)?);
+        }
+        builder
+            .build()
+            .map_err(|error| self.error(None, error.to_string()))
+    }
+
+    fn build_statistics(&self, definitions: Vec<StatisticsDefinition>) -> Vec<Box<dyn Statistics>> {
+        definitions
+            .into_iter()
+            .map(|definition| -> Box<dyn Statistics> {
+                match definition {
+                    StatisticsDefinition::FactoryMoney { output_file } => {
+                        Box::new(FactoryMoneyStatistics::new(output_file))
+                    }
+                    StatisticsDefinition::IncomeStatements { output_file } => {
+                        Box::new(IncomeStatementStatistics::new(output_file))
+                    }
+                    StatisticsDefinition::Inventory { output_directory } => {
+                        Box::new(InventoryStatistics::new(output_directory))
+                    }
+                    StatisticsDefinition::MarketPrices {
+                        output_file,
+                        volume_bars,
+                    } => Box::new(
+                        MarketPriceStatistics::new(output_file).with_volume_bars(volume_bars),
+                    ),
+                    StatisticsDefinition::Production { output_file } => {
+                        Box::new(ProductionStatistics::new(output_file))
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /// Look up the region of a factory or household, which is the first region if none is given.
+    fn build_region(
+        &self,
+        definition: Option<&Spanned<String>>,
+        regions: &HashMap<&str, RegionId>,
+    ) -> Result<RegionId, ScenarioError> {
+        match definition {
+            Some(name) => self.lookup(regions, name, "region").copied(),
+            None => Ok(RegionId::default()),
+        }
+    }
+
+    fn build_route(
+        &self,
+        definition: &RouteDefinition,
+        regions: &HashMap<&str, RegionId>,
+    ) -> Result<Route, ScenarioError> {
+        let from = *self.lookup(regions, &definition.from, "region")?;
+        let to = *self.lookup(regions, &definition.to, "region")?;
+        if from == to {
+            return Err(self.error(
+                definition.to.span(),
+                "route must connect two different regions",
+            ));
+        }
+        if *definition.travel_time.get_ref() == 0 {
+            return Err(self.error(
+                definition.travel_time.span(),
+                "travel time must be positive",
+            ));
+        }
+        Route::new(
+            from,
+            to,
+            definition.distance,
+            *definition.cost_per_item.get_ref(),
+            DateTime::from_hours(*definition.travel_time.get_ref()),
+        )
+        .map_err(|error| self.error(definition.cost_per_item.span(), error.to_string()))
+    }
+
+    fn build_trader(&self, definition: &TraderDefinition) -> Result<Trader, ScenarioError> {
+        let mut trader = Trader::new(Money::from(definition.starting_money))
+            .with_pricing_strategy(self.build_pricing_strategy(definition.pricing.as_ref())?)
+            .with_offer_lifetime(self.build_offer_lifetime(definition.offer_lifetime.as_ref())?)
+            .map_err(|error| self.error(None, error.to_string()))?;
+        if let Some(minimum_margin) = &definition.minimum_margin {
+            trader = trader
+                .with_minimum_margin(*minimum_margin.get_ref())
+                .map_err(|error| self.error(minimum_margin.span(), error.to_string()))?;
+        }
+        if let Some(shipment_size) = &definition.shipment_size {
+            trader = trader
+                .with_shipment_size(*shipment_size.get_ref())
+                .map_err(|error| self.error(shipment_size.span(), error.to_string()))?;
+        }
+        Ok(trader)
+    }
+
+    fn build_ware(&self, definition: &WareDefinition) -> Result<Ware, ScenarioError> {
+        let mut ware_definition =
+            ware::WareDefinition::new(definition.id.get_ref()).with_countable(definition.countable);
+        if let Some(name) = &definition.name {
+            ware_definition = ware_definition.with_name(name);
+        }
+        if let Some(plural) = &definition.plural {
+            ware_definition = ware_definition.with_plural(plural);
+        }
+        if let Some(unit) = &definition.unit {
+            ware_definition = ware_definition.with_unit(unit);
+        }
+        if let Some(shelf_life) = &definition.shelf_life {
+            if *shelf_life.get_ref() == 0 {
+                return Err(self.error(shelf_life.span(), "shelf life must be positive"));
+            }
+            ware_definition =
+                ware_definition.with_shelf_life(DateTime::from_hours(*shelf_life.get_ref()));
+        }
+        if let Some(decay_rate) = &definition.decay_rate {
+            ware_definition = ware_definition
+                .with_decay_rate(*decay_rate.get_ref())
+                .map_err(|error| self.error(decay_rate.span(), error.to_string()))?;
+        }
+        if let Some(volume) = &definition.volume {
+            ware_definition = ware_definition
+                .with_volume(*volume.get_ref())
+                .map_err(|error| self.error(volume.span(), error.to_string()))?;
+        }
+
+        Ok(Ware::new(ware_definition))
+    }
+
+    fn build_ware_amount(
+        &self,
+        definition: &WareAmountDefinition,
+        wares: &HashMap<&str, Ware>,
+    ) -> Result<WareAmount, ScenarioError> {
+        let ware = self.lookup(wares, &definition.ware, "ware")?.clone();
+        if *definition.amount.get_ref() == 0 {
+            return Err(self.error(definition.amount.span(), "ware amount must be positive"));
+        }
+        Ok(WareAmount::new(ware, *definition.amount.get_ref()))
+    }
+
+    fn build_recipe(
+        &self,
+        definition: &RecipeDefinition,
+        wares: &HashMap<&str, Ware>,
+    ) -> Result<Recipe, ScenarioError> {
+        if *definition.rate.get_ref() == 0 {
+            return Err(self.error(definition.rate.span(), "production rate must be positive"));
+        }
+        if definition.outputs.get_ref().is_empty() {
+            return Err(self.error(
+                definition.outputs.span(),
+                "recipe must have at least one output",
+            ));
+        }
+
+        let inputs = definition
+            .inputs
+            .iter()
+            .map(|input| self.build_ware_amount(input, wares))
+            .collect::<Result<Vec<_>, _>>()?;
+        let outputs = definition
+            .outputs
+            .get_ref()
+            .iter()
+            .map(|output| self.build_ware_amount(output, wares))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut recipe = Recipe::new(
+            inputs,
+            outputs,
+            ProductionRate::new(*definition.rate.get_ref()),
+        )
+        .with_name(definition.name.get_ref());
+        if let Some(workers_per_application) = &definition.workers_per_application {
+            if *workers_per_application.get_ref() == 0 {
+                return Err(self.error(
+                    workers_per_application.span(),
+                    "workers per application must be positive",
+                ));
+            }
+            recipe = recipe
+                .with_workers_per_application(*workers_per_application.get_ref())
+                .map_err(|error| self.error(workers_per_application.span(), error.to_string()))?;
+        }
+        Ok(recipe)
+    }
+
+    fn build_factory_template(
+        &self,
+        definition: &FactoryTemplateDefinition,
+        recipes: &HashMap<&str, Recipe>,
+        wares: &HashMap<&str, Ware>,
+    ) -> Result<FactoryTemplate, ScenarioError> {
+        if definition.recipe.is_some() && !definition.recipes.is_empty() {
+            return Err(self.error(
+                definition.name.span(),
+                "factory template must not have both `recipe` and `recipes`",
+            ));
+        }
+        let recipe_names = match &definition.recipe {
+            Some(recipe) => slice::from_ref(recipe),
+            None => definition.recipes.as_slice(),
+        };
+        let Some((first, others)) = recipe_names.split_first() else {
+            return Err(self.error(
+                definition.name.span(),
+                "factory template must have a `recipe` or `recipes`",
+            ));
+        };
+        if *definition.wage.get_ref() == 0 {
+            return Err(self.error(definition.wage.span(), "wage must be positive"));
+        }
+
+        let mut template = FactoryTemplate::new(
+            self.lookup(recipes, first, "recipe")?.clone(),
+            Money::from(*definition.wage.get_ref()),
+        )
+        .map_err(|error| self.error(definition.wage.span(), error.to_string()))?;
+        for (index, name) in others.iter().enumerate() {
+            if recipe_names[..=index]
+                .iter()
+                .any(|other| other.get_ref() == name.get_ref())
+            {
+                return Err(self.error(
+                    name.span(),
+                    format!("duplicate recipe `{}` in factory template", name.get_ref()),
+                ));
+            }
+            template = template.with_recipe(self.lookup(recipes, name, "recipe")?.clone());
+        }
+        if let Some(input_storage) = &definition.input_storage {
+            template = template.with_input_storage(self.build_warehouse(input_storage, wares)?);
+        }
+        if let Some(output_storage) = &definition.output_storage {
+            template = template.with_output_storage(self.build_warehouse(output_storage, wares)?);
+        }
+        Ok(template)
+    }
+
+    fn build_warehouse(
+        &self,
+        definition: &WarehouseDefinition,
+        wares: &HashMap<&str, Ware>,
+    ) -> Result<WarehouseTemplate, ScenarioError> {
+        let mut template = WarehouseTemplate::new();
+        if let Some(volume) = definition.volume {
+            template = template.with_volume_capacity(volume);
+        }
+        for capacity in &definition.wares {
+            let capacity = self.build_ware_amount(capacity, wares)?;
+            template = template.with_ware_capacity(capacity.ware().clone(), capacity.amount());
+        }
+        if let Some(holding_cost) = &definition.holding_cost {
+            template = template
+                .with_holding_cost(*holding_cost.get_ref())
+                .map_err(|error| self.error(holding_cost.span(), error.to_string()))?;
+        }
+        if let Some(valuation) = &definition.valuation {
+            template = template.with_valuation(match valuation {
+                ValuationDefinition::Fifo => InventoryValuation::Fifo,
+                ValuationDefinition::Lifo => InventoryValuation::Lifo,
+                ValuationDefinition::WeightedAverage => InventoryValuation::WeightedAverage,
+                ValuationDefinition::OldestProducedFirst => InventoryValuation::OldestProducedFirst,
+            });
+        }
+        Ok(template)
+    }
+
+    fn build_consumer(
+        &self,
+        definition: &ConsumerDefinition,
+        wares: &HashMap<&str, Ware>,
+        rng: &mut impl Rng,
+    ) -> Result<Consumer, ScenarioError> {
+        if definition.basket.get_ref().is_empty() {
+            return Err(self.error(
+                definition.basket.span(),
+                "basket must contain at least one ware",
+            ));
+        }
+        let mut basket: Vec<(Ware, f64)> = Vec::new();
+        for item in definition.basket.get_ref() {
+            let ware = self.lookup(wares, &item.ware, "ware")?.clone();
+            if basket.iter().any(|(basket_ware, _)| *basket_ware == ware) {
+                return Err(self.error(
+                    item.ware.span(),
+                    format!("duplicate ware `{}` in basket", item.ware.get_ref()),
+                ));
+            }
+            let weight = self.sample(&item.weight, rng)?;
+            if !weight.is_finite() || weight <= 0.0 {
+                return Err(self.error(item.weight.span(), "weight must be positive"));
+            }
+            basket.push((ware, weight));
+        }
+        let elasticity_of_substitution =
+            self.sample(&definition.elasticity_of_substitution, rng)?;
+        if !elasticity_of_substitution.is_finite() || elasticity_of_substitution <= 0.0 {
+            return Err(self.error(
+                definition.elasticity_of_substitution.span(),
+                "elasticity of substitution must be positive",
+            ));
+        }
+
+        let mut consumer = Consumer::new(basket, elasticity_of_substitution)
+            .map_err(|error| self.error(definition.basket.span(), error.to_string()))?;
+        if let Some(hourly_income) = &definition.hourly_income {
+            consumer = consumer.with_hourly_income(Money::from(self.sample(hourly_income, rng)?));
+        }
+        if let Some(savings) = &definition.savings {
+            consumer = consumer.with_savings(Money::from(self.sample(savings, rng)?));
+        }
+        if let Some(propensity_to_consume) = &definition.propensity_to_consume {
+            let sampled_propensity_to_consume = self.sample(propensity_to_consume, rng)?;
+            if !(0.0..=1.0).contains(&sampled_propensity_to_consume) {
+                return Err(self.error(
+                    propensity_to_consume.span(),
+                    "propensity to consume must be between 0.0 and 1.0",
+                ));
+            }
+            consumer = consumer
+                .with_propensity_to_consume(sampled_propensity_to_consume)
+                .map_err(|error| self.error(propensity_to_consume.span(), error.to_string()))?;
+        }
+        if let Some(reservation_wage) = &definition.reservation_wage {
+            consumer =
+                consumer.with_reservation_wage(Money::from(self.sample(reservation_wage, rng)?));
+        }
+
+        Ok(consumer)
+    }
+
+    /// Sample a value for a single household.
+    fn sample<T: SampleUniform + PartialOrd + Copy>(
+        &self,
+        value: &Spanned<SampledValue<T>>,
+        rng: &mut impl Rng,
+    ) -> Result<T, ScenarioError> {
+        match *value.get_ref() {
+            SampledValue::Fixed(value) => Ok(value),
+            SampledValue::Uniform { min, max } => {
+                if min <= max {
+                    Ok(rng.gen_range(min..=max))
+                } else {
+                    Err(self.error(value.span(), "minimum must not be greater than maximum"))
+                }
+            }
+        }
+    }
+
+    fn build_market_entry(
+        &self,
+        definition: &MarketEntryDefinition,
+        factory_templates: &HashMap<&str, FactoryTemplate>,
+    ) -> Result<MarketEntry, ScenarioError> {
+        let templates = definition
+            .templates
+            .iter()
+            .map(|template| {
+                self.lookup(factory_templates, template, "factory template")
+                    .cloned()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let minimum_profit_margin = *definition.minimum_profit_margin.get_ref();
+        if !minimum_profit_margin.is_finite() || minimum_profit_margin <= 0.0 {
+            return Err(self.error(
+                definition.minimum_profit_margin.span(),
+                "minimum profit margin must be positive",
+            ));
+        }
+
+        MarketEntry::new(
+            templates,
+            minimum_profit_margin,
+            Money::from(definition.starting_money),
+            self.build_pricing_strategy(definition.pricing.as_ref())?,
+            self.build_offer_lifetime(definition.offer_lifetime.as_ref())?,
+            definition.maximum_unprofitable_hours,
+        )
+        .map_err(|error| self.error(None, error.to_string()))
+    }
+
+    fn build_pricing_strategy(
+        &self,
+        definition: Option<&Spanned<PricingDefinition>>,
+    ) -> Result<Box<dyn PricingStrategy>, ScenarioError> {
+        let Some(definition) = definition else {
+            return Ok(Box::new(CostPlusHalf));
+        };
+
+        let pricing_strategy: Result<Box<dyn PricingStrategy>, _> = match *definition.get_ref() {
+            PricingDefinition::CostPlusHalf => Ok(Box::new(CostPlusHalf)),
+            PricingDefinition::CostPlus { markup } => {
+                CostPlus::new(markup).map(|strategy| Box::new(strategy) as _)
+            }
+            PricingDefinition::DemandFollowing {
+                initial_markup,
+                step,
+            } => DemandFollowing::new(initial_markup, step).map(|strategy| Box::new(strategy) as _),
+            PricingDefinition::CompetitorUndercut { markup, undercut } => {
+                CompetitorUndercut::new(markup, Money::from(undercut))
+                    .map(|strategy| Box::new(strategy) as _)
+            }
+        };
+        pricing_strategy.map_err(|error| self.error(definition.span(), error.to_string()))
+    }
+
+    fn build_offer_lifetime(
+        &self,
+        definition: Option<&Spanned<u64>>,
+    ) -> Result<DateTime, ScenarioError> {
+        match definition {
+            Some(offer_lifetime) if *offer_lifetime.get_ref() == 0 => {
+                Err(self.error(offer_lifetime.span(), "offer lifetime must be positive"))
+            }
+            Some(offer_lifetime) => Ok(DateTime::from_hours(*offer_lifetime.get_ref())),
+            None => Ok(DateTime::from_hours(1)),
+        }
+    }
+
+    fn lookup<'map, T>(
+        &self,
+        map: &'map HashMap<&str, T>,
+        name: &Spanned<String>,
+        kind: &str,
+    ) -> Result<&'map T, ScenarioError> {
+        map.get(name.get_ref().as_str())
+            .ok_or_else(|| self.error(name.span(), format!("unknown {kind} `{}`", name.get_ref())))
+    }
+
+    fn error(
+        &self,
+        span: impl Into<Option<Range<usize>>>,
+        message: impl Into<String>,
+    ) -> ScenarioError {
+        ScenarioError {
+            file: self.file.clone(),
+            location: span
+                .into()
+                .map(|span| line_and_column(self.source, span.start)),
+            message: message.into(),
+        }
+    }
+}
+
+fn default_countable() -> bool {
+    true
+}
+
+fn default_count() -> u64 {
+    1
+}
+
+/// Convert a byte offset into a one-based line and column.
+fn line_and_column(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset.min(source.len())];
+    let line_start = before.rfind('\n').map(|index| index + 1).unwrap_or(0);
+    let line = before.matches('\n').count() + 1;
+    let column = before[line_start..].chars().count() + 1;
+    (line, column)
+}
+
+impl Display for ScenarioError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.file.display())?;
+        if let Some((line, column)) = self.location {
+            write!(f, ":{line}:{column}")?;
+        }
+        write!(f, ": {}", self.message.trim_end())
+    }
+}
+
+impl std::error::Error for ScenarioError {}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_xoshiro::Xoshiro256PlusPlus;
+
+    use super::{parse, ScenarioError};
+
+    fn error(source: &str) -> ScenarioError {
+        parse(
+            source,
+            "test.toml",
+            &mut Xoshiro256PlusPlus::seed_from_u64(0),
+        )
+        .unwrap_err()
+    }
+
+    fn assert_error(source: &str, location: (usize, usize), message: &str) {
+        let error = error(source);
+        assert_eq!(error.location, Some(location), "{error}");
+        assert_eq!(error.message, message);
+        assert_eq!(
+            error.to_string(),
+            format!("test.toml:{}:{}: {message}", location.0, location.1)
+        );
+    }
+
+    #[test]
+    fn reports_unknown_ware() {
+        assert_error(
+            r#"
+[[wares]]
+id = "apple"
+
+[[recipes]]
+name = "orchard"
+outputs = [{ ware = "pear", amount = 1 }]
+rate = 10
+"#,
+            (7, 21),
+            "unknown ware `pear`",
+        );
+    }
+
+    #[test]
+    fn reports_zero_rate_recipe() {
+        assert_error(
+            r#"
+[[wares]]
+id = "apple"
+
+[[recipes]]
+name = "orchard"
+outputs = [{ ware = "apple", amount = 1 }]
+rate = 0
+"#,
+            (8, 8),
+            "production rate must be positive",
+        );
+    }
+
+    /// Wages are paid per worker since the labour market was introduced,
+    /// so they no longer need to be divisible by the production rate, but they must be positive.
+    #[test]
+    fn reports_zero_wage() {
+        assert_error(
+            r#"
+[[wares]]
+id = "apple"
+
+[[recipes]]
+name = "orchard"
+outputs = [{ ware = "apple", amount = 1 }]
+rate = 10
+
+[[factory_templates]]
+name = "orchard"
+recipe = "orchard"
+wage = 0
+"#,
+            (13, 8),
+            "wage must be positive",
+        );
+    }
+
+    #[test]
+    fn reports_duplicate_ids() {
+        assert_error(
+            r#"
+[[wares]]
+id = "apple"
+
+[[wares]]
+id = "apple"
+"#,
+            (6, 6),
+            "duplicate ware `apple`",
+        );
+        assert_error(
+            r#"
+[[wares]]
+id = "apple"
+
+[[recipes]]
+name = "orchard"
+outputs = [{ ware = "apple", amount = 1 }]
+rate = 10
+
+[[recipes]]
+name = "orchard"
+outputs = [{ ware = "apple", amount = 2 }]
+rate = 10
+"#,
+            (11, 8),
+            "duplicate recipe `orchard`",
+        );
+    }
+
+    #[test]
+    fn reports_unknown_template() {
+        assert_error(
+            r#"
+[[wares]]
+id = "apple"
+
+[[recipes]]
+name = "orchard"
+outputs = [{ ware = "apple", amount = 1 }]
+rate = 10
+
+[[factory_templates]]
+name = "orchard"
+recipe = "orchard"
+wage = 10
+
+[[factories]]
+template = "farm"
+starting_money = 1000
+"#,
+            (16, 12),
+            "unknown factory template `farm`",
+        );
+    }
+}
@@ -0,0 +1,182 @@
+use std::{
+    fmt::Display,
+    fs::File,
+    io::{self, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    ware::{Ware, WareDefinition, WareRegistrationError, WareRegistry},
+    world::World,
+};
+
+/// An error while saving or loading a snapshot.
+#[derive(Debug)]
+pub struct SnapshotError {
+    file: PathBuf,
+    kind: SnapshotErrorKind,
+}
+
+#[derive(Debug)]
+enum SnapshotErrorKind {
+    Io(io::Error),
+    Format(serde_json::Error),
+    Ware(WareRegistrationError),
+}
+
+/// The serialised form of a snapshot.
+///
+/// The wares are stored along with the world,
+/// since the world refers to wares by their id.
+#[derive(Serialize)]
+struct SnapshotRef<'snapshot, Rng> {
+    wares: Vec<&'snapshot WareDefinition>,
+    world: &'snapshot World,
+    rng: &'snapshot Rng,
+}
+
+#[derive(Deserialize)]
+struct SnapshotWares {
+    wares: Vec<WareDefinition>,
+}
+
+#[derive(Deserialize)]
+struct Snapshot<Rng> {
+    world: World,
+    rng: Rng,
+}
+
+/// Save the world and the state of the random number generator to the given file.
+///
+/// Statistics are not saved.
+pub fn save<Rng: Serialize>(
+    path: impl AsRef<Path>,
+    world: &World,
+    rng: &Rng,
+) -> Result<(), SnapshotError> {
+    let path = path.as_ref();
+    let error = |kind| SnapshotError {
+        file: path.into(),
+        kind,
+    };
+
+    let mut writer = BufWriter::new(File::create(path).map_err(|e| error(e.into()))?);
+    write(&mut writer, world, rng).map_err(|e| error(e.into()))?;
+    writer.flush().map_err(|e| error(e.into()))
+}
+
+/// Load a world and the state of the random number generator from the given file.
+///
+/// The wares of the snapshot are registered before the world is loaded.
+pub fn load<Rng: DeserializeOwned>(path: impl AsRef<Path>) -> Result<(World, Rng), SnapshotError> {
+    let path = path.as_ref();
+    let file = File::open(path).map_err(|error| SnapshotError {
+        file: path.into(),
+        kind: error.into(),
+    })?;
+    read(BufReader::new(file)).map_err(|kind| SnapshotError {
+        file: path.into(),
+        kind,
+    })
+}
+
+fn write<Rng: Serialize>(
+    writer: impl Write,
+    world: &World,
+    rng: &Rng,
+) -> Result<(), serde_json::Error> {
+    let snapshot = SnapshotRef {
+        wares: world.wares().iter().map(Ware::definition).collect(),
+        world,
+        rng,
+    };
+    serde_json::to_writer(writer, &snapshot)
+}
+
+fn read<Rng: DeserializeOwned>(reader: impl io::Read) -> Result<(World, Rng), SnapshotErrorKind> {
+    // Parse in two steps, since the wares must be registered before the world can be deserialised.
+    let value: Value = serde_json::from_reader(reader)?;
+    let SnapshotWares { wares } = SnapshotWares::deserialize(&value)?;
+    let mut registry = WareRegistry::new();
+    for ware in wares {
+        registry.register(ware)?;
+    }
+
+    let Snapshot { world, rng } = registry.scope(|| Snapshot::deserialize(value))?;
+    Ok((world, rng))
+}
+
+impl From<io::Error> for SnapshotErrorKind {
+    fn from(value: io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl From<serde_json::Error> for SnapshotErrorKind {
+    fn from(value: serde_json::Error) -> Self {
+        Self::Format(value)
+    }
+}
+
+impl From<WareRegistrationError> for SnapshotErrorKind {
+    fn from(value: WareRegistrationError) -> Self {
+        Self::Ware(value)
+    }
+}
+
+impl Display for SnapshotError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: ", self.file.display())?;
+        match &self.kind {
+            SnapshotErrorKind::Io(error) => write!(f, "{error}"),
+            SnapshotErrorKind::Format(error) => write!(f, "invalid snapshot: {error}"),
+            SnapshotErrorKind::Ware(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_xoshiro::Xoshiro256PlusPlus;
+
+    use crate::{money::Money, scenario, time::DateTime, world::World};
+
+    fn factory_money(world: &World) -> Vec<Money> {
+        world
+            .factories()
+            .map(|(_, factory)| factory.money())
+            .collect()
+    }
+
+    #[test]
+    fn resumed_world_behaves_identically() {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(0);
+        let mut world = scenario::parse(
+            scenario::DEFAULT_SCENARIO,
+            scenario::DEFAULT_SCENARIO_FILE,
+            &mut rng,
+        )
+        .unwrap();
+        world
+            .advance_time(DateTime::from_hours(50), &mut rng)
+            .unwrap();
+
+        let mut snapshot = Vec::new();
+        super::write(&mut snapshot, &world, &rng).unwrap();
+        let (mut resumed_world, mut resumed_rng): (World, Xoshiro256PlusPlus) =
+            super::read(snapshot.as_slice()).unwrap();
+        resumed_world.enable_invariant_checks();
+
+        for _ in 0..50 {
+            world.advance_hour(&mut rng).unwrap();
+            resumed_world.advance_hour(&mut resumed_rng).unwrap();
+            assert_eq!(factory_money(&world), factory_money(&resumed_world));
+        }
+    }
+}
@@ -0,0 +1,192 @@
+use std::{
+    fmt::Display,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use serde::Serialize;
+
+use crate::time::DateTime;
+
+/// A single value of a statistics time series in long format.
+#[derive(Debug, Clone, Serialize)]
+pub struct StatisticsRecord {
+    /// The time in hours.
+    time: u64,
+    /// What the value belongs to, such as a factory or a ware.
+    entity: String,
+    metric: String,
+    value: f64,
+}
+
+/// The file formats into which statistics can be exported.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+/// An error while exporting statistics.
+#[derive(Debug)]
+pub struct ExportError {
+    file: PathBuf,
+    kind: ExportErrorKind,
+}
+
+#[derive(Debug)]
+enum ExportErrorKind {
+    UnknownFormat,
+    Io(io::Error),
+    Csv(csv::Error),
+    Json(serde_json::Error),
+}
+
+impl StatisticsRecord {
+    pub fn new(
+        time: DateTime,
+        entity: impl ToString,
+        metric: impl ToString,
+        value: impl Into<f64>,
+    ) -> Self {
+        Self {
+            time: time.into_hours(),
+            entity: entity.to_string(),
+            metric: metric.to_string(),
+            value: value.into(),
+        }
+    }
+
+    pub fn time(&self) -> DateTime {
+        DateTime::from_hours(self.time)
+    }
+
+    pub fn entity(&self) -> &str {
+        &self.entity
+    }
+
+    pub fn metric(&self) -> &str {
+        &self.metric
+    }
+
+    pub fn value(&self) -> f64 {
+        self.value
+    }
+}
+
+impl ExportFormat {
+    /// Choose the format by the extension of the given file.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "csv" => Some(Self::Csv),
+            "json" => Some(Self::Json),
+            _ => None,
+        }
+    }
+}
+
+/// Write the records to the given file, in the format given by its extension.
+pub fn export(records: &[StatisticsRecord], path: impl AsRef<Path>) -> Result<(), ExportError> {
+    let path = path.as_ref();
+    let error = |kind| ExportError {
+        file: path.into(),
+        kind,
+    };
+
+    let format =
+        ExportFormat::from_path(path).ok_or_else(|| error(ExportErrorKind::UnknownFormat))?;
+    let mut writer = BufWriter::new(File::create(path).map_err(|e| error(e.into()))?);
+    write(records, format, &mut writer).map_err(error)?;
+    writer.flush().map_err(|e| error(e.into()))
+}
+
+/// Write the records in the given format.
+///
+/// CSV has one row per record, JSON is a list of objects.
+fn write(
+    records: &[StatisticsRecord],
+    format: ExportFormat,
+    writer: impl Write,
+) -> Result<(), ExportErrorKind> {
+    match format {
+        ExportFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(writer);
+            for record in records {
+                writer.serialize(record)?;
+            }
+            writer.flush()?;
+        }
+        ExportFormat::Json => serde_json::to_writer(writer, records)?,
+    }
+    Ok(())
+}
+
+impl From<io::Error> for ExportErrorKind {
+    fn from(value: io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl From<csv::Error> for ExportErrorKind {
+    fn from(value: csv::Error) -> Self {
+        Self::Csv(value)
+    }
+}
+
+impl From<serde_json::Error> for ExportErrorKind {
+    fn from(value: serde_json::Error) -> Self {
+        Self::Json(value)
+    }
+}
+
+impl Display for ExportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: ", self.file.display())?;
+        match &self.kind {
+            ExportErrorKind::UnknownFormat => {
+                write!(f, "unknown export format, expected a .csv or .json file")
+            }
+            ExportErrorKind::Io(error) => write!(f, "{error}"),
+            ExportErrorKind::Csv(error) => write!(f, "{error}"),
+            ExportErrorKind::Json(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl std::error::Error for ExportError {}
+
+#[cfg(test)]
+mod tests {
+    use crate::time::DateTime;
+
+    use super::{write, ExportFormat, StatisticsRecord};
+
+    fn records() -> Vec<StatisticsRecord> {
+        vec![
+            StatisticsRecord::new(DateTime::from_hours(1), "apple", "lowest_ask", 12.0),
+            StatisticsRecord::new(DateTime::from_hours(2), 3, "money", 0.5),
+        ]
+    }
+
+    fn written(format: ExportFormat) -> String {
+        let mut output = Vec::new();
+        write(&records(), format, &mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn writes_one_csv_row_per_record() {
+        assert_eq!(
+            written(ExportFormat::Csv),
+            "time,entity,metric,value\n1,apple,lowest_ask,12.0\n2,3,money,0.5\n"
+        );
+    }
+
+    #[test]
+    fn writes_json_list_of_records() {
+        assert_eq!(
+            written(ExportFormat::Json),
+            r#"[{"time":1,"entity":"apple","metric":"lowest_ask","value":12.0},{"time":2,"entity":"3","metric":"money","value":0.5}]"#
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use std::{collections::HashMap, path::PathBuf};
+use std::{collections::BTreeMap, error, path::PathBuf};
 
 use log::debug;
 use plotters::{
@@ -8,14 +8,20 @@ use plotters::{
     style::{IntoFont, BLACK, BLUE, CYAN, GREEN, MAGENTA, RED, TRANSPARENT, WHITE, YELLOW},
 };
 
-use crate::{factory::FactoryId, money::Money, time::DateTime, world::World};
+use crate::{
+    error::{Error, Result},
+    factory::FactoryId,
+    money::Money,
+    time::DateTime,
+    world::World,
+};
 
-use super::Statistics;
+use super::{export::StatisticsRecord, Statistics};
 
 #[derive(Debug)]
 pub struct FactoryMoneyStatistics {
     output_file: PathBuf,
-    money_time_series: HashMap<FactoryId, Vec<(DateTime, Money)>>,
+    money_time_series: BTreeMap<FactoryId, Vec<(DateTime, Money)>>,
 }
 
 impl FactoryMoneyStatistics {
@@ -39,14 +45,38 @@ impl Statistics for FactoryMoneyStatistics {
         }
     }
 
-    fn finalise(&self) {
+    fn name(&self) -> &'static str {
+        "factory_money"
+    }
+
+    fn records(&self) -> Vec<StatisticsRecord> {
+        self.money_time_series
+            .iter()
+            .flat_map(|(factory_id, money_time_series)| {
+                money_time_series.iter().map(move |(time, money)| {
+                    StatisticsRecord::new(*time, format!("factory_{factory_id}"), "money", *money)
+                })
+            })
+            .collect()
+    }
+
+    fn finalise(&self) -> Result<()> {
+        self.draw()
+            .map_err(|error| Error::output(&self.output_file, error))
+    }
+}
+
+impl FactoryMoneyStatistics {
+    fn draw(&self) -> Result<(), Box<dyn error::Error>> {
         let mut iter = self
             .money_time_series
             .values()
             .flat_map(|money_time_series| money_time_series.iter())
             .copied();
         //.map(|(time, money)| (time.into_hours() as f64, f64::from(*money)));
-        let (first_time, first_money) = iter.next().unwrap();
+        let Some((first_time, first_money)) = iter.next() else {
+            return Ok(());
+        };
         let (min_time, max_time, min_money, max_money) = iter.fold(
             (first_time, first_time, first_money, first_money),
             |(min_time, max_time, min_money, max_money), (time, money)| {
@@ -58,17 +88,17 @@ impl Statistics for FactoryMoneyStatistics {
                 )
             },
         );
-        let time_margin = (max_time - min_time) / 20;
-        let money_margin = (max_money - min_money) / 20;
+        let time_margin = max_time.saturating_sub(min_time).checked_div(20)?;
+        let money_margin = max_money.saturating_sub(min_money).checked_div(20)?;
         let chart_min_time = min_time.saturating_sub(time_margin);
-        let chart_max_time = max_time + time_margin;
+        let chart_max_time = max_time.checked_add(time_margin)?;
         let chart_min_money = min_money.saturating_sub(money_margin);
-        let chart_max_money = max_money + money_margin;
+        let chart_max_money = max_money.checked_add(money_margin)?;
 
         debug!("Drawing factory money statistics in area x: {chart_min_time}..{chart_max_time}; y: {chart_min_money}..{chart_max_money}");
 
         let root = SVGBackend::new(&self.output_file, (640, 480)).into_drawing_area();
-        root.fill(&TRANSPARENT).unwrap();
+        root.fill(&TRANSPARENT)?;
 
         let styles = [RED, GREEN, BLUE, BLACK, CYAN, MAGENTA, YELLOW];
         let mut chart = ChartBuilder::on(&root)
@@ -79,23 +109,22 @@ impl Statistics for FactoryMoneyStatistics {
             .build_cartesian_2d(
                 chart_min_time.into_hours()..chart_max_time.into_hours(),
                 chart_min_money.raw()..chart_max_money.raw(),
-            )
-            .unwrap();
+            )?;
         chart
             .configure_mesh()
             .y_label_formatter(&format_money)
-            .draw()
-            .unwrap();
+            .draw()?;
 
-        for ((factory_id, series), style) in self.money_time_series.iter().zip(&styles) {
+        for ((factory_id, series), style) in
+            self.money_time_series.iter().zip(styles.iter().cycle())
+        {
             chart
                 .draw_series(LineSeries::new(
                     series
                         .iter()
                         .map(|(time, money)| (time.into_hours(), money.raw())),
                     style,
-                ))
-                .unwrap()
+                ))?
                 .label(format!("Factory {factory_id}"))
                 .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], *style));
         }
@@ -105,8 +134,8 @@ impl Statistics for FactoryMoneyStatistics {
             .background_style(WHITE)
             .border_style(BLACK)
             .position(SeriesLabelPosition::LowerRight)
-            .draw()
-            .unwrap();
+            .draw()?;
+        Ok(())
     }
 }
 
@@ -0,0 +1,204 @@
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::PathBuf,
+};
+
+use crate::{
+    error::{Error, Result},
+    factory::{
+        report::{BalanceSheet, IncomeStatement},
+        FactoryId,
+    },
+    time::DateTime,
+    world::World,
+};
+
+use super::{export::StatisticsRecord, Statistics};
+
+/// Write an income statement and a balance sheet of every factory to a text file.
+#[derive(Debug)]
+pub struct IncomeStatementStatistics {
+    output_file: PathBuf,
+    reports: BTreeMap<FactoryId, FactoryReport>,
+    time: DateTime,
+}
+
+/// The figures of a factory.
+#[derive(Debug)]
+struct FactoryReport {
+    founded: DateTime,
+    recipe: String,
+    /// The income statement of each hour, with the balance sheet at its end.
+    history: Vec<(DateTime, IncomeStatement, BalanceSheet)>,
+    cumulative: IncomeStatement,
+}
+
+impl IncomeStatementStatistics {
+    pub fn new(output_file: impl Into<PathBuf>) -> Self {
+        Self {
+            output_file: output_file.into(),
+            reports: Default::default(),
+            time: DateTime::ZERO,
+        }
+    }
+
+    fn write(&self, mut writer: impl Write) -> io::Result<()> {
+        for (factory_id, report) in &self.reports {
+            let Some((last_seen, last_hour, balance_sheet)) = report.history.last() else {
+                continue;
+            };
+            let hours = last_seen.saturating_sub(report.founded).into_hours().max(1);
+            write!(
+                writer,
+                "Factory {factory_id} ({}), founded at hour {}",
+                report.recipe,
+                report.founded.into_hours()
+            )?;
+            if *last_seen < self.time {
+                write!(writer, ", closed at hour {}", last_seen.into_hours())?;
+            }
+            writeln!(writer)?;
+
+            writeln!(
+                writer,
+                "  {:<28}{:>14}{:>14}{:>14}",
+                "Income statement", "last hour", "total", "per hour"
+            )?;
+            let total = &report.cumulative;
+            let rows = [
+                (
+                    "Revenue",
+                    last_hour.revenue().into(),
+                    total.revenue().into(),
+                ),
+                (
+                    "Cost of goods sold",
+                    last_hour.cost_of_goods_sold().into(),
+                    total.cost_of_goods_sold().into(),
+                ),
+                (
+                    "Gross profit",
+                    last_hour.gross_profit(),
+                    total.gross_profit(),
+                ),
+                ("Wages", last_hour.wages().into(), total.wages().into()),
+                (
+                    "  capitalised in inventory",
+                    last_hour.capitalised_wages().into(),
+                    total.capitalised_wages().into(),
+                ),
+                (
+                    "Spoilage",
+                    last_hour.spoilage().into(),
+                    total.spoilage().into(),
+                ),
+                (
+                    "Holding costs",
+                    last_hour.holding_costs().into(),
+                    total.holding_costs().into(),
+                ),
+                ("Net profit", last_hour.net_profit(), total.net_profit()),
+            ];
+            for (name, last_hour, total) in rows {
+                writeln!(
+                    writer,
+                    "  {name:<28}{last_hour:>13.2}€{total:>13.2}€{:>13.2}€",
+                    total / hours as f64,
+                )?;
+            }
+
+            writeln!(writer, "  Balance sheet at hour {}", last_seen.into_hours())?;
+            let rows = [
+                ("Cash", balance_sheet.cash().into()),
+                ("Inventory", balance_sheet.inventory().into()),
+                ("Total assets", balance_sheet.total_assets()),
+                (
+                    "Unpaid holding costs",
+                    balance_sheet.unpaid_holding_costs().into(),
+                ),
+                ("Equity", balance_sheet.equity()),
+                ("Paid-in capital", balance_sheet.paid_in_capital().into()),
+                ("Retained earnings", balance_sheet.retained_earnings()),
+            ];
+            for (name, value) in rows {
+                writeln!(writer, "  {name:<28}{value:>13.2}€")?;
+            }
+            writeln!(writer)?;
+        }
+        Ok(())
+    }
+}
+
+impl Statistics for IncomeStatementStatistics {
+    fn collect(&mut self, world: &World) {
+        self.time = world.time();
+        for (factory_id, factory) in world.factories() {
+            let report = self
+                .reports
+                .entry(factory_id)
+                .or_insert_with(|| FactoryReport {
+                    founded: world.time(),
+                    recipe: factory.template().recipe_names(),
+                    history: Vec::new(),
+                    cumulative: Default::default(),
+                });
+            report.history.push((
+                world.time(),
+                *factory.income_statement(),
+                factory.balance_sheet(),
+            ));
+            report.cumulative = *factory.cumulative_income_statement();
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "income_statements"
+    }
+
+    fn records(&self) -> Vec<StatisticsRecord> {
+        let mut records = Vec::new();
+        for (factory_id, report) in &self.reports {
+            let entity = format!("factory_{factory_id}");
+            for (time, income_statement, balance_sheet) in &report.history {
+                let metrics = [
+                    ("revenue", income_statement.revenue().into()),
+                    (
+                        "cost_of_goods_sold",
+                        income_statement.cost_of_goods_sold().into(),
+                    ),
+                    ("gross_profit", income_statement.gross_profit()),
+                    ("wages", income_statement.wages().into()),
+                    (
+                        "capitalised_wages",
+                        income_statement.capitalised_wages().into(),
+                    ),
+                    ("spoilage", income_statement.spoilage().into()),
+                    ("holding_costs", income_statement.holding_costs().into()),
+                    ("net_profit", income_statement.net_profit()),
+                    ("cash", balance_sheet.cash().into()),
+                    ("inventory", balance_sheet.inventory().into()),
+                    (
+                        "unpaid_holding_costs",
+                        balance_sheet.unpaid_holding_costs().into(),
+                    ),
+                ];
+                for (metric, value) in metrics {
+                    records.push(StatisticsRecord::new(*time, &entity, metric, value));
+                }
+            }
+        }
+        records
+    }
+
+    fn finalise(&self) -> Result<()> {
+        File::create(&self.output_file)
+            .and_then(|file| {
+                let mut writer = BufWriter::new(file);
+                self.write(&mut writer)?;
+                writer.flush()
+            })
+            .map_err(|error| Error::output(&self.output_file, error))
+    }
+}
@@ -0,0 +1,426 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    error,
+    fmt::Display,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use log::debug;
+use plotters::{
+    chart::{ChartBuilder, SeriesLabelPosition},
+    coord::Shift,
+    element::Rectangle,
+    prelude::{DrawingArea, IntoDrawingArea, SVGBackend},
+    series::AreaSeries,
+    style::{Color, IntoFont, BLACK, BLUE, CYAN, GREEN, MAGENTA, RED, TRANSPARENT, WHITE, YELLOW},
+};
+
+use crate::{
+    error::{Error, Result},
+    factory::FactoryId,
+    time::DateTime,
+    trader::TraderId,
+    ware::Ware,
+    warehouse::StockLevel,
+    world::World,
+};
+
+use super::{export::StatisticsRecord, Statistics};
+
+/// Draw the stock levels of each factory and trader as stacked areas, one chart per factory or trader.
+///
+/// The upper chart shows the amount of each ware in stock, the lower one its sourcing cost.
+/// The wares of traders are split into the wares in transit and the wares that arrived in any region.
+#[derive(Debug)]
+pub struct InventoryStatistics {
+    output_directory: PathBuf,
+    time_series: BTreeMap<StockHolder, Vec<InventoryRecord>>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum StockHolder {
+    Factory(FactoryId),
+    Trader(TraderId),
+}
+
+#[derive(Debug)]
+struct InventoryRecord {
+    time: DateTime,
+    stock_levels: BTreeMap<(StockKind, Ware), StockLevel>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum StockKind {
+    Input,
+    Output,
+    InTransit,
+    Arrived,
+}
+
+impl InventoryStatistics {
+    pub fn new(output_directory: impl Into<PathBuf>) -> Self {
+        Self {
+            output_directory: output_directory.into(),
+            time_series: Default::default(),
+        }
+    }
+}
+
+impl Statistics for InventoryStatistics {
+    fn collect(&mut self, world: &World) {
+        for (factory_id, factory) in world.factories() {
+            let inputs = factory
+                .input_stock()
+                .into_iter()
+                .map(|(ware, stock_level)| ((StockKind::Input, ware), stock_level));
+            let outputs = factory
+                .output_stock()
+                .into_iter()
+                .map(|(ware, stock_level)| ((StockKind::Output, ware), stock_level));
+            self.time_series
+                .entry(StockHolder::Factory(factory_id))
+                .or_default()
+                .push(InventoryRecord {
+                    time: world.time(),
+                    stock_levels: inputs.chain(outputs).collect(),
+                });
+        }
+        for (trader_id, trader) in world.traders() {
+            let mut stock_levels: BTreeMap<_, StockLevel> = trader
+                .stock_in_transit()
+                .into_iter()
+                .map(|(ware, stock_level)| ((StockKind::InTransit, ware), stock_level))
+                .collect();
+            for (region_id, _) in world.regions() {
+                for (ware, stock_level) in trader.stock(region_id) {
+                    *stock_levels.entry((StockKind::Arrived, ware)).or_default() += stock_level;
+                }
+            }
+            self.time_series
+                .entry(StockHolder::Trader(trader_id))
+                .or_default()
+                .push(InventoryRecord {
+                    time: world.time(),
+                    stock_levels,
+                });
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "inventory"
+    }
+
+    fn records(&self) -> Vec<StatisticsRecord> {
+        let mut records = Vec::new();
+        for (holder, series) in &self.time_series {
+            let entity = holder.entity();
+            for record in series {
+                for ((kind, ware), stock_level) in &record.stock_levels {
+                    let ware = ware.id();
+                    records.push(StatisticsRecord::new(
+                        record.time,
+                        &entity,
+                        format!("{kind}_amount_{ware}"),
+                        stock_level.amount() as f64,
+                    ));
+                    records.push(StatisticsRecord::new(
+                        record.time,
+                        &entity,
+                        format!("{kind}_value_{ware}"),
+                        stock_level.value(),
+                    ));
+                }
+            }
+        }
+        records
+    }
+
+    fn finalise(&self) -> Result<()> {
+        fs::create_dir_all(&self.output_directory)
+            .map_err(|error| Error::output(&self.output_directory, error))?;
+
+        for (holder, records) in &self.time_series {
+            let output_file = self
+                .output_directory
+                .join(format!("inventory_{}.svg", holder.entity()));
+            debug!(
+                "Drawing inventory statistics of {} to {}",
+                holder.title(),
+                output_file.display()
+            );
+
+            draw(&output_file, *holder, records)
+                .map_err(|error| Error::output(&output_file, error))?;
+        }
+        Ok(())
+    }
+}
+
+/// Draw the stock amounts above the inventory value of a factory or trader.
+fn draw(
+    output_file: &Path,
+    holder: StockHolder,
+    records: &[InventoryRecord],
+) -> Result<(), Box<dyn error::Error>> {
+    let holder = holder.title();
+    let root = SVGBackend::new(output_file, (640, 720)).into_drawing_area();
+    root.fill(&TRANSPARENT)?;
+    let (upper, lower) = root.split_vertically(360);
+
+    draw_stacked_areas(
+        &upper,
+        &format!("Stock of {holder}"),
+        records,
+        |stock_level| stock_level.amount() as f64,
+        |amount| format!("{amount:.0}"),
+    )?;
+    draw_stacked_areas(
+        &lower,
+        &format!("Inventory Value of {holder}"),
+        records,
+        |stock_level| stock_level.value().into(),
+        |value| format!("{value:.0}€"),
+    )
+}
+
+/// Draw the stock levels of the records as areas stacked on top of each other.
+fn draw_stacked_areas(
+    area: &DrawingArea<SVGBackend, Shift>,
+    caption: &str,
+    records: &[InventoryRecord],
+    value: impl Fn(&StockLevel) -> f64,
+    y_label_formatter: impl Fn(&f64) -> String,
+) -> Result<(), Box<dyn error::Error>> {
+    let keys: BTreeSet<_> = records
+        .iter()
+        .flat_map(|record| record.stock_levels.keys().cloned())
+        .collect();
+
+    // The upper edge of each area is the sum of its value and the values of all areas below it.
+    let stacked_series: Vec<(_, Vec<_>)> = keys
+        .iter()
+        .enumerate()
+        .map(|(index, key)| {
+            let series = records
+                .iter()
+                .map(|record| {
+                    let stacked_value = keys
+                        .iter()
+                        .take(index + 1)
+                        .filter_map(|key| record.stock_levels.get(key))
+                        .map(&value)
+                        .sum::<f64>();
+                    (record.time.into_hours(), stacked_value)
+                })
+                .collect();
+            (key.clone(), series)
+        })
+        .collect();
+
+    let (Some(first), Some(last)) = (records.first(), records.last()) else {
+        return Ok(());
+    };
+    let (min_time, max_time) = (first.time, last.time);
+    let max_value = stacked_series
+        .last()
+        .map(|(_, series)| series.iter().map(|(_, value)| *value).fold(0.0, f64::max))
+        .unwrap_or(0.0);
+    let chart_max_value = if max_value > 0.0 {
+        max_value * 1.05
+    } else {
+        1.0
+    };
+
+    let styles = [RED, GREEN, BLUE, BLACK, CYAN, MAGENTA, YELLOW];
+    let mut chart = ChartBuilder::on(area)
+        .caption(caption, ("sans-serif", 20).into_font())
+        .margin(5)
+        .x_label_area_size(30)
+        .y_label_area_size(50)
+        .build_cartesian_2d(
+            min_time.into_hours()..max_time.checked_add(DateTime::from_hours(1))?.into_hours(),
+            0.0..chart_max_value,
+        )?;
+    chart
+        .configure_mesh()
+        .y_label_formatter(&y_label_formatter)
+        .draw()?;
+
+    // Draw the topmost area first, such that each area covers the part of the areas above it that lies below its edge.
+    for (((kind, ware), series), style) in stacked_series
+        .iter()
+        .zip(styles.iter().cycle())
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+    {
+        let fill = style.mix(0.5).filled();
+        chart.draw_series(
+            AreaSeries::new(series.iter().copied(), 0.0, WHITE.filled()).border_style(style),
+        )?;
+        chart
+            .draw_series(AreaSeries::new(series.iter().copied(), 0.0, fill))?
+            .label(format!("{} ({kind})", ware.name()))
+            .legend(move |(x, y)| Rectangle::new([(x, y - 5), (x + 20, y + 5)], fill));
+    }
+
+    chart
+        .configure_series_labels()
+        .background_style(WHITE)
+        .border_style(BLACK)
+        .position(SeriesLabelPosition::UpperLeft)
+        .draw()?;
+    Ok(())
+}
+
+impl StockHolder {
+    /// The name of the stock holder in exported records and file names.
+    fn entity(&self) -> String {
+        match self {
+            StockHolder::Factory(factory_id) => format!("factory_{factory_id}"),
+            StockHolder::Trader(trader_id) => format!("trader_{trader_id}"),
+        }
+    }
+
+    /// The name of the stock holder in chart captions.
+    fn title(&self) -> String {
+        match self {
+            StockHolder::Factory(factory_id) => format!("Factory {factory_id}"),
+            StockHolder::Trader(trader_id) => format!("Trader {trader_id}"),
+        }
+    }
+}
+
+impl Display for StockKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StockKind::Input => write!(f, "input"),
+            StockKind::Output => write!(f, "output"),
+            StockKind::InTransit => write!(f, "in_transit"),
+            StockKind::Arrived => write!(f, "arrived"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use rand::SeedableRng;
+    use rand_xoshiro::Xoshiro256PlusPlus;
+
+    use crate::{
+        consumer::Consumer,
+        factory::{template::FactoryTemplate, Factory},
+        money::Money,
+        recipe::{ProductionRate, Recipe},
+        statistics::Statistics,
+        ware::{Ware, WareAmount, WareDefinition},
+        world::World,
+    };
+
+    use super::{InventoryStatistics, StockHolder, StockKind};
+
+    /// An orchard grows oranges, which a juicer presses into juice for the households.
+    fn world() -> World {
+        let orange = Ware::new(WareDefinition::new("inventory_test_orange"));
+        let juice = Ware::new(WareDefinition::new("inventory_test_juice"));
+        let orchard = Recipe::new(
+            [],
+            [WareAmount::new(orange.clone(), 1)],
+            ProductionRate::new(10),
+        );
+        let juicer = Recipe::new(
+            [WareAmount::new(orange, 2)],
+            [WareAmount::new(juice.clone(), 1)],
+            ProductionRate::new(5),
+        );
+        World::builder()
+            .with_factory(Factory::new(
+                FactoryTemplate::new(orchard, Money::from(5)).unwrap(),
+                Money::from(1_000),
+            ))
+            .with_factory(Factory::new(
+                FactoryTemplate::new(juicer, Money::from(5)).unwrap(),
+                Money::from(1_000),
+            ))
+            .with_consumers((0..6).map(|_| {
+                Consumer::new(vec![(juice.clone(), 1.0)], 1.0)
+                    .unwrap()
+                    .with_hourly_income(Money::from(5))
+                    .with_reservation_wage(Money::from(1))
+            }))
+            .with_invariant_checks()
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn samples_input_and_output_stock_of_each_factory() {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(0);
+        let mut world = world();
+        let mut statistics = InventoryStatistics::new("inventory");
+        for _ in 0..12 {
+            world.advance_hour(&mut rng).unwrap();
+            statistics.collect(&world);
+
+            for (factory_id, factory) in world.factories() {
+                let record = statistics.time_series[&StockHolder::Factory(factory_id)]
+                    .last()
+                    .unwrap();
+                assert_eq!(record.time, world.time());
+                for (kind, stock) in [
+                    (StockKind::Input, factory.input_stock()),
+                    (StockKind::Output, factory.output_stock()),
+                ] {
+                    for (ware, stock_level) in stock {
+                        let recorded = record.stock_levels[&(kind, ware)];
+                        assert_eq!(recorded.amount(), stock_level.amount());
+                        assert_eq!(recorded.value(), stock_level.value());
+                    }
+                }
+                assert_eq!(
+                    record.stock_levels.len(),
+                    factory.input_stock().len() + factory.output_stock().len()
+                );
+            }
+        }
+
+        let sampled = |kind| {
+            statistics
+                .time_series
+                .values()
+                .flatten()
+                .flat_map(|record| &record.stock_levels)
+                .any(|((stock_kind, _), stock_level)| {
+                    *stock_kind == kind && stock_level.amount() > 0
+                })
+        };
+        assert!(sampled(StockKind::Input));
+        assert!(sampled(StockKind::Output));
+        assert!(statistics
+            .records()
+            .iter()
+            .all(|record| record.value().is_finite()));
+    }
+
+    #[test]
+    fn samples_empty_stock() {
+        let output_directory = env::temp_dir().join("inventory_statistics_test_empty_stock");
+        let world = world();
+        let mut statistics = InventoryStatistics::new(&output_directory);
+        statistics.collect(&world);
+        statistics.collect(&world);
+
+        assert_eq!(statistics.time_series.len(), 2);
+        assert!(statistics
+            .time_series
+            .values()
+            .flatten()
+            .all(|record| record.stock_levels.is_empty()));
+        assert!(statistics.records().is_empty());
+        statistics.finalise().unwrap();
+        fs::remove_dir_all(output_directory).unwrap();
+    }
+}
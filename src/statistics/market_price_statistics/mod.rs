@@ -0,0 +1,337 @@
+use std::{collections::BTreeMap, error, path::PathBuf};
+
+use log::debug;
+use plotters::{
+    chart::{ChartBuilder, SeriesLabelPosition},
+    element::Rectangle,
+    prelude::{IntoDrawingArea, PathElement, SVGBackend},
+    series::{DashedLineSeries, LineSeries},
+    style::{Color, IntoFont, BLACK, BLUE, CYAN, GREEN, MAGENTA, RED, TRANSPARENT, WHITE, YELLOW},
+};
+
+use crate::{
+    error::{Error, Result},
+    money::Money,
+    region::RegionId,
+    time::DateTime,
+    ware::Ware,
+    world::World,
+};
+
+use super::{export::StatisticsRecord, Statistics};
+
+/// Draw the prices of each ware on the market of each region.
+///
+/// If the world has several regions, the series are labelled with the region name.
+#[derive(Debug)]
+pub struct MarketPriceStatistics {
+    output_file: PathBuf,
+    volume_bars: bool,
+    region_names: BTreeMap<RegionId, String>,
+    price_time_series: BTreeMap<(RegionId, Ware), Vec<MarketPriceRecord>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct MarketPriceRecord {
+    time: DateTime,
+    lowest_ask: Option<Money>,
+    clearing_price: Option<Money>,
+    volume: u64,
+    unsold_amount: u64,
+}
+
+impl MarketPriceStatistics {
+    pub fn new(output_file: impl Into<PathBuf>) -> Self {
+        Self {
+            output_file: output_file.into(),
+            volume_bars: false,
+            region_names: Default::default(),
+            price_time_series: Default::default(),
+        }
+    }
+
+    /// Draw the traded volume of each ware as bars behind the price lines,
+    /// topped by outlined bars for the unsold amount.
+    pub fn with_volume_bars(mut self, volume_bars: bool) -> Self {
+        self.volume_bars = volume_bars;
+        self
+    }
+}
+
+impl Statistics for MarketPriceStatistics {
+    fn collect(&mut self, world: &World) {
+        let time = world.time();
+        for (region_id, region) in world.regions() {
+            self.region_names
+                .entry(region_id)
+                .or_insert_with(|| region.name().to_string());
+            for ware in world.wares() {
+                let record = MarketPriceRecord {
+                    time,
+                    lowest_ask: region.market().lowest_ask(ware),
+                    clearing_price: region.market().clearing_price(ware),
+                    volume: region.market().traded_volume(ware),
+                    unsold_amount: region.market().unsold_amount(ware),
+                };
+                self.price_time_series
+                    .entry((region_id, ware.clone()))
+                    .or_default()
+                    .push(record);
+            }
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "market_prices"
+    }
+
+    fn records(&self) -> Vec<StatisticsRecord> {
+        let mut records = Vec::new();
+        for ((region_id, ware), series) in &self.price_time_series {
+            let entity = if self.region_names.len() > 1 {
+                format!("region_{region_id}_{}", ware.id())
+            } else {
+                ware.id().to_string()
+            };
+            for record in series {
+                let mut push = |metric, value: f64| {
+                    records.push(StatisticsRecord::new(record.time, &entity, metric, value))
+                };
+                if let Some(lowest_ask) = record.lowest_ask {
+                    push("lowest_ask", lowest_ask.into());
+                }
+                if let Some(clearing_price) = record.clearing_price {
+                    push("clearing_price", clearing_price.into());
+                }
+                push("volume", record.volume as f64);
+                push("unsold_amount", record.unsold_amount as f64);
+            }
+        }
+        records
+    }
+
+    fn finalise(&self) -> Result<()> {
+        self.draw()
+            .map_err(|error| Error::output(&self.output_file, error))
+    }
+}
+
+impl MarketPriceStatistics {
+    fn label(&self, region_id: RegionId, ware: &Ware) -> String {
+        match self.region_names.get(&region_id) {
+            Some(region_name) if self.region_names.len() > 1 => {
+                format!("{} in {region_name}", ware.name())
+            }
+            _ => ware.name().to_string(),
+        }
+    }
+
+    fn draw(&self) -> Result<(), Box<dyn error::Error>> {
+        let records = || self.price_time_series.values().flatten();
+        let Some(min_time) = records().map(|record| record.time).min() else {
+            return Ok(());
+        };
+        let max_time = records().map(|record| record.time).max().unwrap();
+        let max_price = records()
+            .flat_map(|record| {
+                [
+                    record.lowest_ask.map(f64::from),
+                    record.clearing_price.map(f64::from),
+                ]
+            })
+            .flatten()
+            .fold(0.0, f64::max);
+        let max_volume = records()
+            .map(|record| record.volume + record.unsold_amount)
+            .max()
+            .unwrap();
+
+        let time_margin = max_time.saturating_sub(min_time).checked_div(20)?;
+        let chart_min_time = min_time.saturating_sub(time_margin);
+        let chart_max_time = max_time
+            .checked_add(time_margin)?
+            .checked_add(DateTime::from_hours(1))?;
+        let chart_max_price = if max_price > 0.0 {
+            max_price * 1.05
+        } else {
+            1.0
+        };
+        let chart_max_volume = max_volume.max(1) + max_volume / 20;
+
+        debug!("Drawing market price statistics in area x: {chart_min_time}..{chart_max_time}; y: 0..{chart_max_price}");
+
+        let root = SVGBackend::new(&self.output_file, (640, 480)).into_drawing_area();
+        root.fill(&TRANSPARENT)?;
+
+        let styles = [RED, GREEN, BLUE, BLACK, CYAN, MAGENTA, YELLOW];
+        let mut chart = ChartBuilder::on(&root)
+            .caption("Market Prices Over Time", ("sans-serif", 24).into_font())
+            .margin(5)
+            .x_label_area_size(30)
+            .y_label_area_size(50)
+            .right_y_label_area_size(if self.volume_bars { 50 } else { 0 })
+            .build_cartesian_2d(
+                chart_min_time.into_hours()..chart_max_time.into_hours(),
+                0.0..chart_max_price,
+            )?
+            .set_secondary_coord(
+                chart_min_time.into_hours()..chart_max_time.into_hours(),
+                0..chart_max_volume,
+            );
+        chart
+            .configure_mesh()
+            .y_label_formatter(&|price| format!("{price:.2}€"))
+            .draw()?;
+
+        if self.volume_bars {
+            chart.configure_secondary_axes().y_desc("Volume").draw()?;
+
+            for (series, style) in self.price_time_series.values().zip(styles.iter().cycle()) {
+                chart.draw_secondary_series(series.iter().flat_map(|record| {
+                    let hour = record.time.into_hours();
+                    let total_amount = record.volume + record.unsold_amount;
+                    [
+                        Rectangle::new(
+                            [(hour, 0), (hour + 1, record.volume)],
+                            style.mix(0.2).filled(),
+                        ),
+                        Rectangle::new(
+                            [(hour, record.volume), (hour + 1, total_amount)],
+                            style.mix(0.2),
+                        ),
+                    ]
+                }))?;
+            }
+        }
+
+        for (((region_id, ware), series), style) in
+            self.price_time_series.iter().zip(styles.iter().cycle())
+        {
+            chart
+                .draw_series(LineSeries::new(
+                    series.iter().filter_map(|record| {
+                        Some((record.time.into_hours(), f64::from(record.clearing_price?)))
+                    }),
+                    style,
+                ))?
+                .label(self.label(*region_id, ware))
+                .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], *style));
+            chart.draw_series(DashedLineSeries::new(
+                series.iter().filter_map(|record| {
+                    Some((record.time.into_hours(), f64::from(record.lowest_ask?)))
+                }),
+                4,
+                4,
+                style.into(),
+            ))?;
+        }
+
+        chart
+            .configure_series_labels()
+            .background_style(WHITE)
+            .border_style(BLACK)
+            .position(SeriesLabelPosition::UpperRight)
+            .draw()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use rand::SeedableRng;
+    use rand_xoshiro::Xoshiro256PlusPlus;
+
+    use crate::{
+        consumer::Consumer,
+        factory::{template::FactoryTemplate, Factory},
+        money::Money,
+        recipe::{ProductionRate, Recipe},
+        region::RegionId,
+        statistics::Statistics,
+        ware::{Ware, WareAmount, WareDefinition},
+        world::World,
+    };
+
+    use super::MarketPriceStatistics;
+
+    fn plum() -> Ware {
+        Ware::new(WareDefinition::new("market_price_statistics_test_plum"))
+    }
+
+    /// An orchard grows more plums than the households can afford.
+    fn world() -> World {
+        let orchard = Recipe::new([], [WareAmount::new(plum(), 50)], ProductionRate::new(10));
+        World::builder()
+            .with_factory(Factory::new(
+                FactoryTemplate::new(orchard, Money::from(5)).unwrap(),
+                Money::from(1_000),
+            ))
+            .with_consumers((0..4).map(|_| {
+                Consumer::new(vec![(plum(), 1.0)], 1.0)
+                    .unwrap()
+                    .with_hourly_income(Money::from(10))
+                    .with_reservation_wage(Money::from(1))
+            }))
+            .with_invariant_checks()
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn samples_prices_volume_and_unsold_amount_per_hour() {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(0);
+        let mut world = world();
+        let mut statistics = MarketPriceStatistics::new("market_prices.svg");
+        for _ in 0..12 {
+            world.advance_hour(&mut rng).unwrap();
+            statistics.collect(&world);
+
+            let market = world.regions().next().unwrap().1.market();
+            let record = statistics.price_time_series[&(RegionId::from(0), plum())]
+                .last()
+                .copied()
+                .unwrap();
+            assert_eq!(record.time, world.time());
+            assert_eq!(record.lowest_ask, market.lowest_ask(&plum()));
+            assert_eq!(record.clearing_price, market.clearing_price(&plum()));
+            assert_eq!(record.volume, market.traded_volume(&plum()));
+            assert_eq!(record.unsold_amount, market.unsold_amount(&plum()));
+        }
+
+        let series = &statistics.price_time_series[&(RegionId::from(0), plum())];
+        assert_eq!(series.len(), 12);
+        assert!(series.iter().any(|record| record.volume > 0));
+        assert!(series.iter().any(|record| record.unsold_amount > 0));
+        assert!(series.iter().any(|record| record.lowest_ask.is_some()));
+        assert!(series.iter().any(|record| record.clearing_price.is_some()));
+    }
+
+    #[test]
+    fn samples_hours_without_offers_or_trades() {
+        let output_file = env::temp_dir().join("market_price_statistics_test_empty.svg");
+        let world = world();
+        let mut statistics = MarketPriceStatistics::new(&output_file).with_volume_bars(true);
+        statistics.collect(&world);
+        statistics.collect(&world);
+
+        let series = &statistics.price_time_series[&(RegionId::from(0), plum())];
+        assert_eq!(series.len(), 2);
+        for record in series {
+            assert_eq!(record.lowest_ask, None);
+            assert_eq!(record.clearing_price, None);
+            assert_eq!(record.volume, 0);
+            assert_eq!(record.unsold_amount, 0);
+        }
+        let records = statistics.records();
+        assert_eq!(records.len(), 4);
+        assert!(records
+            .iter()
+            .all(|record| record.value() == 0.0 && !record.metric().contains("price")));
+
+        statistics.finalise().unwrap();
+        assert!(!fs::read_to_string(&output_file).unwrap().contains("NaN"));
+        fs::remove_file(output_file).unwrap();
+    }
+}
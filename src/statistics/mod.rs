@@ -1,11 +1,25 @@
 use std::fmt::Debug;
 
-use crate::world::World;
+use export::StatisticsRecord;
 
+use crate::{error::Result, world::World};
+
+pub mod export;
 pub mod factory_money_statistics;
+pub mod income_statement_statistics;
+pub mod inventory_statistics;
+pub mod market_price_statistics;
+pub mod production_statistics;
 
 pub trait Statistics: Debug {
     fn collect(&mut self, world: &World);
 
-    fn finalise(&self);
+    /// Write the collected statistics to their output files.
+    fn finalise(&self) -> Result<()>;
+
+    /// The name by which the collector is selected for export, as used for its type in scenario files.
+    fn name(&self) -> &'static str;
+
+    /// The collected time series in long format, one record per time, entity and metric.
+    fn records(&self) -> Vec<StatisticsRecord>;
 }
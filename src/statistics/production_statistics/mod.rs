@@ -0,0 +1,161 @@
+use std::{collections::BTreeMap, error, path::PathBuf};
+
+use log::debug;
+use plotters::{
+    chart::{ChartBuilder, SeriesLabelPosition},
+    prelude::{IntoDrawingArea, PathElement, SVGBackend},
+    series::LineSeries,
+    style::{IntoFont, BLACK, BLUE, CYAN, GREEN, MAGENTA, RED, TRANSPARENT, WHITE, YELLOW},
+};
+
+use crate::{
+    error::{Error, Result},
+    factory::FactoryId,
+    time::DateTime,
+    world::World,
+};
+
+use super::{export::StatisticsRecord, Statistics};
+
+/// Draw how often each factory applied each of its recipes per hour, as chosen by its production planner.
+#[derive(Debug)]
+pub struct ProductionStatistics {
+    output_file: PathBuf,
+    factories: BTreeMap<FactoryId, FactoryProduction>,
+}
+
+#[derive(Debug)]
+struct FactoryProduction {
+    recipe_names: Vec<String>,
+    /// The applications of each recipe, in the order of the recipe names.
+    time_series: Vec<(DateTime, Vec<u64>)>,
+}
+
+impl ProductionStatistics {
+    pub fn new(output_file: impl Into<PathBuf>) -> Self {
+        Self {
+            output_file: output_file.into(),
+            factories: Default::default(),
+        }
+    }
+
+    fn draw(&self) -> Result<(), Box<dyn error::Error>> {
+        let records = || {
+            self.factories
+                .values()
+                .flat_map(|production| production.time_series.iter())
+        };
+        let Some(min_time) = records().map(|(time, _)| *time).min() else {
+            return Ok(());
+        };
+        let max_time = records().map(|(time, _)| *time).max().unwrap_or(min_time);
+        let max_applications = records()
+            .flat_map(|(_, applications)| applications.iter().copied())
+            .max()
+            .unwrap_or(0);
+
+        let chart_max_time = max_time.checked_add(DateTime::from_hours(1))?;
+        let chart_max_applications = max_applications.max(1) + max_applications / 20;
+
+        debug!("Drawing production statistics in area x: {min_time}..{chart_max_time}; y: 0..{chart_max_applications}");
+
+        let root = SVGBackend::new(&self.output_file, (640, 480)).into_drawing_area();
+        root.fill(&TRANSPARENT)?;
+
+        let styles = [RED, GREEN, BLUE, BLACK, CYAN, MAGENTA, YELLOW];
+        let mut chart = ChartBuilder::on(&root)
+            .caption(
+                "Recipe Applications per Hour",
+                ("sans-serif", 24).into_font(),
+            )
+            .margin(5)
+            .x_label_area_size(30)
+            .y_label_area_size(50)
+            .build_cartesian_2d(
+                min_time.into_hours()..chart_max_time.into_hours(),
+                0..chart_max_applications,
+            )?;
+        chart.configure_mesh().draw()?;
+
+        let series = self.factories.iter().flat_map(|(factory_id, production)| {
+            production
+                .recipe_names
+                .iter()
+                .enumerate()
+                .map(move |(index, recipe_name)| (factory_id, production, index, recipe_name))
+        });
+        for ((factory_id, production, index, recipe_name), style) in
+            series.zip(styles.iter().cycle())
+        {
+            chart
+                .draw_series(LineSeries::new(
+                    production
+                        .time_series
+                        .iter()
+                        .map(|(time, applications)| (time.into_hours(), applications[index])),
+                    style,
+                ))?
+                .label(format!("Factory {factory_id}: {recipe_name}"))
+                .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], *style));
+        }
+
+        chart
+            .configure_series_labels()
+            .background_style(WHITE)
+            .border_style(BLACK)
+            .position(SeriesLabelPosition::UpperLeft)
+            .draw()?;
+        Ok(())
+    }
+}
+
+impl Statistics for ProductionStatistics {
+    fn collect(&mut self, world: &World) {
+        for (factory_id, factory) in world.factories() {
+            let recipes = factory.template().recipes();
+            let production =
+                self.factories
+                    .entry(factory_id)
+                    .or_insert_with(|| FactoryProduction {
+                        recipe_names: recipes
+                            .iter()
+                            .map(|recipe| recipe.name().to_string())
+                            .collect(),
+                        time_series: Vec::new(),
+                    });
+
+            // Before the first production, the plan is empty.
+            let mut applications = factory.production_plan().applications().to_vec();
+            applications.resize(recipes.len(), 0);
+            production.time_series.push((world.time(), applications));
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "production"
+    }
+
+    fn records(&self) -> Vec<StatisticsRecord> {
+        let mut records = Vec::new();
+        for (factory_id, production) in &self.factories {
+            let entity = format!("factory_{factory_id}");
+            for (time, applications) in &production.time_series {
+                for (recipe_name, applications) in production.recipe_names.iter().zip(applications)
+                {
+                    records.push(StatisticsRecord::new(
+                        *time,
+                        &entity,
+                        format!("applications_{recipe_name}"),
+                        *applications as f64,
+                    ));
+                }
+            }
+        }
+        records
+    }
+
+    fn finalise(&self) -> Result<()> {
+        self.draw()
+            .map_err(|error| Error::output(&self.output_file, error))
+    }
+}
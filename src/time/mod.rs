@@ -1,9 +1,10 @@
-use std::{
-    fmt::Display,
-    ops::{Add, Div, Sub},
-};
+use std::fmt::Display;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct DateTime {
     hours: u64,
 }
@@ -15,47 +16,39 @@ impl DateTime {
         Self { hours }
     }
 
-    pub fn increment(&mut self) {
-        self.hours = self.hours.checked_add(1).unwrap();
+    pub fn increment(&mut self) -> Result<()> {
+        *self = self.checked_add(Self::from_hours(1))?;
+        Ok(())
     }
 
-    pub fn into_hours(self) -> u64 {
+    pub fn checked_add(self, rhs: Self) -> Result<Self> {
         self.hours
+            .checked_add(rhs.hours)
+            .map(Self::from_hours)
+            .ok_or(Error::Overflow("time addition"))
     }
 
-    pub fn saturating_sub(&self, rhs: Self) -> Self {
-        Self {
-            hours: self.hours.saturating_sub(rhs.hours),
-        }
+    pub fn checked_sub(self, rhs: Self) -> Result<Self> {
+        self.hours
+            .checked_sub(rhs.hours)
+            .map(Self::from_hours)
+            .ok_or(Error::Overflow("time subtraction"))
     }
-}
 
-impl Add for DateTime {
-    type Output = Self;
-
-    fn add(self, rhs: Self) -> Self::Output {
-        Self {
-            hours: self.hours.checked_add(rhs.hours).unwrap(),
-        }
+    pub fn checked_div(self, rhs: u64) -> Result<Self> {
+        self.hours
+            .checked_div(rhs)
+            .map(Self::from_hours)
+            .ok_or(Error::DivisionByZero("time division"))
     }
-}
-
-impl Sub for DateTime {
-    type Output = Self;
 
-    fn sub(self, rhs: Self) -> Self::Output {
-        Self {
-            hours: self.hours.checked_sub(rhs.hours).unwrap(),
-        }
+    pub fn into_hours(self) -> u64 {
+        self.hours
     }
-}
-
-impl Div<u64> for DateTime {
-    type Output = Self;
 
-    fn div(self, rhs: u64) -> Self::Output {
+    pub fn saturating_sub(&self, rhs: Self) -> Self {
         Self {
-            hours: self.hours.checked_div(rhs).unwrap(),
+            hours: self.hours.saturating_sub(rhs.hours),
         }
     }
 }
@@ -0,0 +1,713 @@
+use std::{collections::BTreeMap, fmt::Display, mem};
+
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{Error, Result},
+    factory::pricing::{CostPlusHalf, PricingStrategy},
+    market::{Buyer, Market, OfferId, Seller},
+    money::{ApproximateMoney, Money},
+    region::{route::Route, Region, RegionId},
+    time::DateTime,
+    ware::{Ware, WareAmount},
+    warehouse::{StockLevel, Warehouse, WarehouseBatch},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct TraderId(usize);
+
+/// A merchant that buys wares in regions where they are cheap and ships them along routes
+/// to regions where they sell for more.
+///
+/// Each hour, the trader compares the current prices of every ware at both ends of every route.
+/// If the price at the destination exceeds the purchase price plus the transport cost by at least the minimum margin,
+/// it bids for a shipment at the source and sends the bought wares on their way.
+/// Arrived wares are offered on the market of the destination like the outputs of a factory.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Trader {
+    money: Money,
+    /// The margin over the purchase price plus the transport cost that a shipment must promise.
+    minimum_margin: f64,
+    /// The maximum amount of a ware that the trader buys per region and hour.
+    shipment_size: u64,
+    pricing_strategy: Box<dyn PricingStrategy>,
+    /// How long offers stay on the market before the unsold wares are returned.
+    offer_lifetime: DateTime,
+    /// The arrived wares that are not offered on the market of their region.
+    stock: BTreeMap<RegionId, Warehouse>,
+    /// The wares on their way between regions.
+    shipments: Vec<Shipment>,
+    /// The sourcing cost of all wares that spoiled in stock or did not fit into it.
+    spoilage: ApproximateMoney,
+    /// The route along which the wares bought in a region in the current hour are shipped, as an index into the routes.
+    /// Bids only live within an hour and hence their destinations are not part of snapshots.
+    #[serde(skip)]
+    destinations: BTreeMap<(RegionId, Ware), usize>,
+    /// The offers of this trader on the market of each region.
+    open_offers: BTreeMap<RegionId, BTreeMap<OfferId, OpenOffer>>,
+}
+
+/// Wares on their way from one region to another.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Shipment {
+    ware_amount: WareAmount,
+    /// The purchase price plus the transport cost per item.
+    sourcing_cost_per_item: ApproximateMoney,
+    /// When the wares were produced, such that perishable wares keep ageing in transit.
+    produced: DateTime,
+    from: RegionId,
+    to: RegionId,
+    arrives_at: DateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct OpenOffer {
+    /// The amount offered at the start of the hour.
+    ware_amount: WareAmount,
+    sourcing_cost_per_item: ApproximateMoney,
+}
+
+/// The best destination found for a ware bought in a region.
+struct Opportunity {
+    route_index: usize,
+    /// The highest purchase price plus transport cost per item that still promises the minimum margin.
+    maximum_landed_cost: f64,
+    margin: f64,
+}
+
+impl Trader {
+    pub fn new(starting_money: Money) -> Self {
+        Self {
+            money: starting_money,
+            minimum_margin: 0.1,
+            shipment_size: 100,
+            pricing_strategy: Box::new(CostPlusHalf),
+            offer_lifetime: DateTime::from_hours(1),
+            stock: Default::default(),
+            shipments: Default::default(),
+            spoilage: ApproximateMoney::ZERO,
+            destinations: Default::default(),
+            open_offers: Default::default(),
+        }
+    }
+
+    pub fn with_minimum_margin(mut self, minimum_margin: f64) -> Result<Self> {
+        if !(minimum_margin >= 0.0 && minimum_margin.is_finite()) {
+            return Err(Error::invalid_argument(format!(
+                "minimum margin must not be negative, but is {minimum_margin}"
+            )));
+        }
+        self.minimum_margin = minimum_margin;
+        Ok(self)
+    }
+
+    pub fn with_shipment_size(mut self, shipment_size: u64) -> Result<Self> {
+        if shipment_size == 0 {
+            return Err(Error::invalid_argument("shipment size must be positive"));
+        }
+        self.shipment_size = shipment_size;
+        Ok(self)
+    }
+
+    pub fn with_pricing_strategy(mut self, pricing_strategy: Box<dyn PricingStrategy>) -> Self {
+        self.pricing_strategy = pricing_strategy;
+        self
+    }
+
+    pub fn with_offer_lifetime(mut self, offer_lifetime: DateTime) -> Result<Self> {
+        if offer_lifetime == DateTime::ZERO {
+            return Err(Error::invalid_argument("offer lifetime must be positive"));
+        }
+        self.offer_lifetime = offer_lifetime;
+        Ok(self)
+    }
+
+    pub fn money(&self) -> Money {
+        self.money
+    }
+
+    pub fn minimum_margin(&self) -> f64 {
+        self.minimum_margin
+    }
+
+    pub fn shipment_size(&self) -> u64 {
+        self.shipment_size
+    }
+
+    /// The wares on their way between regions.
+    pub fn shipments(&self) -> &[Shipment] {
+        &self.shipments
+    }
+
+    /// The sourcing cost of all wares that spoiled in stock or did not fit into it, which were written off.
+    pub fn spoilage(&self) -> ApproximateMoney {
+        self.spoilage
+    }
+
+    /// The stock levels of the wares in transit, valued at their purchase price plus the transport cost.
+    pub fn stock_in_transit(&self) -> BTreeMap<Ware, StockLevel> {
+        let mut stock_levels: BTreeMap<Ware, StockLevel> = BTreeMap::new();
+        for shipment in &self.shipments {
+            stock_levels
+                .entry(shipment.ware_amount.ware().clone())
+                .or_default()
+                .add(
+                    shipment.ware_amount.amount(),
+                    shipment.sourcing_cost_per_item,
+                );
+        }
+        stock_levels
+    }
+
+    /// The stock levels of the wares that arrived in the given region, including the unsold wares offered on its market.
+    pub fn stock(&self, region: RegionId) -> BTreeMap<Ware, StockLevel> {
+        let mut stock_levels = self
+            .stock
+            .get(&region)
+            .map(Warehouse::stock_levels)
+            .unwrap_or_default();
+        for open_offer in self
+            .open_offers
+            .get(&region)
+            .into_iter()
+            .flat_map(BTreeMap::values)
+        {
+            stock_levels
+                .entry(open_offer.ware_amount.ware().clone())
+                .or_default()
+                .add(
+                    open_offer.ware_amount.amount(),
+                    open_offer.sourcing_cost_per_item,
+                );
+        }
+        stock_levels
+    }
+
+    /// The amount of the ware that is waiting in the region or on its way there.
+    fn amount_bound_for(&self, region: RegionId, ware: &Ware) -> u64 {
+        let in_transit: u64 = self
+            .shipments
+            .iter()
+            .filter(|shipment| shipment.to == region && shipment.ware_amount.ware() == ware)
+            .map(|shipment| shipment.ware_amount.amount())
+            .sum();
+        in_transit
+            + self
+                .stock(region)
+                .get(ware)
+                .map(StockLevel::amount)
+                .unwrap_or(0)
+    }
+
+    /// Put the shipments that arrived by the given time into the stock of their destination.
+    pub(crate) fn receive_shipments(&mut self, time: DateTime) -> Result<()> {
+        let (arrived, in_transit): (Vec<_>, Vec<_>) = mem::take(&mut self.shipments)
+            .into_iter()
+            .partition(|shipment| shipment.arrives_at <= time);
+        self.shipments = in_transit;
+
+        for shipment in arrived {
+            debug!(
+                "Shipment of {} arrived in region {} from region {}",
+                shipment.ware_amount, shipment.to, shipment.from
+            );
+            let refused_amount = self.stock.entry(shipment.to).or_default().insert_ware(
+                shipment.ware_amount.clone(),
+                shipment.sourcing_cost_per_item,
+                shipment.produced,
+            )?;
+            self.write_off_refused(
+                shipment.ware_amount.with_amount(refused_amount),
+                shipment.sourcing_cost_per_item,
+            );
+        }
+        Ok(())
+    }
+
+    /// Remove the stocked wares that spoiled in the hour that ends at the given time, and write them off.
+    pub(crate) fn spoil_wares(&mut self, time: DateTime) {
+        for (region, warehouse) in &mut self.stock {
+            for (ware, stock_level) in warehouse.spoil(time) {
+                debug!(
+                    "{} worth {} spoiled in region {region}",
+                    WareAmount::new(ware, stock_level.amount()),
+                    stock_level.value()
+                );
+                self.spoilage += stock_level.value();
+            }
+        }
+    }
+
+    /// Put the wares of expired offers in the given region back into stock.
+    pub(crate) fn restock_expired_offers(
+        &mut self,
+        region: RegionId,
+        expired_offers: Vec<(OfferId, WarehouseBatch)>,
+    ) -> Result<()> {
+        for (offer_id, batch) in expired_offers {
+            if let Some(open_offers) = self.open_offers.get_mut(&region) {
+                open_offers.remove(&offer_id);
+            }
+            let ware_amount = WareAmount::new(batch.ware().clone(), batch.amount());
+            let sourcing_cost_per_item = batch.sourcing_cost_per_item();
+            let refused_amount = self.stock.entry(region).or_default().insert_batch(batch)?;
+            self.write_off_refused(
+                ware_amount.with_amount(refused_amount),
+                sourcing_cost_per_item,
+            );
+        }
+        Ok(())
+    }
+
+    /// Write off wares that did not fit into a warehouse, at their sourcing cost.
+    fn write_off_refused(
+        &mut self,
+        ware_amount: WareAmount,
+        sourcing_cost_per_item: ApproximateMoney,
+    ) {
+        if ware_amount.amount() == 0 {
+            return;
+        }
+        debug!("{ware_amount} did not fit into the warehouse and were written off");
+        self.spoilage += sourcing_cost_per_item * ware_amount.amount();
+    }
+
+    /// Offer all stocked wares of the given region on its market.
+    ///
+    /// Offers of perishable wares expire early, see [`WarehouseBatch::offer_lifetime`].
+    pub(crate) fn offer_wares(
+        &mut self,
+        region: RegionId,
+        market: &mut Market,
+        trader_id: TraderId,
+        time: DateTime,
+    ) -> Result<()> {
+        let Some(warehouse) = self.stock.get_mut(&region) else {
+            return Ok(());
+        };
+
+        for batch in warehouse.drain() {
+            let expiry_time = time.checked_add(batch.offer_lifetime(self.offer_lifetime, time))?;
+            let price_per_item =
+                self.pricing_strategy
+                    .price(&batch, Seller::Trader(trader_id), market);
+            let open_offer = OpenOffer {
+                ware_amount: WareAmount::new(batch.ware().clone(), batch.amount()),
+                sourcing_cost_per_item: batch.sourcing_cost_per_item(),
+            };
+            let offer_id = market.offer(
+                batch,
+                price_per_item,
+                Seller::Trader(trader_id),
+                expiry_time,
+            );
+            self.open_offers
+                .entry(region)
+                .or_default()
+                .insert(offer_id, open_offer);
+        }
+        Ok(())
+    }
+
+    /// Inform the pricing strategy about the sales of this hour on the market of the given region.
+    pub(crate) fn record_sales(&mut self, region: RegionId, market: &Market) {
+        let Some(open_offers) = self.open_offers.get_mut(&region) else {
+            return;
+        };
+
+        let mut offered_amounts: BTreeMap<Ware, (u64, u64)> = BTreeMap::new();
+        open_offers.retain(|offer_id, open_offer| {
+            let ware_amount = &open_offer.ware_amount;
+            let remaining_amount = market.offered_amount(*offer_id);
+            let (offered_amount, sold_amount) = offered_amounts
+                .entry(ware_amount.ware().clone())
+                .or_default();
+            *offered_amount += ware_amount.amount();
+            *sold_amount += ware_amount.amount() - remaining_amount;
+
+            open_offer.ware_amount = ware_amount.with_amount(remaining_amount);
+            remaining_amount > 0
+        });
+
+        for (ware, (offered_amount, sold_amount)) in offered_amounts {
+            self.pricing_strategy
+                .record_sales(ware, offered_amount, sold_amount);
+        }
+    }
+
+    /// Bid for the wares that are worth shipping to another region.
+    ///
+    /// For each ware and region, the trader picks the destination with the highest margin over the purchase price
+    /// and the transport cost, estimating the selling price from the cheapest competing offer at the destination,
+    /// or from its last clearing price.
+    /// If the ware has no price at the destination, and none of the trader's wares are waiting or on their way there,
+    /// the trader sends a trial shipment.
+    /// The trader keeps enough money to pay for the transport of everything it bids for.
+    ///
+    /// Returns the money held back by the market of each region for the bids.
+    pub(crate) fn bid_for_shipments(
+        &mut self,
+        regions: &mut [Region],
+        routes: &[Route],
+        wares: &[Ware],
+        trader_id: TraderId,
+    ) -> Result<BTreeMap<RegionId, Money>> {
+        self.destinations.clear();
+
+        let mut opportunities: BTreeMap<(RegionId, Ware), Opportunity> = BTreeMap::new();
+        for (route_index, route) in routes.iter().enumerate() {
+            let (a, b) = route.regions();
+            for (from, to) in [(a, b), (b, a)] {
+                let source = regions[usize::from(from)].market();
+                let destination = regions[usize::from(to)].market();
+                for ware in wares {
+                    // The trader does not buy back the wares it offers itself.
+                    let Some(&(purchase_price, _)) = source
+                        .competitor_price_levels(ware, Seller::Trader(trader_id))?
+                        .first()
+                    else {
+                        continue;
+                    };
+                    let landed_cost = f64::from(ApproximateMoney::from(purchase_price))
+                        + f64::from(route.cost_per_item());
+                    let opportunity = match destination
+                        .lowest_competitor_price(ware, Seller::Trader(trader_id))
+                        .or_else(|| destination.clearing_price(ware))
+                    {
+                        Some(price) => {
+                            let selling_price = f64::from(ApproximateMoney::from(price));
+                            Opportunity {
+                                route_index,
+                                maximum_landed_cost: selling_price / (1.0 + self.minimum_margin),
+                                margin: selling_price / landed_cost - 1.0,
+                            }
+                        }
+                        // A trial shipment only buys the cheapest offers.
+                        None if self.amount_bound_for(to, ware) == 0 => Opportunity {
+                            route_index,
+                            maximum_landed_cost: landed_cost,
+                            margin: self.minimum_margin,
+                        },
+                        None => continue,
+                    };
+
+                    if landed_cost > opportunity.maximum_landed_cost {
+                        continue;
+                    }
+                    match opportunities.get(&(from, ware.clone())) {
+                        Some(best) if best.margin >= opportunity.margin => {}
+                        _ => {
+                            opportunities.insert((from, ware.clone()), opportunity);
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut escrowed_money: BTreeMap<RegionId, Money> = BTreeMap::new();
+        let mut reserved_transport_cost = Money::ZERO;
+        for ((from, ware), opportunity) in opportunities {
+            let route = &routes[opportunity.route_index];
+            let market = regions[usize::from(from)].market_mut();
+            let transport_cost_per_item =
+                Money::from(f64::from(route.cost_per_item()).ceil() as u64);
+
+            // Buy the cheapest offers as long as they still promise the minimum margin.
+            let mut amount = 0;
+            let mut price = Money::ZERO;
+            for (level_price, level_amount) in
+                market.competitor_price_levels(&ware, Seller::Trader(trader_id))?
+            {
+                let landed_cost = f64::from(ApproximateMoney::from(level_price))
+                    + f64::from(route.cost_per_item());
+                if amount >= self.shipment_size || landed_cost > opportunity.maximum_landed_cost {
+                    break;
+                }
+                amount += level_amount.min(self.shipment_size - amount);
+                price = level_price;
+            }
+            let cost_per_item = price.checked_add(transport_cost_per_item)?;
+            if cost_per_item > Money::ZERO {
+                let budget = self.money.saturating_sub(reserved_transport_cost);
+                amount = amount.min(budget.checked_div_money(cost_per_item)?);
+            }
+            if amount == 0 {
+                continue;
+            }
+
+            debug!(
+                "Bidding for {} at {price} per item to ship to region {}",
+                WareAmount::new(ware.clone(), amount),
+                route.destination_from(from).unwrap_or(from)
+            );
+            market.bid(
+                Buyer::Trader(trader_id),
+                WareAmount::new(ware.clone(), amount),
+                price,
+                &mut self.money,
+            )?;
+            let escrow = escrowed_money.entry(from).or_insert(Money::ZERO);
+            *escrow = escrow.checked_add(price.checked_mul(amount)?)?;
+            reserved_transport_cost =
+                reserved_transport_cost.checked_add(route.transport_cost(amount))?;
+            self.destinations
+                .insert((from, ware), opportunity.route_index);
+        }
+        Ok(escrowed_money)
+    }
+
+    /// Ship the wares bought on the market of the given region when it was cleared, and pay for their transport.
+    ///
+    /// Returns the money refunded for the unspent part of the bids and the paid transport cost.
+    pub(crate) fn collect_purchases(
+        &mut self,
+        region: RegionId,
+        market: &mut Market,
+        routes: &[Route],
+        trader_id: TraderId,
+        time: DateTime,
+    ) -> Result<(Money, Money)> {
+        let refund = market.refund(Buyer::Trader(trader_id), &mut self.money)?;
+
+        let mut transport_cost = Money::ZERO;
+        for trade in market.collect_purchases(Buyer::Trader(trader_id)) {
+            let route = self
+                .destinations
+                .get(&(region, trade.ware().clone()))
+                .and_then(|route_index| routes.get(*route_index))
+                .ok_or_else(|| {
+                    Error::invariant_violation(format!(
+                        "trader {trader_id} bought {} in region {region} without a destination",
+                        trade.ware_amount()
+                    ))
+                })?;
+            let to = route.destination_from(region).ok_or_else(|| {
+                Error::invariant_violation(format!("route does not start in region {region}"))
+            })?;
+
+            let cost = route.transport_cost(trade.amount());
+            self.money = self.money.checked_sub(cost).map_err(|_| {
+                Error::invariant_violation(format!(
+                    "trader {trader_id} with {} cannot pay a transport cost of {cost}",
+                    self.money
+                ))
+            })?;
+            transport_cost = transport_cost.checked_add(cost)?;
+
+            debug!(
+                "Shipping {} bought for {} per item from region {region} to region {to}",
+                trade.ware_amount(),
+                trade.price_per_item()
+            );
+            let mut sourcing_cost = ApproximateMoney::from(trade.price_per_item()) * trade.amount();
+            sourcing_cost += cost.into();
+            self.shipments.push(Shipment {
+                ware_amount: trade.ware_amount(),
+                sourcing_cost_per_item: sourcing_cost / trade.amount(),
+                produced: trade.produced(),
+                from: region,
+                to,
+                arrives_at: time.checked_add(route.travel_time())?,
+            });
+        }
+        Ok((refund, transport_cost))
+    }
+
+    /// Collect the proceeds of the sales on the market of a region, and return them.
+    pub(crate) fn collect_money(
+        &mut self,
+        market: &mut Market,
+        trader_id: TraderId,
+    ) -> Result<Money> {
+        market.transfer_money(&mut self.money, Seller::Trader(trader_id))
+    }
+}
+
+impl Shipment {
+    pub fn ware_amount(&self) -> &WareAmount {
+        &self.ware_amount
+    }
+
+    /// The purchase price plus the transport cost per item.
+    pub fn sourcing_cost_per_item(&self) -> ApproximateMoney {
+        self.sourcing_cost_per_item
+    }
+
+    pub fn produced(&self) -> DateTime {
+        self.produced
+    }
+
+    pub fn from(&self) -> RegionId {
+        self.from
+    }
+
+    pub fn to(&self) -> RegionId {
+        self.to
+    }
+
+    pub fn arrives_at(&self) -> DateTime {
+        self.arrives_at
+    }
+}
+
+impl From<usize> for TraderId {
+    fn from(value: usize) -> Self {
+        Self(value)
+    }
+}
+
+impl From<TraderId> for usize {
+    fn from(value: TraderId) -> Self {
+        value.0
+    }
+}
+
+impl Display for TraderId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_xoshiro::Xoshiro256PlusPlus;
+
+    use crate::{
+        factory::FactoryId,
+        market::Seller,
+        money::{ApproximateMoney, Money},
+        region::{route::Route, Region, RegionId},
+        time::DateTime,
+        ware::{Ware, WareDefinition},
+        warehouse::WarehouseBatch,
+    };
+
+    use super::{Trader, TraderId};
+
+    fn source() -> RegionId {
+        RegionId::from(0)
+    }
+
+    fn destination() -> RegionId {
+        RegionId::from(1)
+    }
+
+    fn melon() -> Ware {
+        Ware::new(WareDefinition::new("trader_test_melon"))
+    }
+
+    /// A route from the source to the destination, which takes three hours and costs half a unit per melon.
+    fn routes() -> Vec<Route> {
+        vec![Route::new(source(), destination(), 100, 0.5, DateTime::from_hours(3)).unwrap()]
+    }
+
+    /// A source and a destination region whose markets offer melons at the given prices and amounts.
+    fn regions(source_offers: &[(u64, u64)], destination_offers: &[(u64, u64)]) -> Vec<Region> {
+        let mut regions = vec![Region::new("source"), Region::new("destination")];
+        for (region, offers) in regions.iter_mut().zip([source_offers, destination_offers]) {
+            let market = region.market_mut();
+            for &(price, amount) in offers {
+                market.offer(
+                    WarehouseBatch::new(melon(), amount, ApproximateMoney::from(1), DateTime::ZERO),
+                    Money::from(price),
+                    Seller::Factory(FactoryId::from(0)),
+                    DateTime::from_hours(10),
+                );
+            }
+            market.sort_offers(&mut Xoshiro256PlusPlus::seed_from_u64(0));
+        }
+        regions
+    }
+
+    /// Let the trader bid for shipments, clear the market of the source, and ship the purchases.
+    fn trade(trader: &mut Trader, regions: &mut [Region], time: DateTime) {
+        let trader_id = TraderId::from(0);
+        trader
+            .bid_for_shipments(regions, &routes(), &[melon()], trader_id)
+            .unwrap();
+        let market = regions[usize::from(source())].market_mut();
+        market
+            .clear(&mut Xoshiro256PlusPlus::seed_from_u64(0))
+            .unwrap();
+        trader
+            .collect_purchases(source(), market, &routes(), trader_id, time)
+            .unwrap();
+        market.sort_offers(&mut Xoshiro256PlusPlus::seed_from_u64(0));
+    }
+
+    fn amount_in_transit(trader: &Trader) -> u64 {
+        trader
+            .stock_in_transit()
+            .get(&melon())
+            .map_or(0, |stock_level| stock_level.amount())
+    }
+
+    #[test]
+    fn trader_ships_wares_to_the_region_where_they_sell_for_more() {
+        let mut regions = regions(&[(2, 10), (9, 10)], &[(10, 10)]);
+        let mut trader = Trader::new(Money::from(1_000));
+        trade(&mut trader, &mut regions, DateTime::from_hours(1));
+
+        // Only the melons at 2 leave a margin after the transport cost of 0.5 per melon.
+        let in_transit = trader.stock_in_transit()[&melon()];
+        assert_eq!(in_transit.amount(), 10);
+        assert_eq!(f64::from(in_transit.value()), 25.0);
+        assert_eq!(trader.money(), Money::from(1_000 - 20 - 5));
+
+        trader.receive_shipments(DateTime::from_hours(3)).unwrap();
+        assert_eq!(amount_in_transit(&trader), 10);
+        trader.receive_shipments(DateTime::from_hours(4)).unwrap();
+        assert_eq!(amount_in_transit(&trader), 0);
+        assert_eq!(trader.stock(destination())[&melon()].amount(), 10);
+
+        let market = regions[usize::from(destination())].market_mut();
+        trader
+            .offer_wares(
+                destination(),
+                market,
+                TraderId::from(0),
+                DateTime::from_hours(4),
+            )
+            .unwrap();
+        assert_eq!(market.unsold_amount(&melon()), 20);
+        assert_eq!(trader.stock(destination())[&melon()].amount(), 10);
+    }
+
+    #[test]
+    fn trader_sends_a_trial_shipment_to_a_region_without_a_price() {
+        let mut regions = regions(&[(2, 5), (3, 5)], &[]);
+        let mut trader = Trader::new(Money::from(1_000));
+        trade(&mut trader, &mut regions, DateTime::from_hours(1));
+
+        // A trial shipment only buys the cheapest offers.
+        assert_eq!(amount_in_transit(&trader), 5);
+
+        // No further melons are sent while the trial shipment is on its way.
+        trade(&mut trader, &mut regions, DateTime::from_hours(2));
+        assert_eq!(amount_in_transit(&trader), 5);
+    }
+
+    #[test]
+    fn trader_does_not_bid_for_its_own_offers() {
+        let mut regions = regions(&[(2, 5)], &[(10, 5)]);
+        let trader_id = TraderId::from(0);
+        let source_market = regions[usize::from(source())].market_mut();
+        source_market.offer(
+            WarehouseBatch::new(melon(), 10, ApproximateMoney::from(1), DateTime::ZERO),
+            Money::from(1),
+            Seller::Trader(trader_id),
+            DateTime::from_hours(10),
+        );
+        source_market.sort_offers(&mut Xoshiro256PlusPlus::seed_from_u64(0));
+
+        let mut trader = Trader::new(Money::from(1_000));
+        let escrow = trader
+            .bid_for_shipments(&mut regions, &routes(), &[melon()], trader_id)
+            .unwrap();
+
+        // Only the factory's 5 melons at 2 are bid for, not the trader's own cheaper melons.
+        assert_eq!(escrow.get(&source()), Some(&Money::from(10)));
+    }
+}
@@ -1,28 +1,238 @@
 use std::{
-    fmt::Display,
-    ops::{Div, Mul},
+    cell::RefCell,
+    collections::BTreeMap,
+    fmt::{Debug, Display},
+    hash::{Hash, Hasher},
+    sync::Arc,
 };
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub enum Ware {
-    Water,
-    Seed,
-    Apple,
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{
+    error::{Error, Result},
+    time::DateTime,
+};
+
+thread_local! {
+    /// The registry in which wares are looked up by id while deserialising, see [`WareRegistry::scope`].
+    static DESERIALISATION_REGISTRY: RefCell<Option<WareRegistry>> = const { RefCell::new(None) };
+}
+
+/// A cheap handle to the definition of a ware.
+///
+/// Wares are compared, ordered and hashed by their id,
+/// so the wares of a world must have distinct ids, see [`WareRegistry`].
+#[derive(Clone)]
+pub struct Ware(Arc<WareDefinition>);
+
+/// The properties of a ware, used to create it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WareDefinition {
+    id: String,
+    name: String,
+    plural: String,
+    countable: bool,
+    unit: Option<String>,
+    shelf_life: Option<DateTime>,
+    /// The fraction of the stored amount that spoils every hour.
+    decay_rate: Option<f64>,
+    /// The storage space taken by one item.
+    volume: u64,
+}
+
+/// The wares of a world or a scenario by their id, which ensures that each id has a single definition.
+#[derive(Debug, Clone, Default)]
+pub struct WareRegistry {
+    wares: BTreeMap<String, Ware>,
+}
+
+/// A ware was registered twice with different definitions.
+#[derive(Debug)]
+pub struct WareRegistrationError {
+    id: String,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct WareAmount {
     ware: Ware,
     amount: u64,
 }
 
 impl Ware {
+    /// Create a ware from its definition.
+    ///
+    /// Wares with the same id are equal, so use a [`WareRegistry`] to share them.
+    pub fn new(definition: WareDefinition) -> Self {
+        Self(Arc::new(definition))
+    }
+
+    pub fn definition(&self) -> &WareDefinition {
+        &self.0
+    }
+
+    pub fn id(&self) -> &str {
+        &self.0.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.0.name
+    }
+
     /// Returns true if this ware is countable in the English grammatical sense.
-    fn is_countable(&self) -> bool {
-        match self {
-            Self::Water => false,
-            Self::Seed | Self::Apple => true,
+    pub fn is_countable(&self) -> bool {
+        self.0.countable
+    }
+
+    /// The unit in which amounts of this ware are measured, if any.
+    pub fn unit(&self) -> Option<&str> {
+        self.0.unit.as_deref()
+    }
+
+    /// The time after which this ware spoils, if it is perishable.
+    pub fn shelf_life(&self) -> Option<DateTime> {
+        self.0.shelf_life
+    }
+
+    /// The fraction of the stored amount of this ware that spoils every hour, if it decays.
+    pub fn decay_rate(&self) -> Option<f64> {
+        self.0.decay_rate
+    }
+
+    /// The storage space taken by one item of this ware.
+    pub fn volume(&self) -> u64 {
+        self.0.volume
+    }
+
+    /// Returns true if this ware spoils after its shelf life or decays over time.
+    pub fn is_perishable(&self) -> bool {
+        self.shelf_life().is_some() || self.decay_rate().is_some()
+    }
+}
+
+impl WareRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create and register a ware.
+    ///
+    /// Registering the same definition again returns the registered ware,
+    /// registering a different ware with an existing id fails.
+    pub fn register(&mut self, definition: WareDefinition) -> Result<Ware, WareRegistrationError> {
+        self.insert(Ware::new(definition))
+    }
+
+    /// Register an existing ware.
+    ///
+    /// Returns the ware registered with the same definition before, if any,
+    /// and fails if a different ware with the same id is registered.
+    pub fn insert(&mut self, ware: Ware) -> Result<Ware, WareRegistrationError> {
+        if let Some(registered) = self.wares.get(ware.id()) {
+            return if registered.definition() == ware.definition() {
+                Ok(registered.clone())
+            } else {
+                Err(WareRegistrationError {
+                    id: ware.id().to_owned(),
+                })
+            };
         }
+
+        self.wares.insert(ware.id().to_owned(), ware.clone());
+        Ok(ware)
+    }
+
+    /// Returns the registered ware with the given id.
+    pub fn by_id(&self, id: &str) -> Option<&Ware> {
+        self.wares.get(id)
+    }
+
+    /// All registered wares, ordered by their id.
+    pub fn wares(&self) -> impl '_ + Iterator<Item = &Ware> {
+        self.wares.values()
+    }
+
+    /// Run the given function such that wares deserialised by it are looked up in this registry.
+    ///
+    /// Wares are serialised by their id, and cannot be deserialised outside of this scope.
+    pub(crate) fn scope<T>(&self, f: impl FnOnce() -> T) -> T {
+        let previous =
+            DESERIALISATION_REGISTRY.with(|registry| registry.replace(Some(self.clone())));
+        let result = f();
+        DESERIALISATION_REGISTRY.with(|registry| registry.replace(previous));
+        result
+    }
+}
+
+impl WareDefinition {
+    /// Create the definition of a countable ware without a unit that does not spoil.
+    ///
+    /// The name defaults to the id, and the plural to the name with an appended "s".
+    /// Each item takes one unit of storage space.
+    pub fn new(id: impl Into<String>) -> Self {
+        let id = id.into();
+        Self {
+            name: id.clone(),
+            plural: format!("{id}s"),
+            id,
+            countable: true,
+            unit: None,
+            shelf_life: None,
+            decay_rate: None,
+            volume: 1,
+        }
+    }
+
+    /// Set the display name, and reset the plural to the name with an appended "s".
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self.plural = format!("{}s", self.name);
+        self
+    }
+
+    pub fn with_plural(mut self, plural: impl Into<String>) -> Self {
+        self.plural = plural.into();
+        self
+    }
+
+    pub fn with_countable(mut self, countable: bool) -> Self {
+        self.countable = countable;
+        self
+    }
+
+    pub fn with_unit(mut self, unit: impl Into<String>) -> Self {
+        self.unit = Some(unit.into());
+        self
+    }
+
+    pub fn with_shelf_life(mut self, shelf_life: DateTime) -> Self {
+        self.shelf_life = Some(shelf_life);
+        self
+    }
+
+    /// Let the stored amount of the ware decay by the given fraction every hour.
+    ///
+    /// Fails unless the fraction is between zero and one, exclusive.
+    pub fn with_decay_rate(mut self, decay_rate: f64) -> Result<Self> {
+        if !(decay_rate > 0.0 && decay_rate < 1.0) {
+            return Err(Error::invalid_argument(format!(
+                "decay rate must be between 0 and 1, exclusive, but is {decay_rate}"
+            )));
+        }
+        self.decay_rate = Some(decay_rate);
+        Ok(self)
+    }
+
+    /// Set the storage space taken by one item, which must be positive.
+    pub fn with_volume(mut self, volume: u64) -> Result<Self> {
+        if volume == 0 {
+            return Err(Error::invalid_argument("volume must be positive"));
+        }
+        self.volume = volume;
+        Ok(self)
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
     }
 }
 
@@ -31,76 +241,171 @@ impl WareAmount {
         Self { ware, amount }
     }
 
-    pub fn ware(&self) -> Ware {
-        self.ware
+    pub fn ware(&self) -> &Ware {
+        &self.ware
     }
 
     pub fn amount(&self) -> u64 {
         self.amount
     }
 
-    pub fn with_amount(mut self, amount: u64) -> Self {
-        self.amount = amount;
-        self
+    /// The same ware in the given amount.
+    pub fn with_amount(&self, amount: u64) -> Self {
+        Self::new(self.ware.clone(), amount)
     }
-}
 
-impl Mul<u64> for WareAmount {
-    type Output = WareAmount;
+    /// Multiply the amount, failing if the result does not fit.
+    pub fn checked_mul(&self, rhs: u64) -> Result<Self> {
+        self.amount
+            .checked_mul(rhs)
+            .map(|amount| self.with_amount(amount))
+            .ok_or(Error::Overflow("ware amount multiplication"))
+    }
+}
 
-    fn mul(mut self, rhs: u64) -> Self::Output {
-        self.amount = self.amount.checked_mul(rhs).unwrap();
-        self
+impl PartialEq for Ware {
+    fn eq(&self, other: &Self) -> bool {
+        self.id() == other.id()
     }
 }
 
-impl Mul<u64> for &'_ WareAmount {
-    type Output = WareAmount;
+impl Eq for Ware {}
+
+impl PartialOrd for Ware {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
 
-    fn mul(self, rhs: u64) -> Self::Output {
-        *self * rhs
+impl Ord for Ware {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.id().cmp(other.id())
     }
 }
 
-impl Mul<u64> for &'_ mut WareAmount {
-    type Output = WareAmount;
+impl Hash for Ware {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id().hash(state);
+    }
+}
 
-    fn mul(self, rhs: u64) -> Self::Output {
-        *self * rhs
+/// Wares are serialised by their id, so they can only be deserialised within the scope of a registry,
+/// see [`WareRegistry::scope`].
+impl Serialize for Ware {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.id())
     }
 }
 
-impl Div for WareAmount {
-    type Output = u64;
+impl<'de> Deserialize<'de> for Ware {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let id = String::deserialize(deserializer)?;
+        DESERIALISATION_REGISTRY.with(|registry| match &*registry.borrow() {
+            Some(registry) => registry
+                .by_id(&id)
+                .cloned()
+                .ok_or_else(|| D::Error::custom(format!("unknown ware `{id}`"))),
+            None => Err(D::Error::custom(format!(
+                "cannot look up ware `{id}` outside of a ware registry"
+            ))),
+        })
+    }
+}
 
-    fn div(self, rhs: Self) -> Self::Output {
-        assert_eq!(self.ware, rhs.ware);
-        self.amount.checked_div(rhs.amount).unwrap()
+impl Debug for Ware {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Ware({})", self.id())
     }
 }
 
 impl Display for Ware {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Ware::Water => write!(f, "water"),
-            Ware::Seed => write!(f, "seed"),
-            Ware::Apple => write!(f, "apple"),
-        }
+        write!(f, "{}", self.name())
     }
 }
 
 impl Display for WareAmount {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(unit) = self.ware.unit() {
+            write!(f, "{} {unit} {}", self.amount, self.ware)
+        } else if self.amount != 1 && self.ware.is_countable() {
+            write!(f, "{} {}", self.amount, self.ware.0.plural)
+        } else {
+            write!(f, "{} {}", self.amount, self.ware)
+        }
+    }
+}
+
+impl Display for WareRegistrationError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "{} {}{}",
-            self.amount,
-            self.ware,
-            if self.amount != 1 && self.ware.is_countable() {
-                "s"
-            } else {
-                ""
-            },
+            "ware `{}` is already registered with a different definition",
+            self.id
         )
     }
 }
+
+impl std::error::Error for WareRegistrationError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wares_are_ordered_by_id() {
+        let pear = Ware::new(WareDefinition::new("pear"));
+        let apple = Ware::new(WareDefinition::new("apple"));
+        assert!(apple < pear);
+
+        let mut registry = WareRegistry::new();
+        registry.insert(pear.clone()).unwrap();
+        registry.insert(apple.clone()).unwrap();
+        assert_eq!(registry.wares().collect::<Vec<_>>(), [&apple, &pear]);
+    }
+
+    #[test]
+    fn registries_are_independent() {
+        let mut small = WareRegistry::new();
+        let mut large = WareRegistry::new();
+        small.register(WareDefinition::new("crate")).unwrap();
+        large
+            .register(WareDefinition::new("crate").with_volume(10).unwrap())
+            .unwrap();
+
+        assert_eq!(small.by_id("crate").unwrap().volume(), 1);
+        assert_eq!(large.by_id("crate").unwrap().volume(), 10);
+    }
+
+    #[test]
+    fn conflicting_definitions_are_rejected() {
+        let mut registry = WareRegistry::new();
+        let apple = registry.register(WareDefinition::new("apple")).unwrap();
+        assert_eq!(
+            registry.register(WareDefinition::new("apple")).unwrap(),
+            apple
+        );
+        assert!(registry
+            .register(WareDefinition::new("apple").with_plural("apple"))
+            .is_err());
+    }
+
+    #[test]
+    fn wares_are_deserialised_from_the_registry_in_scope() {
+        let mut registry = WareRegistry::new();
+        let apple = registry
+            .register(WareDefinition::new("apple").with_shelf_life(DateTime::from_hours(5)))
+            .unwrap();
+        let json = serde_json::to_string(&WareAmount::new(apple, 3)).unwrap();
+
+        assert!(serde_json::from_str::<WareAmount>(&json).is_err());
+        let ware_amount: WareAmount = registry.scope(|| serde_json::from_str(&json)).unwrap();
+        assert_eq!(
+            ware_amount.ware().shelf_life(),
+            Some(DateTime::from_hours(5))
+        );
+        assert!(WareRegistry::new()
+            .scope(|| serde_json::from_str::<WareAmount>(&json))
+            .is_err());
+    }
+}
@@ -1,28 +1,54 @@
 use std::{
-    collections::{HashMap, VecDeque},
+    collections::{BTreeMap, VecDeque},
     fmt::Display,
+    mem,
+    ops::AddAssign,
 };
 
+use serde::{Deserialize, Serialize};
+
+use template::WarehouseTemplate;
+use valuation::InventoryValuation;
+
 use crate::{
-    money::ApproximateMoney,
+    error::{Error, Result},
+    money::{ApproximateMoney, Money},
+    time::DateTime,
     ware::{Ware, WareAmount},
 };
 
-#[derive(Debug, Default)]
+pub mod template;
+pub mod valuation;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct Warehouse {
-    wares: HashMap<Ware, WarehouseEntry>,
+    wares: BTreeMap<Ware, WarehouseEntry>,
+    template: WarehouseTemplate,
+    /// The holding cost that accrued but was not charged yet, because it is less than a whole unit of money.
+    accrued_holding_cost: ApproximateMoney,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 struct WarehouseEntry {
     total_amount: u64,
     batches: VecDeque<PartialWarehouseBatch>,
+    /// The fraction of an item that decayed but did not spoil a whole item yet.
+    decay_remainder: f64,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 struct PartialWarehouseBatch {
     sourcing_cost_per_item: ApproximateMoney,
     amount: u64,
+    /// When the wares of this batch were produced.
+    produced: DateTime,
+}
+
+/// The amount of a ware in stock and its total sourcing cost.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StockLevel {
+    amount: u64,
+    value: ApproximateMoney,
 }
 
 #[derive(Debug)]
@@ -30,106 +56,373 @@ pub struct WarehouseBatch {
     ware: Ware,
     amount: u64,
     sourcing_cost_per_item: ApproximateMoney,
+    produced: DateTime,
 }
 
 impl Warehouse {
-    pub fn ware_amount(&self, ware: Ware) -> WareAmount {
-        match self.wares.get(&ware) {
-            Some(entry) => entry.ware_amount(ware),
-            None => WareAmount::new(ware, 0),
+    pub fn new(template: WarehouseTemplate) -> Self {
+        Self {
+            wares: Default::default(),
+            template,
+            accrued_holding_cost: ApproximateMoney::ZERO,
+        }
+    }
+
+    pub fn template(&self) -> &WarehouseTemplate {
+        &self.template
+    }
+
+    pub fn ware_amount(&self, ware: &Ware) -> WareAmount {
+        let amount = self.wares.get(ware).map_or(0, |entry| entry.total_amount);
+        WareAmount::new(ware.clone(), amount)
+    }
+
+    /// The total sourcing cost of all stored wares.
+    pub fn value(&self) -> ApproximateMoney {
+        let mut value = ApproximateMoney::ZERO;
+        for batch in self.wares.values().flat_map(|entry| &entry.batches) {
+            value += batch.sourcing_cost_per_item * batch.amount;
+        }
+        value
+    }
+
+    /// The stock level of every stored ware.
+    pub fn stock_levels(&self) -> BTreeMap<Ware, StockLevel> {
+        let mut stock_levels: BTreeMap<Ware, StockLevel> = BTreeMap::new();
+        for (ware, entry) in &self.wares {
+            let stock_level = stock_levels.entry(ware.clone()).or_default();
+            for batch in &entry.batches {
+                stock_level.add(batch.amount, batch.sourcing_cost_per_item);
+            }
+        }
+        stock_levels
+    }
+
+    /// The storage space taken by all stored wares.
+    pub fn volume(&self) -> u64 {
+        volume_of(
+            self.wares
+                .iter()
+                .map(|(ware, entry)| (ware, entry.total_amount)),
+        )
+    }
+
+    /// How often the given wares fit into the free space of the warehouse,
+    /// if the reserved wares are stored in addition to the stored ones.
+    ///
+    /// Returns [`u64::MAX`] if the capacity does not limit the given wares.
+    pub fn fitting_multiple(
+        &self,
+        ware_amounts: &[WareAmount],
+        reserved: &BTreeMap<Ware, u64>,
+    ) -> u64 {
+        let mut multiple = u64::MAX;
+        for ware_amount in ware_amounts {
+            let ware = ware_amount.ware();
+            let Some(capacity) = self.template.ware_capacity(ware) else {
+                continue;
+            };
+            if ware_amount.amount() > 0 {
+                let used =
+                    self.ware_amount(ware).amount() + reserved.get(ware).copied().unwrap_or(0);
+                multiple = multiple.min(capacity.saturating_sub(used) / ware_amount.amount());
+            }
+        }
+
+        let volume_per_multiple = volume_of(
+            ware_amounts
+                .iter()
+                .map(|ware_amount| (ware_amount.ware(), ware_amount.amount())),
+        );
+        if let Some(capacity) = self
+            .template
+            .volume_capacity()
+            .filter(|_| volume_per_multiple > 0)
+        {
+            let used = self.volume().saturating_add(volume_of(
+                reserved.iter().map(|(ware, amount)| (ware, *amount)),
+            ));
+            multiple = multiple.min(capacity.saturating_sub(used) / volume_per_multiple);
         }
+        multiple
+    }
+
+    /// Returns true if the given wares fit into the free space of the warehouse.
+    pub fn fits(&self, ware_amounts: &[WareAmount]) -> bool {
+        self.fitting_multiple(ware_amounts, &BTreeMap::new()) >= 1
     }
 
+    /// Store wares that were produced at the given time, as far as they fit into the free space of the warehouse.
+    ///
+    /// Returns the amount that did not fit and was refused.
     pub fn insert_ware(
         &mut self,
         ware_amount: WareAmount,
         sourcing_cost_per_item: ApproximateMoney,
-    ) {
-        if let Some(entry) = self.wares.get_mut(&ware_amount.ware()) {
-            entry.insert(ware_amount.amount(), sourcing_cost_per_item);
+        produced: DateTime,
+    ) -> Result<u64> {
+        let fitting_amount = self
+            .fitting_multiple(&[ware_amount.with_amount(1)], &BTreeMap::new())
+            .min(ware_amount.amount());
+        let refused_amount = ware_amount.amount() - fitting_amount;
+        let ware_amount = ware_amount.with_amount(fitting_amount);
+        if fitting_amount == 0 {
+            return Ok(refused_amount);
+        }
+
+        if let Some(entry) = self.wares.get_mut(ware_amount.ware()) {
+            entry.insert(
+                ware_amount.amount(),
+                sourcing_cost_per_item,
+                produced,
+                self.template.valuation(),
+            )?;
         } else {
             self.wares.insert(
-                ware_amount.ware(),
-                WarehouseEntry::new(ware_amount.amount(), sourcing_cost_per_item),
+                ware_amount.ware().clone(),
+                WarehouseEntry::new(ware_amount.amount(), sourcing_cost_per_item, produced),
             );
         }
+        Ok(refused_amount)
     }
 
     /// Remove the given amount of the given ware.
     ///
-    /// Returns the average sourcing cost per item of the wares that were removed.
-    pub fn remove_ware(&mut self, ware_amount: WareAmount) -> ApproximateMoney {
-        let entry = self.wares.get_mut(&ware_amount.ware()).unwrap();
-        let result = entry.remove(ware_amount.amount());
+    /// Returns the average sourcing cost per item of the wares that were removed,
+    /// or an error if less than the given amount is stored.
+    pub fn remove_ware(&mut self, ware_amount: WareAmount) -> Result<ApproximateMoney> {
+        let mut total_sourcing_cost = ApproximateMoney::ZERO;
+        for batch in self.remove_partial_batches(&ware_amount)? {
+            total_sourcing_cost += batch.sourcing_cost_per_item * batch.amount;
+        }
+        Ok(total_sourcing_cost / ware_amount.amount())
+    }
+
+    /// Remove the given amount of the given ware, keeping the sourcing cost and production time of each batch.
+    ///
+    /// Fails if less than the given amount is stored.
+    pub fn remove_batches(&mut self, ware_amount: WareAmount) -> Result<Vec<WarehouseBatch>> {
+        Ok(self
+            .remove_partial_batches(&ware_amount)?
+            .into_iter()
+            .map(|partial_batch| partial_batch.into_batch(ware_amount.ware().clone()))
+            .collect())
+    }
+
+    fn remove_partial_batches(
+        &mut self,
+        ware_amount: &WareAmount,
+    ) -> Result<Vec<PartialWarehouseBatch>> {
+        let stored_amount = self.ware_amount(ware_amount.ware());
+        if ware_amount.amount() == 0 || stored_amount.amount() < ware_amount.amount() {
+            return Err(Error::invalid_argument(format!(
+                "cannot remove {ware_amount} from a warehouse storing {stored_amount}"
+            )));
+        }
+
+        let entry = self.wares.get_mut(ware_amount.ware()).unwrap();
+        let result = entry.remove(ware_amount.amount(), self.template.valuation());
         if entry.is_empty() {
-            self.wares.remove(&ware_amount.ware());
+            self.wares.remove(ware_amount.ware());
+        }
+        Ok(result)
+    }
+
+    /// Store a batch, as far as it fits into the free space of the warehouse.
+    ///
+    /// Returns the amount that did not fit and was refused.
+    pub fn insert_batch(&mut self, batch: WarehouseBatch) -> Result<u64> {
+        self.insert_ware(
+            WareAmount::new(batch.ware, batch.amount),
+            batch.sourcing_cost_per_item,
+            batch.produced,
+        )
+    }
+
+    /// Charge the holding cost for an hour of storing the stored and the reserved wares.
+    ///
+    /// Fractions of a unit of money are carried over until they add up to a whole unit.
+    /// Returns the money due.
+    pub fn charge_holding_cost(&mut self, reserved: &BTreeMap<Ware, u64>) -> Money {
+        let volume = self.volume().saturating_add(volume_of(
+            reserved.iter().map(|(ware, amount)| (ware, *amount)),
+        ));
+        self.accrued_holding_cost += self.template.holding_cost() * volume;
+        let accrued = f64::from(self.accrued_holding_cost);
+        let due = accrued.floor();
+        self.accrued_holding_cost = (accrued - due).into();
+        Money::from(due as u64)
+    }
+
+    /// Let the stored wares spoil during the hour that ends at the given time.
+    ///
+    /// Batches spoil completely once they are older than the shelf life of their ware,
+    /// and wares with a decay rate lose that fraction of their stored amount, starting with the oldest batches.
+    /// Returns the spoiled wares, valued at their sourcing cost.
+    pub fn spoil(&mut self, time: DateTime) -> BTreeMap<Ware, StockLevel> {
+        let mut spoiled = BTreeMap::new();
+        for (ware, entry) in &mut self.wares {
+            let mut stock_level = StockLevel::default();
+            if let Some(shelf_life) = ware.shelf_life() {
+                entry.spoil_expired(time, shelf_life, &mut stock_level);
+            }
+            if let Some(decay_rate) = ware.decay_rate() {
+                entry.decay(decay_rate, &mut stock_level);
+            }
+            if stock_level.amount() > 0 {
+                spoiled.insert(ware.clone(), stock_level);
+            }
         }
-        result
+        self.wares.retain(|_, entry| !entry.is_empty());
+        spoiled
     }
 
     pub fn drain(&mut self) -> impl use<'_> + Iterator<Item = WarehouseBatch> {
-        self.wares.drain().flat_map(|(ware, entry)| {
-            entry
-                .batches
-                .into_iter()
-                .map(move |partial_batch| partial_batch.into_batch(ware))
-        })
+        mem::take(&mut self.wares)
+            .into_iter()
+            .flat_map(|(ware, entry)| {
+                entry
+                    .batches
+                    .into_iter()
+                    .map(move |partial_batch| partial_batch.into_batch(ware.clone()))
+            })
     }
 }
 
+/// The storage space taken by the given amounts of wares.
+fn volume_of<'ware>(ware_amounts: impl Iterator<Item = (&'ware Ware, u64)>) -> u64 {
+    ware_amounts.fold(0, |volume: u64, (ware, amount)| {
+        volume.saturating_add(amount.saturating_mul(ware.volume()))
+    })
+}
+
 impl WarehouseEntry {
-    pub fn new(amount: u64, sourcing_cost_per_item: ApproximateMoney) -> Self {
+    pub fn new(amount: u64, sourcing_cost_per_item: ApproximateMoney, produced: DateTime) -> Self {
         Self {
             total_amount: amount,
             batches: [PartialWarehouseBatch {
                 sourcing_cost_per_item,
                 amount,
+                produced,
             }]
             .into_iter()
             .collect(),
+            decay_remainder: 0.0,
         }
     }
 
-    pub fn ware_amount(&self, ware: Ware) -> WareAmount {
-        WareAmount::new(ware, self.total_amount)
-    }
-
     pub fn is_empty(&self) -> bool {
         self.total_amount == 0
     }
 
-    pub fn insert(&mut self, amount: u64, sourcing_cost_per_item: ApproximateMoney) {
-        self.total_amount = self.total_amount.checked_add(amount).unwrap();
+    /// Add a batch after the stored ones.
+    ///
+    /// With [`InventoryValuation::WeightedAverage`], all batches are revalued at their new average sourcing cost.
+    pub fn insert(
+        &mut self,
+        amount: u64,
+        sourcing_cost_per_item: ApproximateMoney,
+        produced: DateTime,
+        valuation: InventoryValuation,
+    ) -> Result<()> {
+        self.total_amount = self
+            .total_amount
+            .checked_add(amount)
+            .ok_or(Error::Overflow("stored ware amount"))?;
         self.batches.push_back(PartialWarehouseBatch {
             sourcing_cost_per_item,
             amount,
+            produced,
         });
+
+        if valuation == InventoryValuation::WeightedAverage {
+            let mut value = ApproximateMoney::ZERO;
+            for batch in &self.batches {
+                value += batch.sourcing_cost_per_item * batch.amount;
+            }
+            let average_sourcing_cost = value / self.total_amount;
+            for batch in &mut self.batches {
+                batch.sourcing_cost_per_item = average_sourcing_cost;
+            }
+        }
+        Ok(())
     }
 
-    pub fn remove(&mut self, mut amount: u64) -> ApproximateMoney {
-        assert!(amount > 0);
-        self.total_amount = self.total_amount.checked_sub(amount).unwrap();
-        let mut total_sourcing_cost = ApproximateMoney::ZERO;
-        let total_removed_amount = amount;
+    /// Remove the given amount, which must not exceed the total amount,
+    /// from the batches in the order given by the valuation method.
+    ///
+    /// Returns the removed parts of the batches.
+    pub fn remove(
+        &mut self,
+        mut amount: u64,
+        valuation: InventoryValuation,
+    ) -> Vec<PartialWarehouseBatch> {
+        self.total_amount -= amount;
+        let mut removed_batches = Vec::new();
 
         while amount > 0 {
-            let batch = self.batches.front_mut().unwrap();
-            let sourcing_cost_per_item = batch.sourcing_cost_per_item;
-
-            let batch_amount = if amount >= batch.amount {
+            let index = match valuation {
+                // Under the weighted average method all batches have the same sourcing cost,
+                // so they can be removed in any order.
+                InventoryValuation::Fifo | InventoryValuation::WeightedAverage => 0,
+                InventoryValuation::Lifo => self.batches.len() - 1,
+                InventoryValuation::OldestProducedFirst => self.oldest_batch(),
+            };
+            let batch = &mut self.batches[index];
+            if amount >= batch.amount {
                 amount -= batch.amount;
-                self.batches.pop_front().unwrap().amount
+                removed_batches.push(self.batches.remove(index).unwrap());
             } else {
-                let batch_amount = amount;
-                batch.amount -= batch_amount;
+                batch.amount -= amount;
+                removed_batches.push(PartialWarehouseBatch {
+                    sourcing_cost_per_item: batch.sourcing_cost_per_item,
+                    amount,
+                    produced: batch.produced,
+                });
                 amount = 0;
-                batch_amount
-            };
+            }
+        }
+
+        removed_batches
+    }
+
+    /// The index of the first batch that was produced earliest.
+    fn oldest_batch(&self) -> usize {
+        let mut oldest = 0;
+        for (index, batch) in self.batches.iter().enumerate() {
+            if batch.produced < self.batches[oldest].produced {
+                oldest = index;
+            }
+        }
+        oldest
+    }
 
-            total_sourcing_cost += sourcing_cost_per_item * batch_amount;
+    /// Remove all batches produced at least the shelf life before the given time.
+    fn spoil_expired(&mut self, time: DateTime, shelf_life: DateTime, spoiled: &mut StockLevel) {
+        self.batches.retain(|batch| {
+            if time.saturating_sub(batch.produced) < shelf_life {
+                return true;
+            }
+            spoiled.add(batch.amount, batch.sourcing_cost_per_item);
+            false
+        });
+        self.total_amount -= spoiled.amount();
+    }
+
+    /// Remove the fraction of the total amount that decays in an hour, starting with the oldest wares.
+    ///
+    /// Fractions of items are carried over to the next hour, so that small stocks decay as well.
+    fn decay(&mut self, decay_rate: f64, spoiled: &mut StockLevel) {
+        let decayed_amount = self.total_amount as f64 * decay_rate + self.decay_remainder;
+        let spoiled_amount = (decayed_amount.floor() as u64).min(self.total_amount);
+        self.decay_remainder = decayed_amount - spoiled_amount as f64;
+        if spoiled_amount == 0 {
+            return;
         }
 
-        total_sourcing_cost / total_removed_amount
+        for batch in self.remove(spoiled_amount, InventoryValuation::OldestProducedFirst) {
+            spoiled.add(batch.amount, batch.sourcing_cost_per_item);
+        }
     }
 }
 
@@ -139,13 +432,51 @@ impl PartialWarehouseBatch {
             ware,
             amount: self.amount,
             sourcing_cost_per_item: self.sourcing_cost_per_item,
+            produced: self.produced,
         }
     }
 }
 
+impl StockLevel {
+    /// Add the given amount of wares with the given sourcing cost per item.
+    pub fn add(&mut self, amount: u64, sourcing_cost_per_item: ApproximateMoney) {
+        self.amount += amount;
+        self.value += sourcing_cost_per_item * amount;
+    }
+
+    pub fn amount(&self) -> u64 {
+        self.amount
+    }
+
+    pub fn value(&self) -> ApproximateMoney {
+        self.value
+    }
+}
+
+impl AddAssign for StockLevel {
+    fn add_assign(&mut self, rhs: Self) {
+        self.amount += rhs.amount;
+        self.value += rhs.value;
+    }
+}
+
 impl WarehouseBatch {
-    pub fn ware(&self) -> Ware {
-        self.ware
+    pub fn new(
+        ware: Ware,
+        amount: u64,
+        sourcing_cost_per_item: ApproximateMoney,
+        produced: DateTime,
+    ) -> Self {
+        Self {
+            ware,
+            amount,
+            sourcing_cost_per_item,
+            produced,
+        }
+    }
+
+    pub fn ware(&self) -> &Ware {
+        &self.ware
     }
 
     pub fn amount(&self) -> u64 {
@@ -155,6 +486,34 @@ impl WarehouseBatch {
     pub fn sourcing_cost_per_item(&self) -> ApproximateMoney {
         self.sourcing_cost_per_item
     }
+
+    /// When the wares of this batch were produced.
+    pub fn produced(&self) -> DateTime {
+        self.produced
+    }
+
+    /// The time left at the given time until the wares of this batch spoil, if the ware has a shelf life.
+    pub fn remaining_shelf_life(&self, time: DateTime) -> Option<DateTime> {
+        let age = time.saturating_sub(self.produced);
+        self.ware
+            .shelf_life()
+            .map(|shelf_life| shelf_life.saturating_sub(age))
+    }
+
+    /// How long this batch may be offered on the market from the given time, at most the given offer lifetime.
+    ///
+    /// Offers end when the wares spoil, and offers of decaying wares end after an hour,
+    /// such that the unsold wares decay in the warehouse of the seller, who writes them off.
+    pub fn offer_lifetime(&self, offer_lifetime: DateTime, time: DateTime) -> DateTime {
+        let mut offer_lifetime = offer_lifetime;
+        if let Some(remaining_shelf_life) = self.remaining_shelf_life(time) {
+            offer_lifetime = offer_lifetime.min(remaining_shelf_life);
+        }
+        if self.ware.decay_rate().is_some() {
+            offer_lifetime = offer_lifetime.min(DateTime::from_hours(1));
+        }
+        offer_lifetime
+    }
 }
 
 impl Display for Warehouse {
@@ -168,9 +527,311 @@ impl Display for Warehouse {
                 write!(f, ", ")?;
             }
 
-            let ware_amount = WareAmount::new(*ware, entry.total_amount);
+            let ware_amount = WareAmount::new(ware.clone(), entry.total_amount);
             write!(f, "{ware_amount}")?;
         }
         write!(f, "}}")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use crate::{
+        money::ApproximateMoney,
+        time::DateTime,
+        ware::{Ware, WareAmount, WareDefinition},
+    };
+
+    use super::{
+        template::WarehouseTemplate, valuation::InventoryValuation, Warehouse, WarehouseBatch,
+    };
+
+    const ALL_VALUATIONS: [InventoryValuation; 4] = [
+        InventoryValuation::Fifo,
+        InventoryValuation::Lifo,
+        InventoryValuation::WeightedAverage,
+        InventoryValuation::OldestProducedFirst,
+    ];
+
+    fn fruit() -> Ware {
+        Ware::new(WareDefinition::new("valuation_test_fruit"))
+    }
+
+    /// A warehouse that received three batches of ten fruits,
+    /// which did not arrive in the order in which they were produced.
+    fn stocked_warehouse(valuation: InventoryValuation) -> Warehouse {
+        let mut warehouse = Warehouse::new(WarehouseTemplate::new().with_valuation(valuation));
+        for (sourcing_cost_per_item, produced) in [(1, 2), (2, 0), (3, 1)] {
+            warehouse
+                .insert_batch(WarehouseBatch::new(
+                    fruit(),
+                    10,
+                    ApproximateMoney::from(sourcing_cost_per_item),
+                    DateTime::from_hours(produced),
+                ))
+                .unwrap();
+        }
+        warehouse
+    }
+
+    fn assert_approximately_eq(actual: ApproximateMoney, expected: f64) {
+        let actual = f64::from(actual);
+        assert!(
+            (actual - expected).abs() < 1e-9,
+            "expected {expected}, but got {actual}"
+        );
+    }
+
+    #[test]
+    fn valuation_methods_cost_the_same_removal_differently() {
+        // The average sourcing cost per item of removing 15 fruits, and the value of the remaining 15 fruits.
+        let expected = [
+            // 10 fruits at 1€ and 5 at 2€.
+            (InventoryValuation::Fifo, 20.0 / 15.0, 40.0),
+            // 10 fruits at 3€ and 5 at 2€.
+            (InventoryValuation::Lifo, 40.0 / 15.0, 20.0),
+            // All fruits at 2€.
+            (InventoryValuation::WeightedAverage, 2.0, 30.0),
+            // The fruits produced at hour 0 at 2€, and 5 of those produced at hour 1 at 3€.
+            (InventoryValuation::OldestProducedFirst, 35.0 / 15.0, 25.0),
+        ];
+
+        for (valuation, expected_cost, expected_remaining_value) in expected {
+            let mut warehouse = stocked_warehouse(valuation);
+            let cost = warehouse.remove_ware(WareAmount::new(fruit(), 15)).unwrap();
+            assert_approximately_eq(cost, expected_cost);
+            assert_approximately_eq(warehouse.value(), expected_remaining_value);
+            assert_eq!(warehouse.ware_amount(&fruit()).amount(), 15);
+        }
+    }
+
+    #[test]
+    fn valuation_methods_preserve_the_total_value() {
+        for valuation in ALL_VALUATIONS {
+            let mut warehouse = stocked_warehouse(valuation);
+            let mut removed_value = 0.0;
+            for amount in [7, 11, 12] {
+                let cost = warehouse
+                    .remove_ware(WareAmount::new(fruit(), amount))
+                    .unwrap();
+                removed_value += f64::from(cost) * amount as f64;
+            }
+            assert_approximately_eq(ApproximateMoney::from(removed_value), 60.0);
+            assert_approximately_eq(warehouse.value(), 0.0);
+        }
+    }
+
+    #[test]
+    fn weighted_average_is_updated_when_wares_arrive() {
+        let mut warehouse = Warehouse::new(
+            WarehouseTemplate::new().with_valuation(InventoryValuation::WeightedAverage),
+        );
+        warehouse
+            .insert_ware(WareAmount::new(fruit(), 10), 1.0.into(), DateTime::ZERO)
+            .unwrap();
+        let cost = warehouse.remove_ware(WareAmount::new(fruit(), 5)).unwrap();
+        assert_approximately_eq(cost, 1.0);
+
+        warehouse
+            .insert_ware(
+                WareAmount::new(fruit(), 5),
+                4.0.into(),
+                DateTime::from_hours(1),
+            )
+            .unwrap();
+        let cost = warehouse.remove_ware(WareAmount::new(fruit(), 2)).unwrap();
+        assert_approximately_eq(cost, 2.5);
+    }
+
+    #[test]
+    fn removed_batches_keep_their_production_time() {
+        let mut warehouse = stocked_warehouse(InventoryValuation::OldestProducedFirst);
+        let batches = warehouse
+            .remove_batches(WareAmount::new(fruit(), 15))
+            .unwrap();
+        let removed: Vec<_> = batches
+            .iter()
+            .map(|batch| (batch.amount(), batch.produced().into_hours()))
+            .collect();
+        assert_eq!(removed, [(10, 0), (5, 1)]);
+    }
+
+    /// A warehouse storing five fruits, with room for 25 fruits and a volume of 100.
+    fn limited_warehouse() -> Warehouse {
+        let mut warehouse = Warehouse::new(
+            WarehouseTemplate::new()
+                .with_ware_capacity(fruit(), 25)
+                .with_volume_capacity(100),
+        );
+        warehouse
+            .insert_ware(WareAmount::new(fruit(), 5), 1.0.into(), DateTime::ZERO)
+            .unwrap();
+        warehouse
+    }
+
+    #[test]
+    fn fitting_multiple_respects_ware_and_volume_capacities() {
+        let boxed = Ware::new(
+            WareDefinition::new("capacity_test_box")
+                .with_volume(10)
+                .unwrap(),
+        );
+        let warehouse = limited_warehouse();
+        let no_reservations = BTreeMap::new();
+
+        // 20 more fruits fit, while the volume would allow 95.
+        let ten_fruits = [WareAmount::new(fruit(), 10)];
+        assert_eq!(warehouse.fitting_multiple(&ten_fruits, &no_reservations), 2);
+        // Boxes are only limited by the volume.
+        let one_box = [WareAmount::new(boxed.clone(), 1)];
+        assert_eq!(warehouse.fitting_multiple(&one_box, &no_reservations), 9);
+        // One fruit and one box take a volume of 11.
+        let fruit_and_box = [
+            WareAmount::new(fruit(), 1),
+            WareAmount::new(boxed.clone(), 1),
+        ];
+        assert_eq!(
+            warehouse.fitting_multiple(&fruit_and_box, &no_reservations),
+            8
+        );
+        assert!(warehouse.fits(&fruit_and_box));
+        assert!(!warehouse.fits(&[WareAmount::new(boxed.clone(), 10)]));
+
+        // Reserved wares take up space, for their own ware and in volume.
+        let reserved = BTreeMap::from([(fruit(), 6), (boxed, 3)]);
+        assert_eq!(warehouse.fitting_multiple(&ten_fruits, &reserved), 1);
+        assert_eq!(warehouse.fitting_multiple(&one_box, &reserved), 5);
+
+        assert_eq!(
+            warehouse.fitting_multiple(&[WareAmount::new(fruit(), 0)], &no_reservations),
+            u64::MAX
+        );
+        assert_eq!(
+            Warehouse::new(WarehouseTemplate::new()).fitting_multiple(&one_box, &no_reservations),
+            u64::MAX
+        );
+    }
+
+    #[test]
+    fn wares_beyond_the_capacity_are_refused() {
+        let mut warehouse = limited_warehouse();
+        let refused_amount = warehouse
+            .insert_ware(WareAmount::new(fruit(), 30), 1.0.into(), DateTime::ZERO)
+            .unwrap();
+        assert_eq!(refused_amount, 10);
+        assert_eq!(warehouse.ware_amount(&fruit()).amount(), 25);
+
+        let refused_amount = warehouse
+            .insert_batch(WarehouseBatch::new(fruit(), 3, 1.0.into(), DateTime::ZERO))
+            .unwrap();
+        assert_eq!(refused_amount, 3);
+        assert_eq!(warehouse.ware_amount(&fruit()).amount(), 25);
+    }
+
+    #[test]
+    fn holding_costs_accrue_until_they_add_up_to_whole_units() {
+        let template = WarehouseTemplate::new().with_holding_cost(0.25).unwrap();
+        let mut warehouse = Warehouse::new(template.clone());
+        warehouse
+            .insert_ware(WareAmount::new(fruit(), 1), 1.0.into(), DateTime::ZERO)
+            .unwrap();
+        let charged: Vec<_> = (0..8)
+            .map(|_| u64::from(warehouse.charge_holding_cost(&BTreeMap::new())))
+            .collect();
+        assert_eq!(charged, [0, 0, 0, 1, 0, 0, 0, 1]);
+
+        // Reserved wares are charged as if they were stored.
+        let mut warehouse = Warehouse::new(template);
+        warehouse
+            .insert_ware(WareAmount::new(fruit(), 1), 1.0.into(), DateTime::ZERO)
+            .unwrap();
+        let reserved = BTreeMap::from([(fruit(), 1)]);
+        let charged: Vec<_> = (0..4)
+            .map(|_| u64::from(warehouse.charge_holding_cost(&reserved)))
+            .collect();
+        assert_eq!(charged, [0, 1, 0, 1]);
+    }
+
+    #[test]
+    fn batches_spoil_at_the_end_of_their_shelf_life() {
+        let apple = Ware::new(
+            WareDefinition::new("warehouse_test_apple").with_shelf_life(DateTime::from_hours(3)),
+        );
+        let mut warehouse = Warehouse::new(WarehouseTemplate::new());
+        for (sourcing_cost_per_item, produced) in [(1, 0), (2, 1)] {
+            warehouse
+                .insert_ware(
+                    WareAmount::new(apple.clone(), 10),
+                    ApproximateMoney::from(sourcing_cost_per_item),
+                    DateTime::from_hours(produced),
+                )
+                .unwrap();
+        }
+
+        assert!(warehouse.spoil(DateTime::from_hours(2)).is_empty());
+
+        // Only the apples produced at hour 0 are three hours old.
+        let spoiled = warehouse.spoil(DateTime::from_hours(3));
+        assert_eq!(spoiled[&apple].amount(), 10);
+        assert_approximately_eq(spoiled[&apple].value(), 10.0);
+        assert_eq!(warehouse.ware_amount(&apple).amount(), 10);
+        assert_approximately_eq(warehouse.value(), 20.0);
+    }
+
+    #[test]
+    fn decay_carries_fractions_of_items_over_to_the_next_hour() {
+        let pear = Ware::new(
+            WareDefinition::new("warehouse_test_pear")
+                .with_decay_rate(0.04)
+                .unwrap(),
+        );
+        let mut warehouse = Warehouse::new(WarehouseTemplate::new());
+        for (sourcing_cost_per_item, produced) in [(2, 1), (1, 0)] {
+            warehouse
+                .insert_ware(
+                    WareAmount::new(pear.clone(), 5),
+                    ApproximateMoney::from(sourcing_cost_per_item),
+                    DateTime::from_hours(produced),
+                )
+                .unwrap();
+        }
+
+        // Each hour, 0.4 of the 10 pears decay, which adds up to a whole pear in the third hour.
+        assert!(warehouse.spoil(DateTime::from_hours(2)).is_empty());
+        assert!(warehouse.spoil(DateTime::from_hours(3)).is_empty());
+        let spoiled = warehouse.spoil(DateTime::from_hours(4));
+        assert_eq!(spoiled[&pear].amount(), 1);
+        // The oldest pears decay first.
+        assert_approximately_eq(spoiled[&pear].value(), 1.0);
+        assert_eq!(warehouse.ware_amount(&pear).amount(), 9);
+    }
+
+    #[test]
+    fn offers_of_perishable_wares_end_early() {
+        let apple = Ware::new(
+            WareDefinition::new("offer_test_apple").with_shelf_life(DateTime::from_hours(10)),
+        );
+        let pear = Ware::new(
+            WareDefinition::new("offer_test_pear")
+                .with_decay_rate(0.1)
+                .unwrap(),
+        );
+        let offer_lifetime = DateTime::from_hours(24);
+        let time = DateTime::from_hours(4);
+
+        let batch = WarehouseBatch::new(fruit(), 1, ApproximateMoney::ZERO, DateTime::ZERO);
+        assert_eq!(batch.offer_lifetime(offer_lifetime, time), offer_lifetime);
+        let batch = WarehouseBatch::new(apple, 1, ApproximateMoney::ZERO, DateTime::ZERO);
+        assert_eq!(
+            batch.offer_lifetime(offer_lifetime, time),
+            DateTime::from_hours(6)
+        );
+        let batch = WarehouseBatch::new(pear, 1, ApproximateMoney::ZERO, DateTime::ZERO);
+        assert_eq!(
+            batch.offer_lifetime(offer_lifetime, time),
+            DateTime::from_hours(1)
+        );
+    }
+}
@@ -0,0 +1,75 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{Error, Result},
+    money::ApproximateMoney,
+    ware::Ware,
+};
+
+use super::valuation::InventoryValuation;
+
+/// The capacity of a warehouse, the cost of storing wares in it, and how it values them.
+///
+/// By default, a warehouse can store any amount of wares for free, and values them first in, first out.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WarehouseTemplate {
+    /// The total storage space, measured in the volume of the wares.
+    volume_capacity: Option<u64>,
+    /// The maximum amounts of individual wares.
+    ware_capacities: BTreeMap<Ware, u64>,
+    /// The cost per unit of volume and hour.
+    holding_cost: ApproximateMoney,
+    valuation: InventoryValuation,
+}
+
+impl WarehouseTemplate {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limit the total volume of the stored wares.
+    pub fn with_volume_capacity(mut self, volume_capacity: u64) -> Self {
+        self.volume_capacity = Some(volume_capacity);
+        self
+    }
+
+    /// Limit the stored amount of the given ware.
+    pub fn with_ware_capacity(mut self, ware: Ware, capacity: u64) -> Self {
+        self.ware_capacities.insert(ware, capacity);
+        self
+    }
+
+    /// Charge the given cost per unit of volume and hour for the stored wares.
+    pub fn with_holding_cost(mut self, holding_cost: f64) -> Result<Self> {
+        if !(holding_cost >= 0.0 && holding_cost.is_finite()) {
+            return Err(Error::invalid_argument(format!(
+                "holding cost must not be negative, but is {holding_cost}"
+            )));
+        }
+        self.holding_cost = holding_cost.into();
+        Ok(self)
+    }
+
+    pub fn with_valuation(mut self, valuation: InventoryValuation) -> Self {
+        self.valuation = valuation;
+        self
+    }
+
+    pub fn volume_capacity(&self) -> Option<u64> {
+        self.volume_capacity
+    }
+
+    pub fn ware_capacity(&self, ware: &Ware) -> Option<u64> {
+        self.ware_capacities.get(ware).copied()
+    }
+
+    pub fn holding_cost(&self) -> ApproximateMoney {
+        self.holding_cost
+    }
+
+    pub fn valuation(&self) -> InventoryValuation {
+        self.valuation
+    }
+}
@@ -0,0 +1,22 @@
+use serde::{Deserialize, Serialize};
+
+/// How a warehouse determines the sourcing cost of the wares it gives out.
+///
+/// The sourcing cost of the removed wares becomes part of the sourcing cost of the products,
+/// or the cost of goods sold, so the method influences the prices of the factories.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum InventoryValuation {
+    /// The wares that arrived first are removed first.
+    #[default]
+    Fifo,
+    /// The wares that arrived last are removed first.
+    Lifo,
+    /// All stored wares are valued at their average sourcing cost, which is updated whenever wares arrive.
+    WeightedAverage,
+    /// The wares that were produced first are removed first, also known as first expired, first out,
+    /// each valued at the sourcing cost of its own batch.
+    ///
+    /// Unlike [`Self::Fifo`], this follows the physical flow of perishable wares,
+    /// even if older wares arrive later, for example when they are bought or return from the market.
+    OldestProducedFirst,
+}
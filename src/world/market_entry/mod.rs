@@ -0,0 +1,169 @@
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{Error, Result},
+    factory::{
+        pricing::PricingStrategy,
+        template::{EstimatedProfitMargin, FactoryTemplate},
+        Factory,
+    },
+    market::Market,
+    money::Money,
+    time::DateTime,
+};
+
+/// Rules for investors founding new factories and for unsuccessful factories shutting down.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MarketEntry {
+    /// The templates that investors may build new factories from.
+    templates: Vec<FactoryTemplate>,
+    /// Investors found a new factory if its estimated profit margin is at least this high.
+    minimum_profit_margin: f64,
+    /// The money that investors give to a new factory.
+    starting_money: Money,
+    /// The pricing strategy of new factories.
+    pricing_strategy: Box<dyn PricingStrategy>,
+    /// The offer lifetime of new factories.
+    offer_lifetime: DateTime,
+    /// Factories that lose money for more than this many consecutive hours shut down.
+    maximum_unprofitable_hours: u64,
+}
+
+impl MarketEntry {
+    pub fn new(
+        templates: impl IntoIterator<Item = FactoryTemplate>,
+        minimum_profit_margin: f64,
+        starting_money: Money,
+        pricing_strategy: Box<dyn PricingStrategy>,
+        offer_lifetime: DateTime,
+        maximum_unprofitable_hours: u64,
+    ) -> Result<Self> {
+        if !minimum_profit_margin.is_finite() {
+            return Err(Error::invalid_argument(
+                "minimum profit margin must be finite",
+            ));
+        }
+        if offer_lifetime == DateTime::ZERO {
+            return Err(Error::invalid_argument("offer lifetime must be positive"));
+        }
+        Ok(Self {
+            templates: templates.into_iter().collect(),
+            minimum_profit_margin,
+            starting_money,
+            pricing_strategy,
+            offer_lifetime,
+            maximum_unprofitable_hours,
+        })
+    }
+
+    /// The templates that investors may build new factories from.
+    pub fn templates(&self) -> &[FactoryTemplate] {
+        &self.templates
+    }
+
+    /// Returns the factories that investors found at the current market prices.
+    ///
+    /// At most one factory per template is founded at a time.
+    pub fn found_factories(&self, market: &Market) -> Result<Vec<Factory>> {
+        let mut factories = Vec::new();
+        for template in &self.templates {
+            let EstimatedProfitMargin::Margin(margin) = template.estimated_profit_margin(market)?
+            else {
+                continue;
+            };
+
+            if margin >= self.minimum_profit_margin {
+                debug!(
+                    "Founding factory with recipes {} at estimated profit margin {margin:.2}",
+                    template.recipe_names()
+                );
+                factories.push(
+                    Factory::new(template.clone(), self.starting_money)
+                        .with_pricing_strategy(self.pricing_strategy.clone())
+                        .with_offer_lifetime(self.offer_lifetime)?,
+                );
+            }
+        }
+        Ok(factories)
+    }
+
+    /// Returns true if the factory is bankrupt or has been unprofitable for too long.
+    pub fn should_shut_down(&self, factory: &Factory) -> bool {
+        factory.is_bankrupt() || factory.unprofitable_hours() > self.maximum_unprofitable_hours
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_xoshiro::Xoshiro256PlusPlus;
+
+    use crate::{
+        factory::{pricing::CostPlusHalf, template::FactoryTemplate, FactoryId},
+        market::{Market, Seller},
+        money::{ApproximateMoney, Money},
+        recipe::{ProductionRate, Recipe},
+        time::DateTime,
+        ware::{Ware, WareAmount, WareDefinition},
+        warehouse::WarehouseBatch,
+    };
+
+    use super::MarketEntry;
+
+    fn widget() -> Ware {
+        Ware::new(WareDefinition::new("market_entry_test_widget"))
+    }
+
+    /// Investors require a margin of 2.0 for a recipe that needs ten workers at a wage of ten
+    /// to produce ten widgets per hour, so the widget must sell for at least 20.
+    fn market_entry() -> MarketEntry {
+        let recipe = Recipe::new([], [WareAmount::new(widget(), 1)], ProductionRate::new(10));
+        let template = FactoryTemplate::new(recipe, Money::from(10)).unwrap();
+        MarketEntry::new(
+            [template],
+            2.0,
+            Money::from(1_000),
+            Box::new(CostPlusHalf),
+            DateTime::from_hours(1),
+            24,
+        )
+        .unwrap()
+    }
+
+    fn market_offering_widgets_at(price: u64) -> Market {
+        let mut market = Market::default();
+        market.offer(
+            WarehouseBatch::new(widget(), 5, ApproximateMoney::from(1), DateTime::ZERO),
+            Money::from(price),
+            Seller::Factory(FactoryId::from(0)),
+            DateTime::from_hours(1),
+        );
+        market.sort_offers(&mut Xoshiro256PlusPlus::seed_from_u64(0));
+        market
+    }
+
+    #[test]
+    fn founds_factory_at_minimum_profit_margin() {
+        let factories = market_entry()
+            .found_factories(&market_offering_widgets_at(20))
+            .unwrap();
+        assert_eq!(factories.len(), 1);
+        assert_eq!(factories[0].money(), Money::from(1_000));
+    }
+
+    #[test]
+    fn founds_no_factory_below_minimum_profit_margin() {
+        let factories = market_entry()
+            .found_factories(&market_offering_widgets_at(19))
+            .unwrap();
+        assert!(factories.is_empty());
+    }
+
+    #[test]
+    fn founds_no_factory_without_output_price() {
+        let mut market = Market::default();
+        market.sort_offers(&mut Xoshiro256PlusPlus::seed_from_u64(0));
+        assert!(market_entry().found_factories(&market).unwrap().is_empty());
+    }
+}
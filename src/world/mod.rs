@@ -1,50 +1,326 @@
-use std::mem;
+use std::{collections::BTreeMap, mem};
 
-use general_stable_vec::{implementation::option_vec::OptionStableVec, interface::StableVec};
-use log::debug;
-use rand::Rng;
+use general_stable_vec::{
+    implementation::option_vec::OptionStableVec,
+    interface::{StableVec, StableVecAccess},
+};
+use log::{debug, log_enabled, Level};
+use market_entry::MarketEntry;
+use rand::{seq::SliceRandom, Rng};
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    consumer::Consumer,
-    factory::{Factory, FactoryId},
-    market::Market,
+    accounting::{Account, Books},
+    consumer::{Consumer, ConsumerId},
+    error::{Error, Result},
+    factory::{template::FactoryTemplate, Factory, FactoryId},
+    labour::{self, JobOffer, JobSeeker},
+    market::{ledger::TradeLedger, Seller},
+    money::Money,
+    region::{route::Route, Region, RegionId},
     statistics::Statistics,
     time::DateTime,
+    trader::{Trader, TraderId},
+    ware::{Ware, WareAmount, WareRegistry},
 };
 
-#[derive(Debug)]
+pub mod market_entry;
+
+#[derive(Debug, Serialize, Deserialize)]
 pub struct World {
+    #[serde(with = "serde_factories")]
     factories: OptionStableVec<Factory, FactoryId>,
+    /// Factory ids are never reused, such that statistics can track factories by id.
+    next_factory_id: usize,
     consumers: Vec<Consumer>,
-    market: Market,
+    /// The wares that the factories and households of the world produce, use or buy, ordered by id.
+    wares: Vec<Ware>,
+    /// Dividends paid by factories that were not yet distributed to the households.
+    income_pool: Money,
+    /// The regions of the world, indexed by their id.
+    regions: Vec<Region>,
+    routes: Vec<Route>,
+    traders: Vec<Trader>,
+    /// The trades of the markets of all regions, if they are recorded.
+    trade_ledger: Option<TradeLedger>,
+    /// Every transfer of money, such that the money held by everyone can be checked against the books.
+    books: Books,
     time: DateTime,
+    market_entry: Option<MarketEntry>,
+    /// Whether the books are checked after every hour. This is a runtime option and not part of snapshots.
+    #[serde(skip)]
+    check_invariants: bool,
+    /// Statistics are not part of snapshots, but get re-attached after resuming.
+    #[serde(skip)]
     statistics: Vec<Box<dyn Statistics>>,
 }
 
-impl World {
-    pub fn new(
-        factories: impl IntoIterator<Item = Factory>,
-        consumers: impl IntoIterator<Item = Consumer>,
-        statistics: Vec<Box<dyn Statistics>>,
-    ) -> Self {
-        Self {
-            factories: factories.into_iter().collect(),
-            consumers: consumers.into_iter().collect(),
-            market: Default::default(),
-            time: DateTime::ZERO,
-            statistics,
+/// Builds a world from its regions, factories, households, traders and statistics collectors.
+///
+/// If no region is added, the world consists of a single region.
+#[derive(Debug, Default)]
+pub struct WorldBuilder {
+    regions: Vec<Region>,
+    routes: Vec<Route>,
+    factories: Vec<Factory>,
+    consumers: Vec<Consumer>,
+    traders: Vec<Trader>,
+    statistics: Vec<Box<dyn Statistics>>,
+    market_entry: Option<MarketEntry>,
+    record_trades: bool,
+    check_invariants: bool,
+}
+
+impl WorldBuilder {
+    /// Add a region, whose id is the number of regions added before it.
+    pub fn with_region(mut self, region: Region) -> Self {
+        self.regions.push(region);
+        self
+    }
+
+    pub fn with_route(mut self, route: Route) -> Self {
+        self.routes.push(route);
+        self
+    }
+
+    pub fn with_factory(mut self, factory: Factory) -> Self {
+        self.factories.push(factory);
+        self
+    }
+
+    pub fn with_factories(mut self, factories: impl IntoIterator<Item = Factory>) -> Self {
+        self.factories.extend(factories);
+        self
+    }
+
+    pub fn with_consumer(mut self, consumer: Consumer) -> Self {
+        self.consumers.push(consumer);
+        self
+    }
+
+    pub fn with_consumers(mut self, consumers: impl IntoIterator<Item = Consumer>) -> Self {
+        self.consumers.extend(consumers);
+        self
+    }
+
+    pub fn with_trader(mut self, trader: Trader) -> Self {
+        self.traders.push(trader);
+        self
+    }
+
+    pub fn with_statistics(mut self, statistics: Box<dyn Statistics>) -> Self {
+        self.statistics.push(statistics);
+        self
+    }
+
+    /// Allow investors to found new factories and unsuccessful factories to shut down.
+    pub fn with_market_entry(mut self, market_entry: MarketEntry) -> Self {
+        self.market_entry = Some(market_entry);
+        self
+    }
+
+    /// See [`World::enable_trade_ledger`].
+    pub fn with_trade_ledger(mut self) -> Self {
+        self.record_trades = true;
+        self
+    }
+
+    /// See [`World::enable_invariant_checks`].
+    pub fn with_invariant_checks(mut self) -> Self {
+        self.check_invariants = true;
+        self
+    }
+
+    /// Create the world at time zero, and open its books with the money of its factories, households and traders.
+    ///
+    /// Fails if a factory, household or route refers to a region that does not exist.
+    pub fn build(self) -> Result<World> {
+        let mut regions = self.regions;
+        if regions.is_empty() {
+            regions.push(Region::new("world"));
+        }
+        let check_region = |region: RegionId| {
+            if usize::from(region) < regions.len() {
+                Ok(())
+            } else {
+                Err(Error::invalid_argument(format!(
+                    "region {region} does not exist, the world has {} regions",
+                    regions.len()
+                )))
+            }
+        };
+        for factory in &self.factories {
+            check_region(factory.region())?;
+        }
+        for consumer in &self.consumers {
+            check_region(consumer.region())?;
+        }
+        for route in &self.routes {
+            let (from, to) = route.regions();
+            check_region(from)?;
+            check_region(to)?;
         }
+
+        // Wares with the same id but different definitions cannot be told apart by their id.
+        let mut wares = WareRegistry::new();
+        let recipes = self
+            .factories
+            .iter()
+            .map(Factory::template)
+            .chain(self.market_entry.iter().flat_map(MarketEntry::templates))
+            .flat_map(FactoryTemplate::recipes);
+        for recipe in recipes {
+            for ware_amount in recipe.inputs().iter().chain(recipe.outputs()) {
+                wares.insert(ware_amount.ware().clone())?;
+            }
+        }
+        for consumer in &self.consumers {
+            for (ware, _) in consumer.basket() {
+                wares.insert(ware.clone())?;
+            }
+        }
+
+        let factories: OptionStableVec<_, _> = self.factories.into_iter().collect();
+        let consumers = self.consumers;
+        let traders = self.traders;
+
+        let mut books = Books::default();
+        for (factory_id, factory) in factories.iter() {
+            books.transfer(
+                Account::OpeningBalance,
+                Account::FactoryCash(factory_id),
+                factory.money(),
+            )?;
+        }
+        for (consumer_id, consumer) in consumers.iter().enumerate() {
+            books.transfer(
+                Account::OpeningBalance,
+                Account::HouseholdSavings(consumer_id.into()),
+                consumer.savings(),
+            )?;
+        }
+        for (trader_id, trader) in traders.iter().enumerate() {
+            books.transfer(
+                Account::OpeningBalance,
+                Account::TraderCash(trader_id.into()),
+                trader.money(),
+            )?;
+        }
+
+        Ok(World {
+            next_factory_id: factories.len(),
+            factories,
+            consumers,
+            wares: wares.wares().cloned().collect(),
+            income_pool: Money::ZERO,
+            regions,
+            routes: self.routes,
+            traders,
+            trade_ledger: self.record_trades.then(Default::default),
+            books,
+            time: DateTime::ZERO,
+            market_entry: self.market_entry,
+            check_invariants: self.check_invariants,
+            statistics: self.statistics,
+        })
+    }
+}
+
+impl World {
+    pub fn builder() -> WorldBuilder {
+        WorldBuilder::default()
     }
 
     pub fn factories(&self) -> impl Iterator<Item = (FactoryId, &Factory)> {
         self.factories.iter()
     }
 
+    /// The wares that the factories and households of the world produce, use or buy, ordered by id.
+    pub fn wares(&self) -> &[Ware] {
+        &self.wares
+    }
+
+    pub fn consumers(&self) -> impl Iterator<Item = (ConsumerId, &Consumer)> {
+        self.consumers
+            .iter()
+            .enumerate()
+            .map(|(consumer_id, consumer)| (consumer_id.into(), consumer))
+    }
+
+    pub fn regions(&self) -> impl Iterator<Item = (RegionId, &Region)> {
+        self.regions
+            .iter()
+            .enumerate()
+            .map(|(region_id, region)| (region_id.into(), region))
+    }
+
+    pub fn region(&self, region_id: RegionId) -> Option<&Region> {
+        self.regions.get(usize::from(region_id))
+    }
+
+    pub fn routes(&self) -> &[Route] {
+        &self.routes
+    }
+
+    pub fn traders(&self) -> impl Iterator<Item = (TraderId, &Trader)> {
+        self.traders
+            .iter()
+            .enumerate()
+            .map(|(trader_id, trader)| (trader_id.into(), trader))
+    }
+
+    /// The record of all trades, if it is enabled.
+    pub fn trade_ledger(&self) -> Option<&TradeLedger> {
+        self.trade_ledger.as_ref()
+    }
+
+    /// The money held by all factories, households, traders and markets.
+    pub fn total_money(&self) -> Result<Money> {
+        self.factories
+            .iter_elements()
+            .map(Factory::money)
+            .chain(self.consumers.iter().map(Consumer::savings))
+            .chain(self.traders.iter().map(Trader::money))
+            .chain([self.income_pool])
+            .map(Ok)
+            .chain(self.regions.iter().map(|region| region.market().money()))
+            .try_fold(Money::ZERO, |sum, money| sum.checked_add(money?))
+    }
+
     pub fn time(&self) -> DateTime {
         self.time
     }
 
-    pub fn advance_hour(&mut self, rng: &mut impl Rng) {
+    /// Record every trade from now on in the trade ledger.
+    ///
+    /// The ledger grows with every trade and is part of snapshots, so it is disabled by default.
+    pub fn enable_trade_ledger(&mut self) {
+        self.trade_ledger.get_or_insert_with(Default::default);
+    }
+
+    /// Check after every hour that the books match the money held by everyone.
+    ///
+    /// Advancing the world fails as soon as an invariant is violated.
+    pub fn enable_invariant_checks(&mut self) {
+        self.check_invariants = true;
+    }
+
+    /// The attached statistics collectors.
+    pub fn statistics(&self) -> impl Iterator<Item = &dyn Statistics> {
+        self.statistics.iter().map(|statistics| statistics.as_ref())
+    }
+
+    /// Attach a statistics collector.
+    ///
+    /// If the world has already advanced, the current state is collected immediately.
+    pub fn attach_statistics(&mut self, mut statistics: Box<dyn Statistics>) {
+        if self.time != DateTime::ZERO {
+            statistics.collect(self);
+        }
+        self.statistics.push(statistics);
+    }
+
+    pub fn advance_hour(&mut self, rng: &mut impl Rng) -> Result<()> {
         debug!("Advancing world by one hour");
 
         // Collect initial statistics on first update.
@@ -53,39 +329,439 @@ impl World {
         }
 
         // Advance time.
-        self.time.increment();
+        self.time.increment()?;
 
         // Update
-        // 1. Factories produce if possible.
+        // 1. Factories hire workers and pay their wages, then they produce if possible.
+        //    Dividends of the previous hour are distributed to the households.
+        self.run_labour_market(rng)?;
+        self.pay_wages()?;
         for factory in self.factories.iter_elements_mut() {
-            factory.produce_one_hour();
+            factory.produce_one_hour(self.time)?;
         }
+        self.distribute_income()?;
 
-        // 2. Outputs get offered on the market, or reused as inputs.
+        // 2. Expired offers are returned to their factories and traders, and spoiled wares are written off.
+        //    Then outputs get offered on the market of their region, or reused as inputs.
+        //    Traders offer the wares that arrived with their shipments.
+        let mut expired_offers = BTreeMap::new();
+        for (region_id, region) in self.regions.iter_mut().enumerate() {
+            for (seller, offers) in region.market_mut().expire_offers(self.time) {
+                expired_offers.insert((RegionId::from(region_id), seller), offers);
+            }
+        }
         for (factory_id, factory) in self.factories.iter_mut() {
-            factory.reuse_inputs();
-            factory.offer_outputs(&mut self.market, factory_id);
+            factory.restock_expired_offers(
+                expired_offers
+                    .remove(&(factory.region(), Seller::Factory(factory_id)))
+                    .unwrap_or_default(),
+            )?;
+            factory.spoil_wares(self.time);
+            factory.reuse_inputs()?;
+            let market = self.regions[usize::from(factory.region())].market_mut();
+            factory.offer_outputs(market, factory_id, self.time)?;
+        }
+        for (trader_id, trader) in self.traders.iter_mut().enumerate() {
+            let trader_id = TraderId::from(trader_id);
+            for region_id in (0..self.regions.len()).map(RegionId::from) {
+                trader.restock_expired_offers(
+                    region_id,
+                    expired_offers
+                        .remove(&(region_id, Seller::Trader(trader_id)))
+                        .unwrap_or_default(),
+                )?;
+            }
+            trader.receive_shipments(self.time)?;
+            trader.spoil_wares(self.time);
+            for (region_id, region) in self.regions.iter_mut().enumerate() {
+                trader.offer_wares(region_id.into(), region.market_mut(), trader_id, self.time)?;
+            }
+        }
+
+        for region in &mut self.regions {
+            region.market_mut().sort_offers(rng);
+            debug!("{}: {}", region.name(), region.market());
         }
 
-        self.market.sort_offers(rng);
-        debug!("{}", self.market);
+        // 3. Investors found new factories where the market promises profits.
+        self.found_factories()?;
 
-        // 3. Inputs are bought from the market (in random order).
-        //    First, factories buy required inputs, and then consumers consume.
+        // 4. Households earn their external income, and factories plan their production at the current prices.
+        //    Then factories bid for their inputs, traders for their shipments and households for their demand,
+        //    and the markets are cleared, such that the bidding order does not matter.
+        for (consumer_id, consumer) in self.consumers.iter_mut().enumerate() {
+            self.books.transfer(
+                Account::ExternalIncome,
+                Account::HouseholdSavings(consumer_id.into()),
+                consumer.earn_income()?,
+            )?;
+        }
         for factory in self.factories.iter_elements_mut() {
-            factory.buy_inputs(&mut self.market);
+            factory.plan_production(self.regions[usize::from(factory.region())].market())?;
+        }
+        for (factory_id, factory) in self.factories.iter_mut() {
+            let region_id = factory.region();
+            self.books.transfer(
+                Account::FactoryCash(factory_id),
+                Account::MarketEscrow(region_id),
+                factory.bid_for_inputs(
+                    self.regions[usize::from(region_id)].market_mut(),
+                    factory_id,
+                )?,
+            )?;
         }
-        for consumer in &mut self.consumers {
-            consumer.consume(&mut self.market);
+        for (trader_id, trader) in self.traders.iter_mut().enumerate() {
+            let escrowed_money = trader.bid_for_shipments(
+                &mut self.regions,
+                &self.routes,
+                &self.wares,
+                trader_id.into(),
+            )?;
+            for (region_id, money) in escrowed_money {
+                self.books.transfer(
+                    Account::TraderCash(trader_id.into()),
+                    Account::MarketEscrow(region_id),
+                    money,
+                )?;
+            }
+        }
+        for (consumer_id, consumer) in self.consumers.iter_mut().enumerate() {
+            let region_id = consumer.region();
+            self.books.transfer(
+                Account::HouseholdSavings(consumer_id.into()),
+                Account::MarketEscrow(region_id),
+                consumer.bid(
+                    self.regions[usize::from(region_id)].market_mut(),
+                    consumer_id.into(),
+                )?,
+            )?;
+        }
+        for (region_id, region) in self.regions.iter_mut().enumerate() {
+            let region_id = RegionId::from(region_id);
+            region.market_mut().clear(rng)?;
+            if let Some(trade_ledger) = &mut self.trade_ledger {
+                trade_ledger.record(self.time, region_id, region.market().trades())?;
+            }
+            if log_enabled!(Level::Debug) {
+                for ware in &self.wares {
+                    if let Some(price) = region.market().clearing_price(ware) {
+                        let volume = region.market().traded_volume(ware);
+                        debug!(
+                            "Traded {} at {price} in {}",
+                            WareAmount::new(ware.clone(), volume),
+                            region.name()
+                        );
+                    }
+                }
+            }
         }
 
-        // 4. Money is returned from the market to the factories.
+        // 5. Purchases are delivered, and money is returned from the markets to the factories and traders.
+        //    Factories pay the holding costs of their warehouses, and traders ship their purchases.
         for (factory_id, factory) in self.factories.iter_mut() {
-            factory.collect_money(&mut self.market, factory_id);
+            let cash = Account::FactoryCash(factory_id);
+            let escrow = Account::MarketEscrow(factory.region());
+            let market = self.regions[usize::from(factory.region())].market_mut();
+            self.books
+                .transfer(escrow, cash, factory.collect_purchases(market, factory_id)?)?;
+            self.books
+                .transfer(escrow, cash, factory.collect_money(market, factory_id)?)?;
+            factory.record_sales(market);
+            self.books.transfer(
+                cash,
+                Account::StorageOperators,
+                factory.pay_holding_costs()?,
+            )?;
+            factory.close_hour()?;
+            let dividends = factory.pay_dividends()?;
+            self.income_pool = self.income_pool.checked_add(dividends)?;
+            self.books.transfer(cash, Account::IncomePool, dividends)?;
+        }
+        for (trader_id, trader) in self.traders.iter_mut().enumerate() {
+            let cash = Account::TraderCash(trader_id.into());
+            for (region_id, region) in self.regions.iter_mut().enumerate() {
+                let region_id = RegionId::from(region_id);
+                let escrow = Account::MarketEscrow(region_id);
+                let market = region.market_mut();
+                let (refund, transport_cost) = trader.collect_purchases(
+                    region_id,
+                    market,
+                    &self.routes,
+                    trader_id.into(),
+                    self.time,
+                )?;
+                self.books.transfer(escrow, cash, refund)?;
+                self.books
+                    .transfer(cash, Account::Carriers, transport_cost)?;
+                self.books.transfer(
+                    escrow,
+                    cash,
+                    trader.collect_money(market, trader_id.into())?,
+                )?;
+                trader.record_sales(region_id, market);
+            }
         }
+        for (consumer_id, consumer) in self.consumers.iter_mut().enumerate() {
+            let region_id = consumer.region();
+            self.books.transfer(
+                Account::MarketEscrow(region_id),
+                Account::HouseholdSavings(consumer_id.into()),
+                consumer.consume(
+                    self.regions[usize::from(region_id)].market_mut(),
+                    consumer_id.into(),
+                )?,
+            )?;
+        }
+
+        // 6. Bankrupt and unprofitable factories shut down.
+        self.shut_down_factories()?;
 
-        // 5. Collect statistics.
+        debug!("Total money: {}", self.total_money()?);
+        if self.check_invariants {
+            self.check_books()?;
+        }
+
+        // 7. Collect statistics.
         self.collect_statistics();
+        Ok(())
+    }
+
+    /// Let factories adjust their workforce and match job seekers with job offers.
+    fn run_labour_market(&mut self, rng: &mut impl Rng) -> Result<()> {
+        // Factories adjust their wages, and post job offers or plan lay-offs.
+        let mut job_offers = Vec::new();
+        let mut layoffs = BTreeMap::new();
+        for (factory_id, factory) in self.factories.iter_mut() {
+            factory.adjust_wage()?;
+            let desired_workers = factory.desired_workers()?;
+            if factory.workers() > desired_workers {
+                layoffs.insert(factory_id, factory.workers() - desired_workers);
+            }
+            job_offers.push(JobOffer::new(
+                factory_id,
+                desired_workers.saturating_sub(factory.workers()),
+                factory.wage(),
+            ));
+        }
+
+        // Workers are laid off in random order, or quit if their wage fell below their reservation wage.
+        // Everyone else looks for a better paid job.
+        let mut consumer_ids: Vec<_> = (0..self.consumers.len()).collect();
+        consumer_ids.shuffle(rng);
+        let mut job_seekers = Vec::new();
+        for consumer_id in consumer_ids {
+            let consumer = &mut self.consumers[consumer_id];
+            let minimum_wage = if let Some(employer) = consumer.employer() {
+                let factory = factory_mut(&mut self.factories, employer)?;
+                let layoff = layoffs.get_mut(&employer).filter(|layoffs| **layoffs > 0);
+                if let Some(layoffs) = layoff {
+                    *layoffs -= 1;
+                } else if factory.wage() >= consumer.reservation_wage() {
+                    job_seekers.push(JobSeeker::new(
+                        consumer_id.into(),
+                        factory.wage().checked_add(Money::from(1))?,
+                    ));
+                    continue;
+                }
+
+                factory.lose_worker();
+                consumer.leave_job();
+                consumer.reservation_wage()
+            } else {
+                consumer.reservation_wage()
+            };
+            job_seekers.push(JobSeeker::new(consumer_id.into(), minimum_wage));
+        }
+
+        // Households only work in their own region.
+        let mut regional_labour_markets: BTreeMap<RegionId, (Vec<_>, Vec<_>)> = BTreeMap::new();
+        for job_offer in &job_offers {
+            let region_id = factory_mut(&mut self.factories, job_offer.factory_id())?.region();
+            regional_labour_markets
+                .entry(region_id)
+                .or_default()
+                .0
+                .push(*job_offer);
+        }
+        for job_seeker in job_seekers {
+            let region_id = self.consumers[usize::from(job_seeker.consumer_id())].region();
+            regional_labour_markets
+                .entry(region_id)
+                .or_default()
+                .1
+                .push(job_seeker);
+        }
+        let mut hires = Vec::new();
+        for (job_offers, job_seekers) in regional_labour_markets.into_values() {
+            hires.extend(labour::match_workers(job_offers, job_seekers, rng));
+        }
+        debug!("Matched {} job seekers with job offers", hires.len());
+        let mut hired_workers: BTreeMap<FactoryId, u64> = BTreeMap::new();
+        for (consumer_id, factory_id) in hires {
+            let consumer = &mut self.consumers[usize::from(consumer_id)];
+            if let Some(previous_employer) = consumer.employer() {
+                factory_mut(&mut self.factories, previous_employer)?.lose_worker();
+            }
+            consumer.take_job(factory_id);
+            *hired_workers.entry(factory_id).or_default() += 1;
+        }
+        for job_offer in job_offers {
+            factory_mut(&mut self.factories, job_offer.factory_id())?.hire(
+                job_offer.positions(),
+                hired_workers
+                    .get(&job_offer.factory_id())
+                    .copied()
+                    .unwrap_or(0),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Factories pay the wages of their workers for the hour.
+    fn pay_wages(&mut self) -> Result<()> {
+        for (consumer_id, consumer) in self.consumers.iter_mut().enumerate() {
+            if let Some(employer) = consumer.employer() {
+                let wage = factory_mut(&mut self.factories, employer)?.pay_wage()?;
+                consumer.earn(wage)?;
+                self.books.transfer(
+                    Account::FactoryCash(employer),
+                    Account::HouseholdSavings(consumer_id.into()),
+                    wage,
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Distribute the income pool evenly among the households.
+    ///
+    /// The remainder that cannot be split evenly stays in the pool for the next hour.
+    fn distribute_income(&mut self) -> Result<()> {
+        if self.consumers.is_empty() {
+            return Ok(());
+        }
+
+        let income_per_consumer = self.income_pool.checked_div(self.consumers.len() as u64)?;
+        for (consumer_id, consumer) in self.consumers.iter_mut().enumerate() {
+            consumer.earn(income_per_consumer)?;
+            self.income_pool = self.income_pool.checked_sub(income_per_consumer)?;
+            self.books.transfer(
+                Account::IncomePool,
+                Account::HouseholdSavings(consumer_id.into()),
+                income_per_consumer,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Found new factories with money from investors.
+    fn found_factories(&mut self) -> Result<()> {
+        let Some(market_entry) = &self.market_entry else {
+            return Ok(());
+        };
+
+        let mut factories = Vec::new();
+        for (region_id, region) in self.regions.iter().enumerate() {
+            for factory in market_entry.found_factories(region.market())? {
+                factories.push(factory.with_region(region_id.into()));
+            }
+        }
+        for factory in factories {
+            let factory_id = FactoryId::from(self.next_factory_id);
+            self.next_factory_id += 1;
+            debug!(
+                "Founded factory {factory_id} in region {}",
+                factory.region()
+            );
+            self.books.transfer(
+                Account::Investors,
+                Account::FactoryCash(factory_id),
+                factory.money(),
+            )?;
+            self.factories
+                .insert_at_arbitrary_index(factory_id, factory)
+                .map_err(|error| Error::invariant_violation(error.to_string()))?;
+        }
+        Ok(())
+    }
+
+    /// Shut down unsuccessful factories, whose investors take their money out of the world.
+    fn shut_down_factories(&mut self) -> Result<()> {
+        let Some(market_entry) = &self.market_entry else {
+            return Ok(());
+        };
+
+        let shut_down_factory_ids: Vec<_> = self
+            .factories
+            .iter()
+            .filter(|(_, factory)| market_entry.should_shut_down(factory))
+            .map(|(factory_id, _)| factory_id)
+            .collect();
+        for factory_id in shut_down_factory_ids {
+            debug!("Shutting down factory {factory_id}");
+            let mut factory = self
+                .factories
+                .remove(factory_id)
+                .map_err(|error| Error::invariant_violation(error.to_string()))?;
+            factory.withdraw_offers(self.regions[usize::from(factory.region())].market_mut())?;
+            self.books.transfer(
+                Account::FactoryCash(factory_id),
+                Account::Investors,
+                factory.money(),
+            )?;
+            for consumer in &mut self.consumers {
+                if consumer.employer() == Some(factory_id) {
+                    consumer.leave_job();
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Check that every account holds exactly the money that its owner holds,
+    /// such that no money was created or destroyed without being booked.
+    fn check_books(&self) -> Result<()> {
+        if !self.books.is_balanced()? {
+            return Err(Error::invariant_violation(format!(
+                "the debits of the books do not match their credits in hour {}",
+                self.time
+            )));
+        }
+
+        let mut holdings: BTreeMap<Account, Money> = BTreeMap::new();
+        for (factory_id, factory) in self.factories.iter() {
+            holdings.insert(Account::FactoryCash(factory_id), factory.money());
+        }
+        for (consumer_id, consumer) in self.consumers.iter().enumerate() {
+            holdings.insert(
+                Account::HouseholdSavings(consumer_id.into()),
+                consumer.savings(),
+            );
+        }
+        for (trader_id, trader) in self.traders.iter().enumerate() {
+            holdings.insert(Account::TraderCash(trader_id.into()), trader.money());
+        }
+        for (region_id, region) in self.regions.iter().enumerate() {
+            holdings.insert(
+                Account::MarketEscrow(region_id.into()),
+                region.market().money()?,
+            );
+        }
+        holdings.insert(Account::IncomePool, self.income_pool);
+
+        // Closed factories hold no money, so their accounts must be empty.
+        for account in self.books.accounts().filter(Account::is_internal) {
+            holdings.entry(account).or_insert(Money::ZERO);
+        }
+        for (account, money) in holdings {
+            if self.books.balance(account) != Some(money) {
+                return Err(Error::invariant_violation(format!(
+                    "the {account} does not match the books in hour {}",
+                    self.time
+                )));
+            }
+        }
+        Ok(())
     }
 
     fn collect_statistics(&mut self) {
@@ -97,15 +773,356 @@ impl World {
         self.statistics = statistics;
     }
 
-    pub fn advance_time(&mut self, time: DateTime, rng: &mut impl Rng) {
+    pub fn advance_time(&mut self, time: DateTime, rng: &mut impl Rng) -> Result<()> {
         for _ in 0..time.into_hours() {
-            self.advance_hour(rng);
+            self.advance_hour(rng)?;
         }
+        Ok(())
     }
 
-    pub fn finalise_statistics(&self) {
+    pub fn finalise_statistics(&self) -> Result<()> {
         for statistics in &self.statistics {
-            statistics.finalise();
+            statistics.finalise()?;
         }
+        Ok(())
+    }
+}
+
+/// Look up a factory that must exist, such as the employer of a household.
+fn factory_mut(
+    factories: &mut OptionStableVec<Factory, FactoryId>,
+    factory_id: FactoryId,
+) -> Result<&mut Factory> {
+    factories
+        .get_mut(factory_id)
+        .map_err(|error| Error::invariant_violation(format!("factory {factory_id}: {error}")))
+}
+
+/// Serialise the factories as a list of ids and factories, such that ids stay stable.
+mod serde_factories {
+    use general_stable_vec::{implementation::option_vec::OptionStableVec, interface::StableVec};
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    use crate::factory::{Factory, FactoryId};
+
+    pub fn serialize<S: Serializer>(
+        factories: &OptionStableVec<Factory, FactoryId>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(factories.iter())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<OptionStableVec<Factory, FactoryId>, D::Error> {
+        let mut factories = OptionStableVec::new();
+        for (factory_id, factory) in Vec::<(FactoryId, Factory)>::deserialize(deserializer)? {
+            factories
+                .insert_at_arbitrary_index(factory_id, factory)
+                .map_err(D::Error::custom)?;
+        }
+        Ok(factories)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_xoshiro::Xoshiro256PlusPlus;
+
+    use crate::{
+        accounting::Account,
+        consumer::{Consumer, ConsumerId},
+        factory::{pricing::CostPlusHalf, template::FactoryTemplate, Factory},
+        market::Seller,
+        money::Money,
+        recipe::{ProductionRate, Recipe},
+        region::{route::Route, Region, RegionId},
+        scenario,
+        time::DateTime,
+        trader::{Trader, TraderId},
+        ware::{Ware, WareAmount, WareDefinition},
+        warehouse::template::WarehouseTemplate,
+    };
+
+    use super::{market_entry::MarketEntry, World};
+
+    fn factory_money_series(seed: u64) -> Vec<Vec<Money>> {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(seed);
+        let mut world = scenario::parse(
+            scenario::DEFAULT_SCENARIO,
+            scenario::DEFAULT_SCENARIO_FILE,
+            &mut rng,
+        )
+        .unwrap();
+
+        (0..200)
+            .map(|_| {
+                world
+                    .advance_time(DateTime::from_hours(1), &mut rng)
+                    .unwrap();
+                world
+                    .factories()
+                    .map(|(_, factory)| factory.money())
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn same_seed_produces_identical_runs() {
+        assert_eq!(factory_money_series(42), factory_money_series(42));
+        assert_eq!(factory_money_series(7), factory_money_series(7));
+    }
+
+    /// A world in which a single factory produces gadgets that nobody buys and that cost 1 per hour to store,
+    /// while factories that make a loss for more than ten hours shut down.
+    fn world_with_gadget_factory(starting_money: Money, wage: Money) -> World {
+        let gadget = Ware::new(WareDefinition::new("world_test_gadget"));
+        let trinket = Ware::new(WareDefinition::new("world_test_trinket"));
+        let recipe = Recipe::new([], [WareAmount::new(gadget, 1)], ProductionRate::new(1));
+        let template = FactoryTemplate::new(recipe, wage)
+            .unwrap()
+            .with_output_storage(WarehouseTemplate::new().with_holding_cost(1.0).unwrap());
+        World::builder()
+            .with_factory(Factory::new(template, starting_money))
+            .with_consumer(
+                Consumer::new(vec![(trinket, 1.0)], 1.0)
+                    .unwrap()
+                    .with_reservation_wage(Money::from(1)),
+            )
+            .with_market_entry(
+                MarketEntry::new(
+                    [],
+                    1.0,
+                    Money::from(1_000),
+                    Box::new(CostPlusHalf),
+                    DateTime::from_hours(1),
+                    10,
+                )
+                .unwrap(),
+            )
+            .with_invariant_checks()
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn check_books_reports_holdings_that_diverge_from_their_accounts() {
+        let mut world = world_with_gadget_factory(Money::from(1_000), Money::from(10));
+        world.check_books().unwrap();
+
+        world.income_pool = Money::from(1);
+        assert!(world.check_books().is_err());
+        world.income_pool = Money::ZERO;
+
+        world
+            .books
+            .transfer(
+                Account::IncomePool,
+                Account::HouseholdSavings(ConsumerId::from(0)),
+                Money::from(5),
+            )
+            .unwrap();
+        assert!(world.check_books().is_err());
+    }
+
+    #[test]
+    fn bankrupt_factory_returns_its_money_to_investors() {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(0);
+        let mut world = world_with_gadget_factory(Money::from(50), Money::from(100));
+        assert!(world.factories().all(|(_, factory)| factory.is_bankrupt()));
+
+        world.advance_hour(&mut rng).unwrap();
+        assert_eq!(world.factories().count(), 0);
+        assert_eq!(
+            world.books.balance(Account::Investors),
+            Some(Money::from(50))
+        );
+    }
+
+    #[test]
+    fn persistently_unprofitable_factory_shuts_down() {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(0);
+        let mut world = world_with_gadget_factory(Money::from(1_000), Money::from(10));
+
+        world
+            .advance_time(DateTime::from_hours(10), &mut rng)
+            .unwrap();
+        let (_, factory) = world.factories().next().unwrap();
+        assert_eq!(factory.unprofitable_hours(), 10);
+        let remaining_money = factory.money();
+
+        world.advance_hour(&mut rng).unwrap();
+        assert_eq!(world.factories().count(), 0);
+        let returned_money = world.books.balance(Account::Investors).unwrap();
+        assert!(returned_money > Money::ZERO && returned_money < remaining_money);
+        let holding_costs = world.books.balance(Account::StorageOperators).unwrap();
+        assert!(holding_costs > Money::ZERO);
+        assert_eq!(
+            world
+                .total_money()
+                .unwrap()
+                .checked_add(returned_money)
+                .unwrap()
+                .checked_add(holding_costs)
+                .unwrap(),
+            Money::from(1_000)
+        );
+    }
+
+    /// A factory that produces the given amount of the given ware per worker and hour, in the given region.
+    fn regional_factory(ware: Ware, amount: u64, wage: u64, region: RegionId) -> Factory {
+        let recipe = Recipe::new([], [WareAmount::new(ware, amount)], ProductionRate::new(1));
+        Factory::new(
+            FactoryTemplate::new(recipe, Money::from(wage)).unwrap(),
+            Money::from(1_000),
+        )
+        .with_region(region)
+    }
+
+    /// A household of the given region that works for any wage and spends its income on the given ware.
+    fn regional_household(ware: Ware, region: RegionId) -> Consumer {
+        Consumer::new(vec![(ware, 1.0)], 1.0)
+            .unwrap()
+            .with_reservation_wage(Money::from(1))
+            .with_hourly_income(Money::from(50))
+            .with_region(region)
+    }
+
+    #[test]
+    fn traders_ship_wares_to_the_region_where_they_sell_for_more() {
+        let melon = Ware::new(WareDefinition::new("world_test_melon"));
+        let nut = Ware::new(WareDefinition::new("world_test_nut"));
+        let (valley, city) = (RegionId::from(0), RegionId::from(1));
+        let travel_time = DateTime::from_hours(3);
+        let mut world = World::builder()
+            .with_region(Region::new("valley"))
+            .with_region(Region::new("city"))
+            .with_route(Route::new(valley, city, 100, 0.5, travel_time).unwrap())
+            // Melons cost 1 to grow in the valley, but 20 in the city.
+            .with_factory(regional_factory(melon.clone(), 10, 10, valley))
+            .with_factory(regional_factory(melon.clone(), 1, 20, city))
+            // Nobody in the valley buys melons.
+            .with_consumer(regional_household(nut, valley))
+            .with_consumer(regional_household(melon.clone(), city))
+            .with_trader(Trader::new(Money::from(1_000)))
+            .with_invariant_checks()
+            .build()
+            .unwrap();
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(0);
+        let shipments_of_trader =
+            |world: &World| world.traders().next().unwrap().1.shipments().to_vec();
+
+        world.advance_hour(&mut rng).unwrap();
+        let shipments = shipments_of_trader(&world);
+        assert_eq!(shipments.len(), 1);
+        assert_eq!(shipments[0].from(), valley);
+        assert_eq!(shipments[0].to(), city);
+        assert_eq!(
+            shipments[0].arrives_at(),
+            world.time().checked_add(travel_time).unwrap()
+        );
+        let (_, trader) = world.traders().next().unwrap();
+        assert_eq!(trader.stock_in_transit()[&melon].amount(), 10);
+        assert!(trader.stock(city).is_empty());
+
+        // The first shipment stays in transit for the travel time, and is then offered in the city.
+        let amount_sold_by_trader = |world: &World| -> u64 {
+            world
+                .region(city)
+                .unwrap()
+                .market()
+                .trades()
+                .iter()
+                .filter(|trade| trade.seller() == Seller::Trader(TraderId::from(0)))
+                .map(|trade| trade.amount())
+                .sum()
+        };
+        world
+            .advance_time(DateTime::from_hours(2), &mut rng)
+            .unwrap();
+        let (_, trader) = world.traders().next().unwrap();
+        assert!(trader.stock(city).is_empty());
+        assert_eq!(amount_sold_by_trader(&world), 0);
+        world.advance_hour(&mut rng).unwrap();
+        assert!(shipments_of_trader(&world)
+            .iter()
+            .all(|shipment| shipment.arrives_at() > world.time()));
+        let (_, trader) = world.traders().next().unwrap();
+        let unsold_amount = trader
+            .stock(city)
+            .get(&melon)
+            .map_or(0, |stock_level| stock_level.amount());
+        assert_eq!(unsold_amount + amount_sold_by_trader(&world), 10);
+
+        // The money the trader paid for the transport left the world through the carriers.
+        world
+            .advance_time(DateTime::from_hours(10), &mut rng)
+            .unwrap();
+        let (trader_id, trader) = world.traders().next().unwrap();
+        assert_eq!(trader_id, TraderId::from(0));
+        assert_eq!(
+            world.books.balance(Account::TraderCash(trader_id)),
+            Some(trader.money())
+        );
+        assert!(world.books.balance(Account::Carriers).unwrap() > Money::ZERO);
+    }
+
+    #[test]
+    fn households_only_work_in_their_own_region() {
+        let pepper = Ware::new(WareDefinition::new("world_test_pepper"));
+        let (valley, city) = (RegionId::from(0), RegionId::from(1));
+        let employees = |city_households: usize| {
+            let mut world = World::builder()
+                .with_region(Region::new("valley"))
+                .with_region(Region::new("city"))
+                .with_factory(regional_factory(pepper.clone(), 1, 10, city))
+                .with_consumers((0..3).map(|_| regional_household(pepper.clone(), valley)))
+                .with_consumers(
+                    (0..city_households).map(|_| regional_household(pepper.clone(), city)),
+                )
+                .with_invariant_checks()
+                .build()
+                .unwrap();
+            world
+                .advance_hour(&mut Xoshiro256PlusPlus::seed_from_u64(0))
+                .unwrap();
+            let (factory_id, factory) = world.factories().next().unwrap();
+            assert_eq!(factory.workers(), city_households.min(1) as u64);
+            world
+                .consumers()
+                .filter(|(_, consumer)| consumer.employer() == Some(factory_id))
+                .map(|(_, consumer)| consumer.region())
+                .collect::<Vec<_>>()
+        };
+
+        // The valley households do not take the job in the city, even if nobody else does.
+        assert_eq!(employees(0), []);
+        assert_eq!(employees(1), [city]);
+    }
+
+    #[test]
+    fn wares_with_the_same_id_must_have_the_same_definition() {
+        let pepper = Ware::new(WareDefinition::new("world_test_pepper"));
+        let hot_pepper =
+            Ware::new(WareDefinition::new("world_test_pepper").with_name("hot pepper"));
+        let builder = || {
+            World::builder().with_factory(regional_factory(
+                pepper.clone(),
+                1,
+                10,
+                RegionId::from(0),
+            ))
+        };
+
+        assert!(builder()
+            .with_consumer(regional_household(pepper.clone(), RegionId::from(0)))
+            .build()
+            .is_ok());
+        assert!(builder()
+            .with_consumer(regional_household(hot_pepper, RegionId::from(0)))
+            .build()
+            .is_err());
     }
 }